@@ -0,0 +1,311 @@
+use std::collections::VecDeque;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::{Direction, Point};
+
+/// All four movement directions, in the order the autopilot considers them.
+pub const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+/// How the autopilot chooses between moves that are equally good. Either way it keeps
+/// going straight if it can, so it doesn't zigzag for no reason.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum TieBreak {
+    // Straight on, then a right turn, then a left one.
+    #[default]
+    RightHand,
+    // Straight on, then a left turn, then a right one.
+    LeftHand,
+}
+
+/// Returns the direction a quarter turn clockwise from `dir`.
+fn turn_right(dir: Direction) -> Direction {
+    match dir {
+        Direction::Up => Direction::Right,
+        Direction::Right => Direction::Down,
+        Direction::Down => Direction::Left,
+        Direction::Left => Direction::Up,
+    }
+}
+
+/// Returns all four directions, most preferred first, for a snake heading `heading`:
+/// straight on, the two turns in the order `tie_break` gives, then straight back.
+pub fn preference_order(heading: Direction, tie_break: TieBreak) -> [Direction; 4] {
+    let right = turn_right(heading);
+    let left = right.opposite();
+    let back = heading.opposite();
+    match tie_break {
+        TieBreak::RightHand => [heading, right, left, back],
+        TieBreak::LeftHand => [heading, left, right, back],
+    }
+}
+
+/// Picks the lowest-scoring of `candidates` (each a move and its score), taking the
+/// one earliest in `preference_order` when several share the lowest score.
+///
+/// Returns None if there are no candidates.
+pub fn best_move(candidates: &[(Direction, u32)], heading: Direction, tie_break: TieBreak) -> Option<Direction> {
+    let order = preference_order(heading, tie_break);
+    let rank = |dir: Direction| order.iter().position(|&d| d == dir);
+    candidates
+        .iter()
+        .min_by_key(|&&(dir, score)| (score, rank(dir)))
+        .map(|&(dir, _)| dir)
+}
+
+/// Returns the cell one step away from `p` in direction `dir` (no bounds checking).
+pub fn step(p: Point, dir: Direction) -> Point {
+    match dir {
+        Direction::Up => Point { x: p.x, y: p.y - 1 },
+        Direction::Down => Point { x: p.x, y: p.y + 1 },
+        Direction::Left => Point { x: p.x - 1, y: p.y },
+        Direction::Right => Point { x: p.x + 1, y: p.y },
+    }
+}
+
+/// Returns true if `p` lies on a grid of the given dimensions.
+pub fn in_bounds(p: Point, grid_width: i32, grid_height: i32) -> bool {
+    p.x >= 0 && p.x < grid_width && p.y >= 0 && p.y < grid_height
+}
+
+/// Returns the direction that moves from `from` to the orthogonally adjacent cell `to`.
+pub fn direction_between(from: Point, to: Point) -> Option<Direction> {
+    DIRECTIONS.into_iter().find(|&dir| step(from, dir) == to)
+}
+
+/// Finds a shortest path from `start` to `goal` with a breadth-first search.
+///
+/// Cells for which `blocked` returns true are treated as walls. The returned path
+/// begins with `start` and ends with `goal`, or is None if the goal is unreachable.
+pub fn find_path<F>(
+    start: Point,
+    goal: Point,
+    grid_width: i32,
+    grid_height: i32,
+    blocked: F,
+) -> Option<Vec<Point>>
+where
+    F: Fn(Point) -> bool,
+{
+    let index = |p: Point| (p.y * grid_width + p.x) as usize;
+    // For each visited cell, the cell we reached it from.
+    let mut came_from: Vec<Option<Point>> = vec![None; (grid_width * grid_height) as usize];
+    let mut visited = vec![false; came_from.len()];
+    let mut queue = VecDeque::new();
+
+    visited[index(start)] = true;
+    queue.push_back(start);
+
+    while let Some(current) = queue.pop_front() {
+        if current == goal {
+            // Walk the breadcrumbs back to the start.
+            let mut path = vec![current];
+            let mut cell = current;
+            while let Some(prev) = came_from[index(cell)] {
+                path.push(prev);
+                cell = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for dir in DIRECTIONS {
+            let next = step(current, dir);
+            if !in_bounds(next, grid_width, grid_height) {
+                continue;
+            }
+            if visited[index(next)] || (next != goal && blocked(next)) {
+                continue;
+            }
+            visited[index(next)] = true;
+            came_from[index(next)] = Some(current);
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+/// Computes the BFS distance from every cell to `goal`, row by row.
+///
+/// Cells that are blocked, or cannot reach the goal, have no distance.
+pub fn distance_field<F>(goal: Point, grid_width: i32, grid_height: i32, blocked: F) -> Vec<Option<u32>>
+where
+    F: Fn(Point) -> bool,
+{
+    let index = |p: Point| (p.y * grid_width + p.x) as usize;
+    let mut distances = vec![None; (grid_width * grid_height) as usize];
+    let mut queue = VecDeque::new();
+
+    distances[index(goal)] = Some(0);
+    queue.push_back(goal);
+
+    while let Some(current) = queue.pop_front() {
+        let distance = distances[index(current)].unwrap_or(0);
+        for dir in DIRECTIONS {
+            let next = step(current, dir);
+            if !in_bounds(next, grid_width, grid_height) {
+                continue;
+            }
+            if distances[index(next)].is_some() || blocked(next) {
+                continue;
+            }
+            distances[index(next)] = Some(distance + 1);
+            queue.push_back(next);
+        }
+    }
+    distances
+}
+
+/// Returns the move made by a player of the given `skill` (from 0 to 1), given the
+/// strongest move `best` and every move that doesn't collide straight away in `safe`.
+///
+/// With probability `skill` the move is `best`; otherwise it is picked at random from
+/// `safe`, so it may still happen to be `best`. With no safe moves it is `best`.
+pub fn skilled_move<R: Rng>(rng: &mut R, best: Direction, safe: &[Direction], skill: f32) -> Direction {
+    if rng.gen_bool(skill.clamp(0.0, 1.0) as f64) {
+        return best;
+    }
+    safe.choose(rng).copied().unwrap_or(best)
+}
+
+/// Counts the cells reachable from `start` without crossing a blocked cell, `start`
+/// included.
+pub fn region_size<F>(start: Point, grid_width: i32, grid_height: i32, blocked: F) -> usize
+where
+    F: Fn(Point) -> bool,
+{
+    distance_field(start, grid_width, grid_height, blocked).iter().flatten().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    #[test]
+    fn find_path_starts_at_start_and_ends_at_goal() {
+        let start = Point { x: 1, y: 1 };
+        let goal = Point { x: 6, y: 4 };
+        let path = find_path(start, goal, 8, 8, |_| false).unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        // A shortest path on an open grid takes the Manhattan distance in steps.
+        assert_eq!(path.len(), 9);
+        for pair in path.windows(2) {
+            assert!(direction_between(pair[0], pair[1]).is_some());
+        }
+    }
+
+    #[test]
+    fn find_path_goes_around_walls() {
+        // A wall down column 2 with a gap at the bottom.
+        let wall = |p: Point| p.x == 2 && p.y < 4;
+        let path = find_path(Point { x: 0, y: 0 }, Point { x: 4, y: 0 }, 5, 5, wall).unwrap();
+        assert!(path.iter().all(|&p| !wall(p)));
+        assert!(path.contains(&Point { x: 2, y: 4 }));
+    }
+
+    #[test]
+    fn find_path_reports_an_unreachable_goal() {
+        let wall = |p: Point| p.x == 2;
+        assert!(find_path(Point { x: 0, y: 0 }, Point { x: 4, y: 0 }, 5, 5, wall).is_none());
+    }
+
+    #[test]
+    fn find_path_may_end_on_a_blocked_goal() {
+        let goal = Point { x: 3, y: 0 };
+        let path = find_path(Point { x: 0, y: 0 }, goal, 5, 5, |p| p == goal).unwrap();
+        assert_eq!(path.last(), Some(&goal));
+    }
+
+    #[test]
+    fn distance_field_counts_steps_to_the_goal() {
+        let goal = Point { x: 1, y: 1 };
+        let distances = distance_field(goal, 4, 3, |_| false);
+        let at = |x: i32, y: i32| distances[(y * 4 + x) as usize];
+        assert_eq!(at(1, 1), Some(0));
+        assert_eq!(at(0, 0), Some(2));
+        assert_eq!(at(3, 2), Some(3));
+    }
+
+    #[test]
+    fn distance_field_routes_around_walls_and_skips_them() {
+        // Column 1 is walled off except in the bottom row.
+        let wall = |p: Point| p.x == 1 && p.y < 2;
+        let distances = distance_field(Point { x: 0, y: 0 }, 3, 3, wall);
+        let at = |x: i32, y: i32| distances[(y * 3 + x) as usize];
+        assert_eq!(at(1, 0), None);
+        assert_eq!(at(2, 0), Some(6));
+        assert_eq!(at(1, 2), Some(3));
+    }
+
+    #[test]
+    fn distance_field_leaves_cut_off_cells_unreachable() {
+        let wall = |p: Point| p.x == 1;
+        let distances = distance_field(Point { x: 0, y: 0 }, 3, 2, wall);
+        assert_eq!(distances, vec![Some(0), None, None, Some(1), None, None]);
+    }
+
+    #[test]
+    fn in_bounds_covers_the_edges_and_corners() {
+        let inside = |x, y| in_bounds(Point { x, y }, 10, 8);
+        for (x, y) in [(0, 0), (9, 0), (0, 7), (9, 7), (4, 0), (0, 3), (9, 3), (4, 7), (4, 3)] {
+            assert!(inside(x, y), "({x}, {y})");
+        }
+        for (x, y) in [(-1, 0), (10, 0), (0, -1), (0, 8), (-1, -1), (10, 8), (4, 8), (10, 3)] {
+            assert!(!inside(x, y), "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn a_perfect_player_always_makes_the_best_move() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let safe = [Direction::Up, Direction::Down, Direction::Left];
+        for _ in 0..100 {
+            assert_eq!(skilled_move(&mut rng, Direction::Left, &safe, 1.0), Direction::Left);
+        }
+    }
+
+    #[test]
+    fn an_unskilled_player_picks_any_safe_move() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let safe = [Direction::Up, Direction::Down, Direction::Left];
+        let moves: Vec<Direction> = (0..100).map(|_| skilled_move(&mut rng, Direction::Left, &safe, 0.0)).collect();
+        assert!(moves.iter().all(|dir| safe.contains(dir)));
+        for dir in safe {
+            assert!(moves.contains(&dir), "never picked {dir:?}");
+        }
+        // Boxed in, even a random player falls back on the best move.
+        assert_eq!(skilled_move(&mut rng, Direction::Right, &[], 0.0), Direction::Right);
+    }
+
+    #[test]
+    fn preference_order_goes_straight_then_turns_by_hand_then_back() {
+        use Direction::*;
+        assert_eq!(preference_order(Up, TieBreak::RightHand), [Up, Right, Left, Down]);
+        assert_eq!(preference_order(Up, TieBreak::LeftHand), [Up, Left, Right, Down]);
+        assert_eq!(preference_order(Left, TieBreak::RightHand), [Left, Up, Down, Right]);
+    }
+
+    #[test]
+    fn best_move_breaks_ties_the_same_way_whatever_the_candidate_order() {
+        use Direction::*;
+        let tied = [(Left, 2), (Down, 5), (Right, 2)];
+        let mut reversed = tied;
+        reversed.reverse();
+        for candidates in [tied, reversed] {
+            assert_eq!(best_move(&candidates, Up, TieBreak::RightHand), Some(Right));
+            assert_eq!(best_move(&candidates, Up, TieBreak::LeftHand), Some(Left));
+        }
+        assert_eq!(best_move(&[(Down, 1), (Up, 3)], Up, TieBreak::RightHand), Some(Down));
+        assert_eq!(best_move(&[], Up, TieBreak::RightHand), None);
+    }
+}
@@ -0,0 +1,103 @@
+use ggez::audio::{self, SoundSource};
+use ggez::Context;
+
+/// Volume change applied by each volume key press.
+pub const VOLUME_STEP: f32 = 0.1;
+
+/// Game events that have a sound effect attached.
+#[derive(Clone, Copy)]
+pub enum Sound {
+    Eat,
+    Death,
+}
+
+/// Returns `volume` moved by `delta`, clamped to [0, 1].
+pub fn adjust_volume(volume: f32, delta: f32) -> f32 {
+    (volume + delta).clamp(0.0, 1.0)
+}
+
+/// Returns the volume a channel actually plays at, taking its mute into account.
+pub fn effective_volume(volume: f32, muted: bool) -> f32 {
+    if muted { 0.0 } else { volume.clamp(0.0, 1.0) }
+}
+
+/// The game's loaded sound sources, split into a music channel and an effects channel.
+///
+/// Every source is optional: a missing or undecodable file simply plays nothing.
+#[derive(Default)]
+pub struct Audio {
+    music: Option<audio::Source>,
+    eat: Option<audio::Source>,
+    death: Option<audio::Source>,
+}
+
+impl Audio {
+    /// Loads whichever sounds are present under `/sounds` in the resource directory.
+    pub fn load(ctx: &mut Context) -> Audio {
+        let mut music = audio::Source::new(ctx, "/sounds/music.ogg").ok();
+        if let Some(music) = &mut music {
+            music.set_repeat(true);
+        }
+        Audio {
+            music,
+            eat: audio::Source::new(ctx, "/sounds/eat.ogg").ok(),
+            death: audio::Source::new(ctx, "/sounds/death.ogg").ok(),
+        }
+    }
+
+    /// Sets the music channel to `music` and every sound effect to `sfx`.
+    pub fn apply_volumes(&mut self, music: f32, sfx: f32) {
+        if let Some(source) = &mut self.music {
+            source.set_volume(music);
+        }
+        for source in [&mut self.eat, &mut self.death].into_iter().flatten() {
+            source.set_volume(sfx);
+        }
+    }
+
+    /// Starts the background music looping, if there is any.
+    pub fn start_music(&mut self, ctx: &Context) {
+        if let Some(source) = &mut self.music {
+            // Audio failures shouldn't stop the game, so errors are ignored.
+            let _ = source.play(ctx);
+        }
+    }
+
+    /// Plays a sound effect over anything already playing.
+    pub fn play(&mut self, ctx: &Context, sound: Sound) {
+        let source = match sound {
+            Sound::Eat => &mut self.eat,
+            Sound::Death => &mut self.death,
+        };
+        if let Some(source) = source {
+            let _ = source.play_detached(ctx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adjust_volume_stays_between_silent_and_full() {
+        assert_eq!(adjust_volume(0.5, VOLUME_STEP), 0.6);
+        assert_eq!(adjust_volume(0.95, VOLUME_STEP), 1.0);
+        assert_eq!(adjust_volume(0.05, -VOLUME_STEP), 0.0);
+        assert_eq!(adjust_volume(1.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn muting_silences_only_its_own_channel() {
+        let (music, sfx) = (0.4, 0.8);
+        assert_eq!((effective_volume(music, true), effective_volume(sfx, false)), (0.0, 0.8));
+        assert_eq!((effective_volume(music, false), effective_volume(sfx, true)), (0.4, 0.0));
+    }
+
+    #[test]
+    fn effective_volume_clamps_out_of_range_settings() {
+        // A hand-edited settings file can hold anything.
+        assert_eq!(effective_volume(3.0, false), 1.0);
+        assert_eq!(effective_volume(-1.0, false), 0.0);
+    }
+}
@@ -0,0 +1,70 @@
+//! Times the per-frame board bookkeeping of `--dirty-rects` on a 100x100 board.
+//!
+//! A full redraw fills all 10,000 cells; the cached board works out which cells
+//! changed and fills only those, two on a plain step. The fills themselves need a
+//! window, so this times the work that decides what gets filled.
+//!
+//! Run with `cargo bench --bench dirty_bookkeeping`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// The game is a binary crate, so the module is pulled in by path, along with
+// the two types it borrows from the crate root.
+#[allow(dead_code)]
+#[path = "../dirty.rs"]
+mod dirty;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum FoodKind {
+    Normal,
+    Poison,
+}
+
+const SIZE: i32 = 100;
+
+/// A snake winding back and forth across the top `rows` rows, head first.
+fn snake(rows: i32) -> Vec<Point> {
+    (0..rows)
+        .flat_map(|y| (0..SIZE).map(move |x| Point { x: if y % 2 == 0 { x } else { SIZE - 1 - x }, y }))
+        .rev()
+        .collect()
+}
+
+fn board(c: &mut Criterion) {
+    let walls: Vec<Point> = (0..SIZE).map(|y| Point { x: SIZE / 2, y }).filter(|p| p.y > 20).collect();
+    let food = Some((Point { x: 10, y: 90 }, FoodKind::Normal));
+    let before_snake = snake(10);
+    // One step on: the head moves into the next row and the tail leaves the first.
+    let mut after_snake = before_snake.clone();
+    after_snake.insert(0, Point { x: 0, y: 10 });
+    after_snake.pop();
+    let kinds = |snake: &[Point]| dirty::cell_kinds(SIZE, SIZE, &walls, snake, food, None, None);
+    let before = kinds(&before_snake);
+
+    let mut group = c.benchmark_group("board_100x100");
+    group.bench_function("every_cell", |b| {
+        b.iter(|| {
+            let after = kinds(black_box(&after_snake));
+            let cells: Vec<Point> = (0..SIZE).flat_map(|y| (0..SIZE).map(move |x| Point { x, y })).collect();
+            black_box((after, cells))
+        })
+    });
+    group.bench_function("changed_cells", |b| {
+        b.iter(|| {
+            let after = kinds(black_box(&after_snake));
+            let changed = dirty::changed_cells(&before, &after, SIZE);
+            black_box((after, changed))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, board);
+criterion_main!(benches);
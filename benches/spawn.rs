@@ -0,0 +1,63 @@
+//! Times the three ways of picking a free cell for food on a 20x20 board, at
+//! several fill levels.
+//!
+//! Rejection sampling guesses cells until one is free, so its cost grows with the
+//! share of the board taken. Collecting lists every free cell and reservoir
+//! sampling walks them all, so both pay for the whole board on every pick, and
+//! reservoir sampling draws a random number per free cell on top. The game
+//! guesses first and only collects once its guesses run out.
+//!
+//! Run with `cargo bench --bench spawn`.
+
+use std::collections::HashSet;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+// The game is a binary crate, so the module is pulled in by path, along with
+// the type it borrows from the crate root. Its tests are built here too, without
+// a harness to run them.
+#[allow(dead_code, unused_imports)]
+#[path = "../spawn.rs"]
+mod spawn;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+const SIZE: i32 = 20;
+/// Percentages of the board taken when picking.
+const FILLS: [usize; 5] = [10, 50, 75, 90, 99];
+
+/// Takes `percent` of the board's cells, chosen at random from a fixed seed.
+fn occupied(percent: usize) -> HashSet<Point> {
+    let mut cells: Vec<Point> = (0..SIZE).flat_map(|y| (0..SIZE).map(move |x| Point { x, y })).collect();
+    cells.shuffle(&mut StdRng::seed_from_u64(1));
+    cells.truncate(cells.len() * percent / 100);
+    cells.into_iter().collect()
+}
+
+fn strategies(c: &mut Criterion) {
+    let mut group = c.benchmark_group("free_cell_20x20");
+    for percent in FILLS {
+        let taken = occupied(percent);
+        let mut rng = StdRng::seed_from_u64(2);
+        group.bench_with_input(BenchmarkId::new("rejection", percent), &taken, |b, taken| {
+            b.iter(|| black_box(spawn::by_rejection(&mut rng, taken, SIZE, SIZE, usize::MAX)))
+        });
+        group.bench_with_input(BenchmarkId::new("collecting", percent), &taken, |b, taken| {
+            b.iter(|| black_box(spawn::by_collecting(&mut rng, taken, SIZE, SIZE)))
+        });
+        group.bench_with_input(BenchmarkId::new("reservoir", percent), &taken, |b, taken| {
+            b.iter(|| black_box(spawn::by_reservoir(&mut rng, taken, SIZE, SIZE)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, strategies);
+criterion_main!(benches);
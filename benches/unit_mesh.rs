@@ -0,0 +1,54 @@
+//! Times the CPU side of filling a 400-segment snake's cells, before and after
+//! draw() switched to one cached unit mesh.
+//!
+//! Before, every rectangle was tessellated into a mesh of its own each frame, then
+//! uploaded and drawn. After, each one is only a draw parameter that stretches and
+//! tints the shared unit square. Uploading and drawing need a window, so this
+//! times the tessellation the old path did per rectangle against building the
+//! parameters the new one uses.
+//!
+//! Run with `cargo bench --bench unit_mesh`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ggez::graphics::{Color, DrawMode, DrawParam, MeshBuilder, Rect};
+
+const CELL: f32 = 20.0;
+const LENGTH: usize = 400;
+
+/// The cells of a snake winding across a 20x20 board, as screen rectangles.
+fn segment_rects() -> Vec<Rect> {
+    (0..LENGTH)
+        .map(|i| {
+            let (row, col) = (i / 20, i % 20);
+            let col = if row % 2 == 0 { col } else { 19 - col };
+            Rect::new(col as f32 * CELL, row as f32 * CELL, CELL, CELL)
+        })
+        .collect()
+}
+
+fn snake(c: &mut Criterion) {
+    let rects = segment_rects();
+    let green = Color::from_rgb(0, 255, 0);
+    let mut group = c.benchmark_group("snake_400_segments");
+    group.bench_function("mesh_per_rect", |b| {
+        b.iter(|| {
+            for &rect in &rects {
+                let mut mesh = MeshBuilder::new();
+                mesh.rectangle(DrawMode::fill(), black_box(rect), green).unwrap();
+                black_box(mesh);
+            }
+        })
+    });
+    group.bench_function("unit_rect_params", |b| {
+        b.iter(|| {
+            for &rect in &rects {
+                let param = DrawParam::new().dest([rect.x, rect.y]).scale([rect.w, rect.h]).color(green);
+                black_box(param);
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, snake);
+criterion_main!(benches);
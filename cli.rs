@@ -0,0 +1,500 @@
+use std::path::PathBuf;
+
+use crate::ai::TieBreak;
+use crate::{DEFAULT_GRID_SIZE, Direction, SpawnStrategy, SpeedCurve, WallToll, validate_dimensions};
+
+/// Options that configure a game session, parsed from the command line.
+#[derive(Clone, Default)]
+pub struct Options {
+    // Advance the snake one cell per key press instead of on a timer.
+    pub step_on_input: bool,
+    // Keep the snake still at the start of a run until the first turn is pressed.
+    pub wait_for_input: bool,
+    // Grid size in cells, for the default grid (None keeps it DEFAULT_GRID_SIZE).
+    pub grid_width: Option<i32>,
+    pub grid_height: Option<i32>,
+    // Level file to play instead of the empty default grid.
+    pub level_path: Option<PathBuf>,
+    // Treat the level file's Y axis as pointing up.
+    pub level_y_up: bool,
+    // Occasionally spawn poison food that shrinks the snake.
+    pub poison: bool,
+    // Occasionally spawn a short-lived bonus food worth extra points.
+    pub bonus: bool,
+    // Every so often put out a second food for a few seconds.
+    pub double_food: bool,
+    // Keep a separate count of style points, earned by moving along walls.
+    pub style_points: bool,
+    // Make each food worth less the longer the snake is, instead of always one point.
+    pub length_decay: bool,
+    // Enable developer overlays and keys.
+    pub debug: bool,
+    // Start with the head coordinate readout in the HUD.
+    pub head_info: bool,
+    // Show the coordinate and contents of the cell under the mouse in the HUD.
+    pub hover: bool,
+    // List the snake's segment coordinates in a panel beside the grid.
+    pub panel: bool,
+    // Start with row and column numbers along the grid's edges.
+    pub rulers: bool,
+    // Fixed RNG seed, for reproducible food placement.
+    pub seed: Option<u64>,
+    // Seconds between autosaves of the run in progress (None disables autosave).
+    pub autosave_interval: Option<f32>,
+    // Score a run needs to keep the daily streak going.
+    pub streak_threshold: Option<u32>,
+    // Directory every frame of play is written to as a numbered PNG.
+    pub record_frames: Option<PathBuf>,
+    // TOML file of translated on-screen text.
+    pub locale_path: Option<PathBuf>,
+    // CSV file that gets a row appended for every finished run.
+    pub stats_file: Option<PathBuf>,
+    // How many times per run a death may be rewound (0 disables rewinding).
+    pub rewinds: u32,
+    // Crashes a run survives, each respawning the snake somewhere safe (0 means none).
+    pub lives: u32,
+    // Which way the snake starts moving, and how many segments it starts with (0 means 1).
+    pub start_dir: Direction,
+    pub start_length: usize,
+    // Food this many steps from the head gets eaten without the head reaching it.
+    pub magnet_radius: u32,
+    // The magnet only pulls in food straight ahead of the head, not beside or behind it.
+    pub head_on: bool,
+    // How often the autopilot makes its best move, from 0 to 1 (None means always);
+    // the rest of the time it makes a random safe one.
+    pub ai_skill: Option<f32>,
+    // Which way the autopilot turns when two moves are equally good.
+    pub ai_tie_break: TieBreak,
+    // Starting time between snake moves, in seconds, and how it shrinks as the score
+    // goes up (None keeps it steady).
+    pub move_period: Option<f32>,
+    pub speed_curve: Option<SpeedCurve>,
+    // Show a bar in the HUD filling up toward the stepped curve's next speed-up.
+    pub speed_bar: bool,
+    // Vertical moves take this many times as long as horizontal ones (0 means the same).
+    pub vertical_factor: f32,
+    // How long a step that would go straight waits for a turn, in seconds.
+    pub grace: Option<f32>,
+    // Start in mirror mode, with every arrow key steering the opposite way.
+    pub invert_controls: bool,
+    // Turn the snake along the grid edge instead of ending the game.
+    pub bumpers: bool,
+    // Leaving the grid on one edge brings the snake back on the opposite edge.
+    pub wrap: bool,
+    // Fill the grid with a procedurally generated maze, optionally built from a
+    // shareable seed string instead of the run's seed.
+    pub maze: bool,
+    pub maze_seed: Option<String>,
+    // Daily challenge: the run's seed and maze come from today's date.
+    pub daily: bool,
+    // How new food positions are picked, and how many upcoming ones are shown.
+    pub spawn: SpawnStrategy,
+    pub preview: usize,
+    // How many upcoming foods flash up for a moment each time the food is eaten.
+    pub reveal: usize,
+    // How many screen cells wide each logical cell is drawn, for a chunkier look (0 means 1).
+    pub cell_scale: u32,
+    // Largest initial window, in pixels; bigger grids get smaller cells to fit.
+    pub max_window: Option<(f32, f32)>,
+    // Skip the banner and flash when a run passes the high score.
+    pub no_celebration: bool,
+    // Score practice: eating scores but never grows the snake.
+    pub no_grow: bool,
+    // Keep the snake plain green instead of warming its color as the speed goes up.
+    pub no_speed_tint: bool,
+    // Fade the snake out from its head to its tail, like a light trail (not drawn on
+    // the cached board of --dirty-rects).
+    pub trail_fade: bool,
+    // Shade the grid's cells in a subtle checkerboard instead of plain black.
+    pub checkerboard: bool,
+    // Gap left around the snake and food inside each cell, as a fraction of the cell.
+    pub cell_padding: f32,
+    // Drop shadows under the snake and food: how far down and right they fall, as a
+    // fraction of a cell (None draws no shadows), and their RGBA color.
+    pub shadow_offset: Option<f32>,
+    pub shadow_color: Option<[u8; 4]>,
+    // A border around the arena: its thickness in pixels (None draws no border), and
+    // its RGBA color.
+    pub border: Option<f32>,
+    pub border_color: Option<[u8; 4]>,
+    // TTF font for all text, and its pixel size.
+    pub font_path: Option<PathBuf>,
+    pub font_size: Option<f32>,
+    // Redraw only the cells that changed each frame, from a cached board.
+    pub dirty_rects: bool,
+    // Slide the snake between cells instead of jumping a cell per move.
+    pub smooth: bool,
+    // Race a ghost of the best recorded run.
+    pub ghost: bool,
+    // Draw a small chart of the snake's recent length.
+    pub sparkline: bool,
+    // Draw a fading streak from an eaten food to its replacement when that lands far off.
+    pub food_streak: bool,
+    // Turn off purely cosmetic animation, whatever the settings say.
+    pub reduced_motion: bool,
+    // Redden the grid's edges as the head nears them (not in wrap mode, which has no walls).
+    pub edge_warning: bool,
+    // Time attack: the run ends after this many seconds.
+    pub time_limit: Option<f32>,
+    // Ghost walls: the edges wrap, but each pass through one costs this toll.
+    pub wall_toll: Option<WallToll>,
+    // Zen mode: no food, no growth and no score, with the edges wrapping around.
+    pub zen: bool,
+    // Play in the terminal instead of a window (needs the `terminal` feature).
+    pub terminal: bool,
+    // Also take commands, one per line, from stdin, for scripted demos and smoke tests.
+    pub stdin_input: bool,
+    // Run the AI swarm demo with this many snakes instead of a game.
+    pub demo: Option<usize>,
+    // Watch this recorded run instead of playing.
+    pub replay_path: Option<PathBuf>,
+}
+
+impl Options {
+    /// Parses options from command-line arguments (excluding the program name).
+    pub fn parse<I>(args: I) -> Result<Options, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--step-on-input" => options.step_on_input = true,
+                "--wait-for-input" => options.wait_for_input = true,
+                "--width" => {
+                    let width = args.next().ok_or("--width requires a number of cells")?;
+                    options.grid_width = Some(width.parse().map_err(|_| format!("invalid width: {}", width))?);
+                }
+                "--height" => {
+                    let height = args.next().ok_or("--height requires a number of cells")?;
+                    options.grid_height = Some(height.parse().map_err(|_| format!("invalid height: {}", height))?);
+                }
+                "--level" => {
+                    let path = args.next().ok_or("--level requires a path")?;
+                    options.level_path = Some(PathBuf::from(path));
+                }
+                "--level-y-up" => options.level_y_up = true,
+                "--poison" => options.poison = true,
+                "--bonus" => options.bonus = true,
+                "--double-food" => options.double_food = true,
+                "--debug" => options.debug = true,
+                "--style-points" => options.style_points = true,
+                "--length-decay" => options.length_decay = true,
+                "--terminal" => options.terminal = true,
+                "--stdin-input" => options.stdin_input = true,
+                "--head-info" => options.head_info = true,
+                "--hover" => options.hover = true,
+                "--rulers" => options.rulers = true,
+                "--panel" => options.panel = true,
+                "--bumpers" => options.bumpers = true,
+                "--mirror" => options.invert_controls = true,
+                "--wrap" => options.wrap = true,
+                "--zen" => {
+                    options.zen = true;
+                    options.wrap = true;
+                }
+                "--reduced-motion" => options.reduced_motion = true,
+                "--edge-warning" => options.edge_warning = true,
+                "--sparkline" => options.sparkline = true,
+                "--food-streak" => options.food_streak = true,
+                "--smooth" => options.smooth = true,
+                "--dirty-rects" => options.dirty_rects = true,
+                "--checkerboard" => options.checkerboard = true,
+                "--trail-fade" => options.trail_fade = true,
+                "--no-speed-tint" => options.no_speed_tint = true,
+                "--no-celebration" => options.no_celebration = true,
+                "--no-grow" => options.no_grow = true,
+                "--ghost" => options.ghost = true,
+                "--maze" => options.maze = true,
+                "--maze-seed" => {
+                    let seed = args.next().ok_or("--maze-seed requires a seed string")?;
+                    options.maze_seed = Some(seed);
+                    options.maze = true;
+                }
+                "--daily" => {
+                    options.daily = true;
+                    options.maze = true;
+                }
+                "--seed" => {
+                    let seed = args.next().ok_or("--seed requires a number")?;
+                    options.seed = Some(seed.parse().map_err(|_| format!("invalid seed: {}", seed))?);
+                }
+                "--rewinds" => {
+                    let count = args.next().ok_or("--rewinds requires a number")?;
+                    options.rewinds = count.parse().map_err(|_| format!("invalid rewind count: {}", count))?;
+                }
+                "--lives" => {
+                    let count = args.next().ok_or("--lives requires a number")?;
+                    options.lives = count.parse().map_err(|_| format!("invalid number of lives: {}", count))?;
+                }
+                "--head-on" => options.head_on = true,
+                "--magnet" => {
+                    let radius = args.next().ok_or("--magnet requires a number of cells")?;
+                    options.magnet_radius = radius.parse().map_err(|_| format!("invalid magnet radius: {}", radius))?;
+                }
+                "--move-period" => {
+                    let secs = args.next().ok_or("--move-period requires a number of seconds")?;
+                    options.move_period = Some(secs.parse().map_err(|_| format!("invalid move period: {}", secs))?);
+                }
+                "--ai-skill" => {
+                    let skill = args.next().ok_or("--ai-skill requires a number from 0 to 1")?;
+                    let skill: f32 = skill.parse().map_err(|_| format!("invalid AI skill: {}", skill))?;
+                    if !(0.0..=1.0).contains(&skill) {
+                        return Err("AI skill must be between 0 and 1".to_string());
+                    }
+                    options.ai_skill = Some(skill);
+                }
+                "--ai-tie-break" => {
+                    let rule = args.next().ok_or("--ai-tie-break requires right or left")?;
+                    options.ai_tie_break = match rule.as_str() {
+                        "right" => TieBreak::RightHand,
+                        "left" => TieBreak::LeftHand,
+                        _ => return Err(format!("unknown tie-break rule: {}", rule)),
+                    };
+                }
+                "--speed-curve" => {
+                    let curve = args.next().ok_or("--speed-curve requires linear, exponential or stepped")?;
+                    options.speed_curve = Some(match curve.as_str() {
+                        "linear" => SpeedCurve::Linear,
+                        "exponential" => SpeedCurve::Exponential,
+                        "stepped" => SpeedCurve::Stepped,
+                        _ => return Err(format!("unknown speed curve: {}", curve)),
+                    });
+                }
+                "--speed-bar" => options.speed_bar = true,
+                "--vertical-factor" => {
+                    let factor = args.next().ok_or("--vertical-factor requires a number")?;
+                    let factor: f32 = factor.parse().map_err(|_| format!("invalid vertical factor: {}", factor))?;
+                    if factor.is_nan() || factor <= 0.0 {
+                        return Err("vertical factor must be positive".to_string());
+                    }
+                    options.vertical_factor = factor;
+                }
+                "--grace" => {
+                    let secs = args.next().ok_or("--grace requires a number of seconds")?;
+                    let secs: f32 = secs.parse().map_err(|_| format!("invalid grace window: {}", secs))?;
+                    if secs.is_nan() || secs < 0.0 {
+                        return Err("grace window can't be negative".to_string());
+                    }
+                    options.grace = Some(secs);
+                }
+                "--start-dir" => {
+                    let dir = args.next().ok_or("--start-dir requires up, down, left or right")?;
+                    options.start_dir = match dir.as_str() {
+                        "up" => Direction::Up,
+                        "down" => Direction::Down,
+                        "left" => Direction::Left,
+                        "right" => Direction::Right,
+                        _ => return Err(format!("unknown start direction: {}", dir)),
+                    };
+                }
+                "--start-length" => {
+                    let length = args.next().ok_or("--start-length requires a number")?;
+                    options.start_length = length.parse().map_err(|_| format!("invalid start length: {}", length))?;
+                }
+                "--spawn" => {
+                    let strategy = args.next().ok_or("--spawn requires uniform, far, open or tail")?;
+                    options.spawn = match strategy.as_str() {
+                        "uniform" => SpawnStrategy::Uniform,
+                        "far" => SpawnStrategy::Far,
+                        "open" => SpawnStrategy::Open,
+                        "tail" => SpawnStrategy::NearTail,
+                        _ => return Err(format!("unknown spawn strategy: {}", strategy)),
+                    };
+                }
+                "--reveal" => {
+                    let count = args.next().ok_or("--reveal requires a number of foods")?;
+                    options.reveal = count.parse().map_err(|_| format!("invalid reveal count: {}", count))?;
+                }
+                "--preview" => {
+                    let count = args.next().ok_or("--preview requires a number of foods")?;
+                    options.preview = count.parse().map_err(|_| format!("invalid preview count: {}", count))?;
+                }
+                "--cell-scale" => {
+                    let scale = args.next().ok_or("--cell-scale requires a number")?;
+                    let scale: u32 = scale.parse().map_err(|_| format!("invalid cell scale: {}", scale))?;
+                    if scale == 0 {
+                        return Err("cell scale must be at least 1".to_string());
+                    }
+                    options.cell_scale = scale;
+                }
+                "--max-window" => {
+                    let size = args.next().ok_or("--max-window requires a size like 800x600")?;
+                    let max_window = parse_window_size(&size).ok_or(format!("invalid window size: {}", size))?;
+                    options.max_window = Some(max_window);
+                }
+                "--cell-padding" => {
+                    let fraction = args.next().ok_or("--cell-padding requires a fraction of a cell")?;
+                    let fraction: f32 = fraction.parse().map_err(|_| format!("invalid cell padding: {}", fraction))?;
+                    if !(0.0..0.5).contains(&fraction) {
+                        return Err("cell padding must be at least 0 and less than 0.5".to_string());
+                    }
+                    options.cell_padding = fraction;
+                }
+                "--shadow" => {
+                    let fraction = args.next().ok_or("--shadow requires an offset as a fraction of a cell")?;
+                    let fraction: f32 = fraction.parse().map_err(|_| format!("invalid shadow offset: {}", fraction))?;
+                    if !(0.0..=1.0).contains(&fraction) {
+                        return Err("shadow offset must be between 0 and 1".to_string());
+                    }
+                    options.shadow_offset = Some(fraction);
+                }
+                "--shadow-color" => {
+                    let color = args.next().ok_or("--shadow-color requires a color like 00000080")?;
+                    options.shadow_color = Some(parse_hex_color(&color).ok_or(format!("invalid color: {}", color))?);
+                }
+                "--border" => {
+                    let px = args.next().ok_or("--border requires a thickness in pixels")?;
+                    let px: f32 = px.parse().map_err(|_| format!("invalid border thickness: {}", px))?;
+                    if !px.is_finite() || px < 0.0 {
+                        return Err("border thickness can't be negative".to_string());
+                    }
+                    options.border = Some(px);
+                }
+                "--border-color" => {
+                    let color = args.next().ok_or("--border-color requires a color like 808080")?;
+                    options.border_color = Some(parse_hex_color(&color).ok_or(format!("invalid color: {}", color))?);
+                }
+                "--font" => {
+                    let path = args.next().ok_or("--font requires a path")?;
+                    options.font_path = Some(PathBuf::from(path));
+                }
+                "--font-size" => {
+                    let size = args.next().ok_or("--font-size requires a number")?;
+                    let size: f32 = size.parse().map_err(|_| format!("invalid font size: {}", size))?;
+                    if size.is_nan() || size <= 0.0 {
+                        return Err("font size must be positive".to_string());
+                    }
+                    options.font_size = Some(size);
+                }
+                "--time-limit" => {
+                    let secs = args.next().ok_or("--time-limit requires a number of seconds")?;
+                    let secs: f32 = secs.parse().map_err(|_| format!("invalid time limit: {}", secs))?;
+                    if secs.is_nan() || secs <= 0.0 {
+                        return Err("time limit must be positive".to_string());
+                    }
+                    options.time_limit = Some(secs);
+                }
+                "--wall-toll" => {
+                    let toll = args.next().ok_or("--wall-toll requires a number of points, or of seconds like 2s")?;
+                    let invalid = || format!("invalid wall toll: {}", toll);
+                    options.wall_toll = Some(match toll.strip_suffix('s') {
+                        Some(secs) => {
+                            let secs: f32 = secs.parse().map_err(|_| invalid())?;
+                            if !secs.is_finite() || secs < 0.0 {
+                                return Err(invalid());
+                            }
+                            WallToll::Seconds(secs)
+                        }
+                        None => WallToll::Points(toll.parse().map_err(|_| invalid())?),
+                    });
+                    options.wrap = true;
+                }
+                "--demo" => {
+                    let count = args.next().ok_or("--demo requires a number of snakes")?;
+                    let count: usize = count.parse().map_err(|_| format!("invalid snake count: {}", count))?;
+                    if count == 0 {
+                        return Err("--demo needs at least one snake".to_string());
+                    }
+                    options.demo = Some(count);
+                }
+                "--autosave" => {
+                    let secs = args.next().ok_or("--autosave requires a number of seconds")?;
+                    let secs: f32 = secs.parse().map_err(|_| format!("invalid autosave interval: {}", secs))?;
+                    if secs.is_nan() || secs <= 0.0 {
+                        return Err("autosave interval must be positive".to_string());
+                    }
+                    options.autosave_interval = Some(secs);
+                }
+                "--streak-threshold" => {
+                    let score = args.next().ok_or("--streak-threshold requires a score")?;
+                    options.streak_threshold = Some(score.parse().map_err(|_| format!("invalid score: {}", score))?);
+                }
+                "--replay" => {
+                    let path = args.next().ok_or("--replay requires a path")?;
+                    options.replay_path = Some(PathBuf::from(path));
+                }
+                "--record-frames" => {
+                    let dir = args.next().ok_or("--record-frames requires a directory")?;
+                    options.record_frames = Some(PathBuf::from(dir));
+                }
+                "--locale" => {
+                    let path = args.next().ok_or("--locale requires a path")?;
+                    options.locale_path = Some(PathBuf::from(path));
+                }
+                "--stats-file" => {
+                    let path = args.next().ok_or("--stats-file requires a path")?;
+                    options.stats_file = Some(PathBuf::from(path));
+                }
+                _ => return Err(format!("unknown argument: {}", arg)),
+            }
+        }
+        if matches!(options.wall_toll, Some(WallToll::Seconds(_))) && options.time_limit.is_none() {
+            return Err("a wall toll in seconds needs a --time-limit to take them from".to_string());
+        }
+        if options.grid_width.is_some() || options.grid_height.is_some() {
+            if options.level_path.is_some() {
+                return Err("--width and --height can't be used with --level, which sets its own size".to_string());
+            }
+            validate_dimensions(
+                options.grid_width.unwrap_or(DEFAULT_GRID_SIZE),
+                options.grid_height.unwrap_or(DEFAULT_GRID_SIZE),
+            )?;
+        }
+        if options.head_on && options.magnet_radius == 0 {
+            return Err("--head-on limits what --magnet reaches, so it needs a magnet".to_string());
+        }
+        if options.speed_bar && options.speed_curve != Some(SpeedCurve::Stepped) {
+            return Err("--speed-bar shows the steps of --speed-curve stepped, so it needs that curve".to_string());
+        }
+        if options.daily && (options.seed.is_some() || options.maze_seed.is_some()) {
+            return Err("--daily picks its own seeds, so it can't be used with --seed or --maze-seed".to_string());
+        }
+        if options.stdin_input && options.terminal {
+            return Err("--stdin-input can't be used with --terminal, which reads keys from stdin".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Parses an `RRGGBB` or `RRGGBBAA` hex color (opaque if the alpha is left out),
+/// with or without a leading `#`.
+fn parse_hex_color(color: &str) -> Option<[u8; 4]> {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) || (hex.len() != 6 && hex.len() != 8) {
+        return None;
+    }
+    let mut rgba = [255; 4];
+    for (i, channel) in rgba.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(rgba)
+}
+
+/// Parses a `WIDTHxHEIGHT` size in pixels, such as `800x600`.
+fn parse_window_size(size: &str) -> Option<(f32, f32)> {
+    let (width, height) = size.split_once('x')?;
+    let (width, height): (f32, f32) = (width.parse().ok()?, height.parse().ok()?);
+    (width >= 1.0 && height >= 1.0).then_some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn parses_step_on_input() {
+        assert!(!parse(&[]).unwrap().step_on_input);
+        assert!(parse(&["--step-on-input"]).unwrap().step_on_input);
+    }
+
+    #[test]
+    fn rejects_unknown_arguments() {
+        assert_eq!(parse(&["--bogus"]).err().unwrap(), "unknown argument: --bogus");
+    }
+}
@@ -0,0 +1,88 @@
+use crate::Point;
+
+/// A command typed into the debug console.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    /// `grow N`: adds N segments to the tail.
+    Grow(usize),
+    /// `speed SECS`: sets the time between moves.
+    Speed(f32),
+    /// `spawn food`: moves the food to a fresh cell.
+    SpawnFood,
+    /// `teleport X Y`: moves the snake so its head is on cell (X, Y).
+    Teleport(i32, i32),
+}
+
+/// Parses one console line into a command.
+///
+/// Words are separated by whitespace and the command name is case-insensitive.
+/// Returns a message saying what's wrong for anything that isn't a valid command.
+pub fn parse(line: &str) -> Result<Command, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((name, args)) = words.split_first() else {
+        return Err("type a command: grow, speed, spawn or teleport".to_string());
+    };
+    match (name.to_ascii_lowercase().as_str(), args) {
+        ("grow", [count]) => Ok(Command::Grow(number(count)?)),
+        ("grow", _) => Err("usage: grow N".to_string()),
+        ("speed", [secs]) => {
+            let secs: f32 = number(secs)?;
+            if secs.is_nan() || secs <= 0.0 {
+                return Err("the move period must be positive".to_string());
+            }
+            Ok(Command::Speed(secs))
+        }
+        ("speed", _) => Err("usage: speed SECS".to_string()),
+        ("spawn", ["food"]) => Ok(Command::SpawnFood),
+        ("spawn", _) => Err("usage: spawn food".to_string()),
+        ("teleport", [x, y]) => Ok(Command::Teleport(number(x)?, number(y)?)),
+        ("teleport", _) => Err("usage: teleport X Y".to_string()),
+        _ => Err(format!("unknown command: {}", name)),
+    }
+}
+
+/// Parses a command argument as a number.
+fn number<T: std::str::FromStr>(word: &str) -> Result<T, String> {
+    word.parse().map_err(|_| format!("not a number: {}", word))
+}
+
+/// Moves `snake` as a whole so its head lands on `head`, keeping its shape.
+///
+/// Returns None if any segment would end up on a cell `fits` rejects.
+pub fn shifted(snake: &[Point], head: Point, fits: impl Fn(Point) -> bool) -> Option<Vec<Point>> {
+    let old_head = *snake.first()?;
+    let (dx, dy) = (head.x - old_head.x, head.y - old_head.y);
+    let moved: Vec<Point> = snake.iter().map(|p| Point { x: p.x + dx, y: p.y + dy }).collect();
+    moved.iter().all(|&p| fits(p)).then_some(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_command_in_any_case() {
+        assert_eq!(parse("grow 3"), Ok(Command::Grow(3)));
+        assert_eq!(parse("  SPEED 0.25 "), Ok(Command::Speed(0.25)));
+        assert_eq!(parse("Spawn food"), Ok(Command::SpawnFood));
+        assert_eq!(parse("teleport -1 4"), Ok(Command::Teleport(-1, 4)));
+    }
+
+    #[test]
+    fn rejects_bad_lines_with_a_reason() {
+        assert_eq!(parse(""), Err("type a command: grow, speed, spawn or teleport".to_string()));
+        assert_eq!(parse("grow"), Err("usage: grow N".to_string()));
+        assert_eq!(parse("grow lots"), Err("not a number: lots".to_string()));
+        assert_eq!(parse("speed 0"), Err("the move period must be positive".to_string()));
+        assert_eq!(parse("spawn wall"), Err("usage: spawn food".to_string()));
+        assert_eq!(parse("fly"), Err("unknown command: fly".to_string()));
+    }
+
+    #[test]
+    fn shifted_keeps_the_shape_or_refuses() {
+        let snake = [Point { x: 2, y: 2 }, Point { x: 1, y: 2 }];
+        let moved = shifted(&snake, Point { x: 5, y: 0 }, |_| true).unwrap();
+        assert_eq!(moved, vec![Point { x: 5, y: 0 }, Point { x: 4, y: 0 }]);
+        assert!(shifted(&snake, Point { x: 0, y: 0 }, |p| p.x >= 0).is_none());
+    }
+}
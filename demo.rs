@@ -0,0 +1,223 @@
+use ggez::event::EventHandler;
+use ggez::graphics::{self, Color, DrawMode, Mesh, Rect};
+use ggez::{Context, GameResult};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::ai;
+use crate::layout::Layout;
+use crate::{Direction, Point};
+
+/// Time between swarm moves, in seconds.
+const DEMO_MOVE_PERIOD: f32 = 0.1;
+
+/// Body colors handed out to the demo snakes in turn.
+const DEMO_COLORS: [(u8, u8, u8); 6] = [
+    (0, 255, 0),
+    (0, 160, 255),
+    (255, 200, 0),
+    (255, 0, 200),
+    (0, 255, 200),
+    (255, 120, 0),
+];
+
+/// One AI-controlled snake in the swarm demo.
+pub struct DemoSnake {
+    // The first element is the head.
+    pub body: Vec<Point>,
+    pub color: Color,
+}
+
+/// A screensaver-like demo where several AI snakes share the board and one food.
+///
+/// A snake that collides is removed; when the last one dies, a fresh swarm is spawned.
+pub struct Swarm {
+    pub snakes: Vec<DemoSnake>,
+    pub food: Option<Point>,
+    pub grid_width: i32,
+    pub grid_height: i32,
+    // How many snakes each new swarm starts with.
+    count: usize,
+    rng: StdRng,
+    move_timer: f32,
+}
+
+impl Swarm {
+    /// Creates a swarm of `count` one-cell snakes at random free cells.
+    pub fn new(count: usize, grid_width: i32, grid_height: i32, seed: u64) -> Swarm {
+        let mut swarm = Swarm {
+            snakes: Vec::new(),
+            food: None,
+            grid_width,
+            grid_height,
+            count,
+            rng: StdRng::seed_from_u64(seed),
+            move_timer: 0.0,
+        };
+        swarm.respawn();
+        swarm
+    }
+
+    /// Replaces every snake with a fresh swarm and places new food.
+    fn respawn(&mut self) {
+        self.snakes.clear();
+        let mut cells = self.free_cells();
+        cells.shuffle(&mut self.rng);
+        for (i, head) in cells.into_iter().take(self.count).enumerate() {
+            let (r, g, b) = DEMO_COLORS[i % DEMO_COLORS.len()];
+            self.snakes.push(DemoSnake {
+                body: vec![head],
+                color: Color::from_rgb(r, g, b),
+            });
+        }
+        self.spawn_food();
+    }
+
+    /// Returns every cell not covered by a snake.
+    fn free_cells(&self) -> Vec<Point> {
+        let mut cells = Vec::new();
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                let p = Point { x, y };
+                if !self.is_occupied(p) {
+                    cells.push(p);
+                }
+            }
+        }
+        cells
+    }
+
+    /// Returns true if any snake's body covers `p`.
+    fn is_occupied(&self, p: Point) -> bool {
+        self.snakes.iter().any(|snake| snake.body.contains(&p))
+    }
+
+    /// Returns true if `p` is off the board or covered by a snake.
+    fn is_blocked(&self, p: Point) -> bool {
+        !ai::in_bounds(p, self.grid_width, self.grid_height) || self.is_occupied(p)
+    }
+
+    /// Places the shared food on a random free cell (None if the board is full).
+    fn spawn_food(&mut self) {
+        self.food = self.free_cells().choose(&mut self.rng).copied();
+    }
+
+    /// Picks the direction for snake `index`: along the shortest path to the food, or
+    /// failing that any move that survives the tick.
+    fn choose_direction(&self, index: usize) -> Option<Direction> {
+        let head = self.snakes[index].body[0];
+        if let Some(food) = self.food
+            && let Some(path) = ai::find_path(head, food, self.grid_width, self.grid_height, |p| self.is_occupied(p))
+            && path.len() > 1
+        {
+            return ai::direction_between(path[0], path[1]);
+        }
+        ai::DIRECTIONS.into_iter().find(|&dir| !self.is_blocked(ai::step(head, dir)))
+    }
+
+    /// Moves every snake one cell, in order, removing any that collide.
+    ///
+    /// Snakes move one after another, so a snake that steps onto a cell another snake
+    /// has just moved into collides with it.
+    pub fn tick(&mut self) {
+        let mut index = 0;
+        while index < self.snakes.len() {
+            let head = self.snakes[index].body[0];
+            // A trapped snake keeps going and crashes, like a player would.
+            let dir = self.choose_direction(index).unwrap_or(Direction::Right);
+            let new_head = ai::step(head, dir);
+            if self.is_blocked(new_head) {
+                self.snakes.remove(index);
+                continue;
+            }
+            let ate = self.food == Some(new_head);
+            let body = &mut self.snakes[index].body;
+            body.insert(0, new_head);
+            if ate {
+                self.spawn_food();
+            } else {
+                body.pop();
+            }
+            index += 1;
+        }
+        if self.snakes.is_empty() {
+            self.respawn();
+        }
+    }
+}
+
+impl EventHandler for Swarm {
+    /// Advances the swarm on a fixed timer.
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.move_timer += ggez::timer::delta(ctx).as_secs_f32();
+        if self.move_timer > DEMO_MOVE_PERIOD {
+            self.move_timer = 0.0;
+            self.tick();
+        }
+        Ok(())
+    }
+
+    /// Draws every snake in its own color, plus the shared food.
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::from_rgb(0, 0, 0));
+        let layout = Layout::fit(graphics::drawable_size(ctx), self.grid_width, self.grid_height);
+        let cells = self
+            .snakes
+            .iter()
+            .flat_map(|snake| snake.body.iter().map(move |&p| (p, snake.color)))
+            .chain(self.food.map(|food| (food, Color::from_rgb(255, 0, 0))));
+        for (p, color) in cells {
+            let rectangle = Mesh::new_rectangle(ctx, DrawMode::fill(), layout.cell_rect(p), color)?;
+            graphics::draw(ctx, &rectangle, (ggez::mint::Point2 { x: 0.0, y: 0.0 },))?;
+        }
+        graphics::present(ctx)
+    }
+
+    /// Keeps drawing in window pixels when the window is resized.
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        let _ = graphics::set_screen_coordinates(ctx, Rect::new(0.0, 0.0, width, height));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snake(body: &[(i32, i32)]) -> DemoSnake {
+        DemoSnake {
+            body: body.iter().map(|&(x, y)| Point { x, y }).collect(),
+            color: Color::WHITE,
+        }
+    }
+
+    #[test]
+    fn a_snake_that_crashes_is_removed() {
+        let mut swarm = Swarm::new(2, 3, 1, 1);
+        // The left snake is boxed in by the wall and the right one, which can move on.
+        swarm.snakes = vec![snake(&[(0, 0)]), snake(&[(1, 0)])];
+        swarm.food = None;
+        swarm.tick();
+        assert_eq!(swarm.snakes.len(), 1);
+        assert_eq!(swarm.snakes[0].body, vec![Point { x: 2, y: 0 }]);
+    }
+
+    #[test]
+    fn the_last_crash_brings_a_fresh_swarm() {
+        let mut swarm = Swarm::new(2, 2, 1, 1);
+        swarm.snakes = vec![snake(&[(0, 0), (1, 0)])];
+        swarm.food = None;
+        swarm.tick();
+        assert_eq!(swarm.snakes.len(), 2);
+        assert!(swarm.snakes.iter().all(|snake| snake.body.len() == 1));
+    }
+
+    #[test]
+    fn the_swarm_keeps_running_on_a_crowded_board() {
+        let mut swarm = Swarm::new(6, 4, 4, 7);
+        for _ in 0..500 {
+            swarm.tick();
+            assert!(!swarm.snakes.is_empty());
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use ggez::graphics::{Canvas, Color};
+
+use crate::{FoodKind, Point};
+
+/// What a grid cell shows on the cached board.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CellKind {
+    Empty,
+    Wall,
+    Snake,
+    Food(FoodKind),
+    ExtraFood,
+    Bonus,
+}
+
+/// The board as last drawn onto an offscreen canvas, so a frame only needs to redraw
+/// the cells that changed since.
+pub struct BoardCache {
+    pub canvas: Canvas,
+    // What each cell (row by row) showed when it was last drawn.
+    pub cells: Vec<CellKind>,
+    // The window size and snake color the canvas was drawn for; if either changes,
+    // every cell is redrawn.
+    pub window: (f32, f32),
+    pub snake_color: Color,
+}
+
+/// Lists what every cell of the board shows, row by row.
+///
+/// Where things overlap, the one drawn last wins: the bonus, then the food, then the
+/// extra food, then the snake, then walls.
+pub fn cell_kinds(
+    grid_width: i32,
+    grid_height: i32,
+    walls: &[Point],
+    snake: &[Point],
+    food: Option<(Point, FoodKind)>,
+    extra_food: Option<Point>,
+    bonus: Option<Point>,
+) -> Vec<CellKind> {
+    let mut cells = vec![CellKind::Empty; (grid_width * grid_height) as usize];
+    let mut set = |p: Point, kind: CellKind| {
+        if let Some(cell) = cells.get_mut((p.y * grid_width + p.x) as usize) {
+            *cell = kind;
+        }
+    };
+    for &p in walls {
+        set(p, CellKind::Wall);
+    }
+    for &p in snake {
+        set(p, CellKind::Snake);
+    }
+    if let Some(p) = extra_food {
+        set(p, CellKind::ExtraFood);
+    }
+    if let Some((p, kind)) = food {
+        set(p, CellKind::Food(kind));
+    }
+    if let Some(p) = bonus {
+        set(p, CellKind::Bonus);
+    }
+    cells
+}
+
+/// Returns the cells whose contents differ between two boards of the same size.
+pub fn changed_cells(before: &[CellKind], after: &[CellKind], grid_width: i32) -> Vec<Point> {
+    before
+        .iter()
+        .zip(after)
+        .enumerate()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(i, _)| Point {
+            x: i as i32 % grid_width,
+            y: i as i32 / grid_width,
+        })
+        .collect()
+}
@@ -0,0 +1,147 @@
+use ggez::graphics::Rect;
+use ggez::mint::Point2;
+use serde::{Deserialize, Serialize};
+
+use crate::Point;
+
+/// Distance (in pixels) kept between the HUD text and the edges of its area.
+const HUD_MARGIN: f32 = 10.0;
+
+/// Where on the screen the score HUD is drawn.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum HudPosition {
+    #[default]
+    TopLeft,
+    TopRight,
+    Bottom,
+}
+
+impl HudPosition {
+    /// Returns the next position in the cycle, wrapping back to the start.
+    pub fn next(self) -> HudPosition {
+        match self {
+            HudPosition::TopLeft => HudPosition::TopRight,
+            HudPosition::TopRight => HudPosition::Bottom,
+            HudPosition::Bottom => HudPosition::TopLeft,
+        }
+    }
+}
+
+/// Computes the top-left draw coordinate for HUD text of the given size.
+///
+/// `area` is the screen rectangle the HUD is placed in and `text` is the text's
+/// (width, height) in pixels. The bottom position centers the text horizontally.
+pub fn hud_origin(position: HudPosition, area: Rect, text: (f32, f32)) -> Point2<f32> {
+    let (text_w, text_h) = text;
+    match position {
+        HudPosition::TopLeft => Point2 { x: area.x + HUD_MARGIN, y: area.y + HUD_MARGIN },
+        HudPosition::TopRight => Point2 { x: area.right() - text_w - HUD_MARGIN, y: area.y + HUD_MARGIN },
+        HudPosition::Bottom => Point2 {
+            x: area.x + (area.w - text_w) / 2.0,
+            y: area.bottom() - text_h - HUD_MARGIN,
+        },
+    }
+}
+
+/// Computes where to draw each line of a text block so the block is centered in `area`.
+///
+/// `lines` holds each line's (width, height). Lines are stacked top to bottom with
+/// `spacing` pixels between them, and each is centered horizontally.
+pub fn centered_line_origins(area: Rect, lines: &[(f32, f32)], spacing: f32) -> Vec<Point2<f32>> {
+    let gaps = lines.len().saturating_sub(1) as f32 * spacing;
+    let block_height: f32 = lines.iter().map(|&(_, line_h)| line_h).sum::<f32>() + gaps;
+
+    let mut y = area.y + (area.h - block_height) / 2.0;
+    lines
+        .iter()
+        .map(|&(line_w, line_h)| {
+            let origin = Point2 { x: area.x + (area.w - line_w) / 2.0, y };
+            y += line_h + spacing;
+            origin
+        })
+        .collect()
+}
+
+/// Composes the side panel's text: a header with the snake's length, then up to
+/// `rows` of its segments' coordinates, head first, starting `scroll` segments in.
+///
+/// The scroll position is limited so the last segment stays on screen, and the
+/// header says which segments are showing when they don't all fit.
+pub fn panel_lines(snake: &[Point], scroll: usize, rows: usize) -> Vec<String> {
+    let scroll = scroll.min(snake.len().saturating_sub(rows));
+    let shown = &snake[scroll..snake.len().min(scroll + rows)];
+    let mut lines = vec![if shown.len() == snake.len() || shown.is_empty() {
+        format!("Length {}", snake.len())
+    } else {
+        format!("Length {} ({}-{})", snake.len(), scroll, scroll + shown.len() - 1)
+    }];
+    lines.extend(shown.iter().zip(scroll..).map(|(p, i)| format!("{}: {},{}", i, p.x, p.y)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 400x300 window, with 100x20 pixels of text.
+    const AREA: Rect = Rect { x: 0.0, y: 0.0, w: 400.0, h: 300.0 };
+    const TEXT: (f32, f32) = (100.0, 20.0);
+
+    fn origin(position: HudPosition, area: Rect) -> (f32, f32) {
+        let p = hud_origin(position, area, TEXT);
+        (p.x, p.y)
+    }
+
+    #[test]
+    fn top_left_sits_in_from_the_corner() {
+        assert_eq!(origin(HudPosition::TopLeft, AREA), (10.0, 10.0));
+    }
+
+    #[test]
+    fn top_right_ends_a_margin_from_the_right_edge() {
+        assert_eq!(origin(HudPosition::TopRight, AREA), (290.0, 10.0));
+    }
+
+    #[test]
+    fn bottom_is_centered_a_margin_above_the_bottom_edge() {
+        assert_eq!(origin(HudPosition::Bottom, AREA), (150.0, 270.0));
+    }
+
+    #[test]
+    fn positions_follow_an_offset_area() {
+        let area = Rect::new(50.0, 40.0, 400.0, 300.0);
+        assert_eq!(origin(HudPosition::TopLeft, area), (60.0, 50.0));
+        assert_eq!(origin(HudPosition::TopRight, area), (340.0, 50.0));
+        assert_eq!(origin(HudPosition::Bottom, area), (200.0, 310.0));
+    }
+
+    #[test]
+    fn next_cycles_through_every_position() {
+        let start = HudPosition::TopLeft;
+        assert!(start.next() == HudPosition::TopRight);
+        assert!(start.next().next() == HudPosition::Bottom);
+        assert!(start.next().next().next() == start);
+    }
+
+    #[test]
+    fn centered_lines_stack_around_the_middle() {
+        let origins = centered_line_origins(AREA, &[(200.0, 40.0), (100.0, 20.0)], 10.0);
+        let origins: Vec<(f32, f32)> = origins.iter().map(|p| (p.x, p.y)).collect();
+        // The block is 70 pixels tall, so it starts 115 pixels down.
+        assert_eq!(origins, vec![(100.0, 115.0), (150.0, 165.0)]);
+    }
+
+    #[test]
+    fn the_panel_lists_every_segment_head_first() {
+        let snake = [Point { x: 4, y: 2 }, Point { x: 3, y: 2 }, Point { x: 3, y: 3 }];
+        assert_eq!(panel_lines(&snake, 0, 10), vec!["Length 3", "0: 4,2", "1: 3,2", "2: 3,3"]);
+    }
+
+    #[test]
+    fn a_long_snake_scrolls_but_keeps_its_tail_in_view() {
+        let snake: Vec<Point> = (0..6).map(|x| Point { x, y: 0 }).collect();
+        assert_eq!(panel_lines(&snake, 1, 2), vec!["Length 6 (1-2)", "1: 1,0", "2: 2,0"]);
+        assert_eq!(panel_lines(&snake, 99, 2), vec!["Length 6 (4-5)", "4: 4,0", "5: 5,0"]);
+        assert_eq!(panel_lines(&[], 3, 2), vec!["Length 0"]);
+    }
+}
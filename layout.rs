@@ -0,0 +1,368 @@
+use ggez::graphics::Rect;
+use ggez::mint::Point2;
+
+use crate::{Direction, Point};
+
+/// Where the grid sits in the window: the size of one cell and the grid's top-left corner.
+///
+/// The grid is scaled to fit the window and centered, leaving letterbox bars along
+/// whichever sides the grid's aspect ratio doesn't fill.
+#[derive(Clone, Copy)]
+pub struct Layout {
+    pub cell_size: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub grid_width: i32,
+    pub grid_height: i32,
+}
+
+impl Layout {
+    /// Fits a grid of the given dimensions into a window of (width, height) pixels.
+    ///
+    /// Cells are kept a whole number of pixels (at least one) so edges stay crisp.
+    pub fn fit(window: (f32, f32), grid_width: i32, grid_height: i32) -> Layout {
+        Layout::fit_scaled(window, grid_width, grid_height, 1)
+    }
+
+    /// Fits the grid like `fit`, with each logical cell drawn `cell_scale` screen cells
+    /// across (0 counts as 1).
+    ///
+    /// The screen cells are kept whole pixels, so a logical cell is always an exact
+    /// multiple of one.
+    pub fn fit_scaled(window: (f32, f32), grid_width: i32, grid_height: i32, cell_scale: u32) -> Layout {
+        let (w, h) = window;
+        let scale = cell_scale.max(1) as f32;
+        let screen_cell = (w / (grid_width as f32 * scale))
+            .min(h / (grid_height as f32 * scale))
+            .floor()
+            .max(1.0);
+        let cell_size = screen_cell * scale;
+        Layout {
+            cell_size,
+            offset_x: ((w - grid_width as f32 * cell_size) / 2.0).max(0.0).floor(),
+            offset_y: ((h - grid_height as f32 * cell_size) / 2.0).max(0.0).floor(),
+            grid_width,
+            grid_height,
+        }
+    }
+
+    /// Fits the grid into the window beside a side panel `panel_width` pixels wide
+    /// along the window's left edge, centering it in the space that is left.
+    pub fn fit_beside_panel(
+        window: (f32, f32),
+        panel_width: f32,
+        grid_width: i32,
+        grid_height: i32,
+        cell_scale: u32,
+    ) -> Layout {
+        let beside = ((window.0 - panel_width).max(0.0), window.1);
+        let mut layout = Layout::fit_scaled(beside, grid_width, grid_height, cell_scale);
+        layout.offset_x += panel_width;
+        layout
+    }
+
+    /// Fits the grid beside the side panel like `fit_beside_panel`, but keeps `margin`
+    /// pixels clear on every side of the grid, for the arena border.
+    pub fn fit_with_margin(
+        window: (f32, f32),
+        panel_width: f32,
+        margin: f32,
+        grid_width: i32,
+        grid_height: i32,
+        cell_scale: u32,
+    ) -> Layout {
+        let inner = ((window.0 - 2.0 * margin).max(0.0), (window.1 - 2.0 * margin).max(0.0));
+        let mut layout = Layout::fit_beside_panel(inner, panel_width, grid_width, grid_height, cell_scale);
+        layout.offset_x += margin;
+        layout.offset_y += margin;
+        layout
+    }
+
+    /// Returns the screen rectangle covered by the whole grid.
+    pub fn grid_rect(&self) -> Rect {
+        Rect::new(
+            self.offset_x,
+            self.offset_y,
+            self.grid_width as f32 * self.cell_size,
+            self.grid_height as f32 * self.cell_size,
+        )
+    }
+
+    /// Returns the screen rectangle covered by cell `p`.
+    pub fn cell_rect(&self, p: Point) -> Rect {
+        Rect::new(
+            self.offset_x + p.x as f32 * self.cell_size,
+            self.offset_y + p.y as f32 * self.cell_size,
+            self.cell_size,
+            self.cell_size,
+        )
+    }
+
+    /// Returns the cell under the screen position (`x`, `y`), or None if it is off the
+    /// grid, in the letterbox bars, the border or the side panel.
+    pub fn cell_at(&self, x: f32, y: f32) -> Option<Point> {
+        let col = ((x - self.offset_x) / self.cell_size).floor();
+        let row = ((y - self.offset_y) / self.cell_size).floor();
+        let inside = (0.0..self.grid_width as f32).contains(&col) && (0.0..self.grid_height as f32).contains(&row);
+        inside.then_some(Point { x: col as i32, y: row as i32 })
+    }
+
+    /// Returns the screen position of the center of cell `p`.
+    pub fn cell_center(&self, p: Point) -> Point2<f32> {
+        Point2 {
+            x: self.offset_x + (p.x as f32 + 0.5) * self.cell_size,
+            y: self.offset_y + (p.y as f32 + 0.5) * self.cell_size,
+        }
+    }
+
+    /// Returns the part of `rect` that lies inside the grid, or None if there is none.
+    pub fn clip(&self, rect: Rect) -> Option<Rect> {
+        let grid = self.grid_rect();
+        let left = rect.left().max(grid.left());
+        let top = rect.top().max(grid.top());
+        let right = rect.right().min(grid.right());
+        let bottom = rect.bottom().min(grid.bottom());
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some(Rect::new(left, top, right - left, bottom - top))
+    }
+
+    /// Returns where to draw the number of column `x`: just inside the top-left corner
+    /// of the column's top cell.
+    pub fn column_label_origin(&self, x: i32) -> Point2<f32> {
+        let cell = self.cell_rect(Point { x, y: 0 });
+        Point2 { x: cell.x + 1.0, y: cell.y + 1.0 }
+    }
+
+    /// Returns where to draw the number of row `y`: at the left edge of the row's first
+    /// cell, halfway down, so it clears the column number above it.
+    pub fn row_label_origin(&self, y: i32) -> Point2<f32> {
+        let cell = self.cell_rect(Point { x: 0, y });
+        Point2 { x: cell.x + 1.0, y: cell.y + cell.h / 2.0 }
+    }
+
+    /// Returns the drop shadow of `rect`: the same rectangle moved down and right by
+    /// `offset` (a fraction of a cell).
+    pub fn shadow_rect(&self, rect: Rect, offset: f32) -> Rect {
+        let shift = offset * self.cell_size;
+        Rect::new(rect.x + shift, rect.y + shift, rect.w, rect.h)
+    }
+
+    /// Returns the bars of a border `thickness` pixels wide around the outside of the grid.
+    ///
+    /// A solid border is four bars, the top and bottom ones covering the corners. A
+    /// dashed one has a dash beside every other cell along each edge, starting from
+    /// the first, and leaves the corners open.
+    pub fn border_rects(&self, thickness: f32, dashed: bool) -> Vec<Rect> {
+        let grid = self.grid_rect();
+        let (top, bottom) = (grid.y - thickness, grid.bottom());
+        let (left, right) = (grid.x - thickness, grid.right());
+        if !dashed {
+            let across = grid.w + 2.0 * thickness;
+            return vec![
+                Rect::new(left, top, across, thickness),
+                Rect::new(left, bottom, across, thickness),
+                Rect::new(left, grid.y, thickness, grid.h),
+                Rect::new(right, grid.y, thickness, grid.h),
+            ];
+        }
+        let mut dashes = Vec::new();
+        for x in (0..self.grid_width).step_by(2) {
+            let cell = self.cell_rect(Point { x, y: 0 });
+            dashes.push(Rect::new(cell.x, top, cell.w, thickness));
+            dashes.push(Rect::new(cell.x, bottom, cell.w, thickness));
+        }
+        for y in (0..self.grid_height).step_by(2) {
+            let cell = self.cell_rect(Point { x: 0, y });
+            dashes.push(Rect::new(left, cell.y, thickness, cell.h));
+            dashes.push(Rect::new(right, cell.y, thickness, cell.h));
+        }
+        dashes
+    }
+
+    /// Returns a strip `width` pixels wide along the inside of the grid's `side` edge,
+    /// starting `from` pixels in from it.
+    pub fn edge_strip(&self, side: Direction, from: f32, width: f32) -> Rect {
+        let grid = self.grid_rect();
+        match side {
+            Direction::Up => Rect::new(grid.x, grid.y + from, grid.w, width),
+            Direction::Down => Rect::new(grid.x, grid.bottom() - from - width, grid.w, width),
+            Direction::Left => Rect::new(grid.x + from, grid.y, width, grid.h),
+            Direction::Right => Rect::new(grid.right() - from - width, grid.y, width, grid.h),
+        }
+    }
+
+    /// Returns the padding, in pixels, for a fraction of a cell.
+    pub fn padding(&self, fraction: f32) -> f32 {
+        fraction * self.cell_size
+    }
+}
+
+/// Shrinks `rect` by `padding` pixels on every side.
+///
+/// The padding is limited to half the rectangle's shorter side, so the result never
+/// has negative dimensions.
+pub fn inset(rect: Rect, padding: f32) -> Rect {
+    let padding = padding.clamp(0.0, rect.w.min(rect.h).max(0.0) / 2.0);
+    Rect::new(rect.x + padding, rect.y + padding, rect.w - 2.0 * padding, rect.h - 2.0 * padding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_wide_window_gets_bars_left_and_right() {
+        let layout = Layout::fit((800.0, 400.0), 20, 20);
+        assert_eq!(layout.cell_size, 20.0);
+        assert_eq!((layout.offset_x, layout.offset_y), (200.0, 0.0));
+    }
+
+    #[test]
+    fn a_tall_window_gets_bars_top_and_bottom() {
+        let layout = Layout::fit((400.0, 800.0), 20, 10);
+        assert_eq!(layout.cell_size, 20.0);
+        assert_eq!((layout.offset_x, layout.offset_y), (0.0, 300.0));
+    }
+
+    #[test]
+    fn cells_stay_whole_pixels_and_the_grid_stays_centered() {
+        let layout = Layout::fit((410.0, 405.0), 20, 20);
+        assert_eq!(layout.cell_size, 20.0);
+        assert_eq!((layout.offset_x, layout.offset_y), (5.0, 2.0));
+    }
+
+    #[test]
+    fn a_tiny_window_still_gets_one_pixel_cells() {
+        let layout = Layout::fit((10.0, 10.0), 20, 20);
+        assert_eq!(layout.cell_size, 1.0);
+        assert_eq!((layout.offset_x, layout.offset_y), (0.0, 0.0));
+    }
+
+    #[test]
+    fn the_panel_and_margin_push_the_grid_over() {
+        let layout = Layout::fit_with_margin((520.0, 420.0), 80.0, 10.0, 20, 20, 1);
+        assert_eq!(layout.cell_size, 20.0);
+        assert_eq!((layout.offset_x, layout.offset_y), (10.0 + 80.0 + 10.0, 10.0));
+    }
+
+    #[test]
+    fn inset_shrinks_every_side() {
+        let rect = inset(Rect::new(10.0, 10.0, 20.0, 10.0), 2.0);
+        assert_eq!((rect.x, rect.y, rect.w, rect.h), (12.0, 12.0, 16.0, 6.0));
+    }
+
+    #[test]
+    fn inset_never_goes_negative() {
+        let rect = inset(Rect::new(10.0, 10.0, 20.0, 10.0), 50.0);
+        assert_eq!((rect.x, rect.y, rect.w, rect.h), (15.0, 15.0, 10.0, 0.0));
+        let rect = inset(Rect::new(0.0, 0.0, 8.0, 8.0), -3.0);
+        assert_eq!((rect.w, rect.h), (8.0, 8.0));
+    }
+
+    #[test]
+    fn scaled_cells_are_whole_screen_cells_wide() {
+        let fine = Layout::fit((400.0, 400.0), 20, 20);
+        let chunky = Layout::fit_scaled((400.0, 400.0), 10, 10, 2);
+        for p in [Point { x: 0, y: 0 }, Point { x: 3, y: 7 }, Point { x: 9, y: 9 }] {
+            let rect = chunky.cell_rect(p);
+            let corner = fine.cell_rect(Point { x: p.x * 2, y: p.y * 2 });
+            assert_eq!((rect.x, rect.y), (corner.x, corner.y));
+            assert_eq!((rect.w, rect.h), (2.0 * corner.w, 2.0 * corner.h));
+            // The logical grid is untouched: the scaled cell still maps back to `p`.
+            assert_eq!(chunky.cell_at(rect.x + 1.0, rect.y + rect.h - 1.0), Some(p));
+        }
+    }
+
+    #[test]
+    fn scaling_rounds_to_whole_screen_cells() {
+        let layout = Layout::fit_scaled((410.0, 410.0), 10, 10, 3);
+        assert_eq!(layout.cell_size, 3.0 * 13.0);
+        assert_eq!(Layout::fit_scaled((400.0, 400.0), 10, 10, 0).cell_size, 40.0);
+    }
+
+    #[test]
+    fn ruler_labels_sit_inside_their_cells() {
+        let layout = Layout::fit((300.0, 220.0), 10, 10);
+        assert_eq!((layout.cell_size, layout.offset_x, layout.offset_y), (22.0, 40.0, 0.0));
+        let column = layout.column_label_origin(3);
+        assert_eq!((column.x, column.y), (40.0 + 3.0 * 22.0 + 1.0, 1.0));
+        let row = layout.row_label_origin(4);
+        assert_eq!((row.x, row.y), (41.0, 4.0 * 22.0 + 11.0));
+        // The top-left cell's two labels don't overlap.
+        assert!(layout.row_label_origin(0).y > layout.column_label_origin(0).y);
+    }
+
+    #[test]
+    fn shadows_drop_down_and_right_by_a_fraction_of_a_cell() {
+        let layout = Layout::fit((200.0, 200.0), 10, 10);
+        let cell = layout.cell_rect(Point { x: 2, y: 3 });
+        let shadow = layout.shadow_rect(cell, 0.25);
+        assert_eq!((shadow.x, shadow.y), (cell.x + 5.0, cell.y + 5.0));
+        assert_eq!((shadow.w, shadow.h), (cell.w, cell.h));
+        assert_eq!(layout.shadow_rect(cell, 0.0), cell);
+    }
+
+    #[test]
+    fn the_grid_starts_right_of_the_panel() {
+        let layout = Layout::fit_beside_panel((540.0, 400.0), 140.0, 20, 20, 1);
+        assert_eq!(layout.cell_size, 20.0);
+        assert_eq!((layout.offset_x, layout.offset_y), (140.0, 0.0));
+        assert_eq!(layout.cell_at(139.0, 10.0), None, "the panel isn't part of the grid");
+        assert_eq!(layout.cell_at(141.0, 10.0), Some(Point { x: 0, y: 0 }));
+    }
+
+    #[test]
+    fn a_solid_border_hugs_the_grid_and_covers_the_corners() {
+        let layout = Layout::fit((400.0, 400.0), 4, 4);
+        let bars = layout.border_rects(5.0, false);
+        let expected = vec![
+            Rect::new(-5.0, -5.0, 410.0, 5.0),
+            Rect::new(-5.0, 400.0, 410.0, 5.0),
+            Rect::new(-5.0, 0.0, 5.0, 400.0),
+            Rect::new(400.0, 0.0, 5.0, 400.0),
+        ];
+        assert_eq!(bars, expected);
+    }
+
+    #[test]
+    fn a_dashed_border_marks_every_other_cell_and_leaves_the_corners_open() {
+        let layout = Layout::fit((400.0, 400.0), 4, 4);
+        let dashes = layout.border_rects(5.0, true);
+        assert_eq!(dashes.len(), 8);
+        assert!(dashes.contains(&Rect::new(200.0, -5.0, 100.0, 5.0)));
+        assert!(dashes.contains(&Rect::new(400.0, 200.0, 5.0, 100.0)));
+        assert!(!dashes.contains(&Rect::new(100.0, -5.0, 100.0, 5.0)));
+        assert!(dashes.iter().all(|dash| dash.w == 100.0 || dash.h == 100.0));
+    }
+
+    #[test]
+    fn cell_at_finds_the_cell_under_a_point_inside_the_bars() {
+        let layout = Layout::fit((800.0, 400.0), 20, 20);
+        assert_eq!(layout.cell_at(200.0, 0.0), Some(Point { x: 0, y: 0 }));
+        assert_eq!(layout.cell_at(219.9, 19.9), Some(Point { x: 0, y: 0 }));
+        assert_eq!(layout.cell_at(220.0, 20.0), Some(Point { x: 1, y: 1 }));
+        assert_eq!(layout.cell_at(599.9, 399.9), Some(Point { x: 19, y: 19 }));
+        for y in 0..20 {
+            for x in 0..20 {
+                let center = layout.cell_center(Point { x, y });
+                assert_eq!(layout.cell_at(center.x, center.y), Some(Point { x, y }));
+            }
+        }
+    }
+
+    #[test]
+    fn cell_at_is_none_off_the_grid() {
+        let layout = Layout::fit((800.0, 400.0), 20, 20);
+        assert_eq!(layout.cell_at(199.9, 100.0), None, "in the left bar");
+        assert_eq!(layout.cell_at(600.0, 100.0), None, "in the right bar");
+        assert_eq!(layout.cell_at(300.0, -0.1), None);
+        assert_eq!(layout.cell_at(300.0, 400.0), None);
+
+        let beside_panel = Layout::fit_with_margin((520.0, 420.0), 80.0, 10.0, 20, 20, 1);
+        assert_eq!(beside_panel.cell_at(50.0, 50.0), None, "over the panel");
+        assert_eq!(beside_panel.cell_at(100.0, 10.0), Some(Point { x: 0, y: 0 }));
+        assert_eq!(beside_panel.cell_at(105.0, 5.0), None, "in the margin");
+    }
+}
@@ -0,0 +1,231 @@
+use std::fs;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::ai;
+use crate::{Direction, Point, validate_dimensions};
+
+/// Grid cells per generated wall segment; lower values make denser mazes.
+const MAZE_CELLS_PER_SEGMENT: i32 = 40;
+/// How many cells ahead of (and behind) the start a generated maze leaves open.
+const MAZE_CLEAR_AHEAD: i32 = 5;
+
+/// A level loaded from a text file: the grid size, its walls, and the starting snake.
+///
+/// Level files are line based. Blank lines and lines starting with `#` are ignored;
+/// every other line is one of:
+///
+/// ```text
+/// size <width> <height>   (required, before any cells)
+/// wall <x> <y>            an obstacle cell
+/// snake <x> <y>           a snake segment, head first
+/// ```
+#[derive(Clone)]
+pub struct Level {
+    pub width: i32,
+    pub height: i32,
+    pub obstacles: Vec<Point>,
+    // Starting snake, head first. Empty means "use the default start".
+    pub snake: Vec<Point>,
+}
+
+/// Converts a point between the Y-up and Y-down conventions on a grid of the given height.
+///
+/// The conversion is its own inverse.
+pub fn flip_y(p: Point, grid_height: i32) -> Point {
+    Point {
+        x: p.x,
+        y: grid_height - 1 - p.y,
+    }
+}
+
+impl Level {
+    /// Reads and parses a level file. See [`Level::parse`].
+    pub fn load(path: &Path, y_up: bool) -> Result<Level, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("could not read level {}: {}", path.display(), e))?;
+        Level::parse(&source, y_up)
+    }
+
+    /// Parses a level from its text form.
+    ///
+    /// When `y_up` is set, the file's Y axis is taken to point upward and every
+    /// coordinate is flipped into the game's Y-down convention.
+    pub fn parse(source: &str, y_up: bool) -> Result<Level, String> {
+        let mut size: Option<(i32, i32)> = None;
+        let mut obstacles = Vec::new();
+        let mut snake = Vec::new();
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let values = fields[1..]
+                .iter()
+                .map(|v| v.parse::<i32>())
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(|_| format!("line {}: expected integer coordinates", number + 1))?;
+            if values.len() != 2 {
+                return Err(format!("line {}: expected two values", number + 1));
+            }
+
+            if fields[0] == "size" {
+                validate_dimensions(values[0], values[1]).map_err(|e| format!("line {}: {}", number + 1, e))?;
+                size = Some((values[0], values[1]));
+                continue;
+            }
+
+            let (width, height) =
+                size.ok_or_else(|| format!("line {}: cells listed before size", number + 1))?;
+            let mut p = Point {
+                x: values[0],
+                y: values[1],
+            };
+            if !ai::in_bounds(p, width, height) {
+                return Err(format!("line {}: cell is outside the grid", number + 1));
+            }
+            if y_up {
+                p = flip_y(p, height);
+            }
+            match fields[0] {
+                "wall" => obstacles.push(p),
+                "snake" => snake.push(p),
+                other => return Err(format!("line {}: unknown entry '{}'", number + 1, other)),
+            }
+        }
+
+        let (width, height) = size.ok_or("level has no size line")?;
+        if snake.iter().any(|p| obstacles.contains(p)) {
+            return Err("snake starts inside a wall".to_string());
+        }
+        // Without snake lines the snake starts at the grid center, which must be open.
+        let center = Point {
+            x: width / 2,
+            y: height / 2,
+        };
+        if snake.is_empty() && obstacles.contains(&center) {
+            return Err("the default start cell is a wall; add snake lines".to_string());
+        }
+        Ok(Level {
+            width,
+            height,
+            obstacles,
+            snake,
+        })
+    }
+}
+
+/// Turns a shareable maze seed string into a maze seed.
+///
+/// This is 64-bit FNV-1a over the string's bytes, which (unlike the standard
+/// library's hasher) gives the same number on every platform and Rust version, so
+/// the same string always makes the same maze.
+pub fn seed_from_str(text: &str) -> u64 {
+    text.bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// Generates a random maze of short wall segments, deterministically from `seed`.
+///
+/// Cells near `start`, and the line through it along `heading` (ahead of the
+/// snake and behind it, where its starting body lies), are kept clear so the
+/// opening moves are always safe.
+pub fn generate_maze(seed: u64, width: i32, height: i32, start: Point, heading: Direction) -> Vec<Point> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let keep_clear = |p: Point| {
+        let on_line = match heading {
+            Direction::Left | Direction::Right => p.y == start.y && (p.x - start.x).abs() <= MAZE_CLEAR_AHEAD,
+            Direction::Up | Direction::Down => p.x == start.x && (p.y - start.y).abs() <= MAZE_CLEAR_AHEAD,
+        };
+        ((p.x - start.x).abs() <= 2 && (p.y - start.y).abs() <= 2) || on_line
+    };
+
+    let mut obstacles: Vec<Point> = Vec::new();
+    for _ in 0..(width * height) / MAZE_CELLS_PER_SEGMENT {
+        let length = rng.gen_range(2..=5);
+        let horizontal = rng.gen_bool(0.5);
+        let origin = Point {
+            x: rng.gen_range(0..width),
+            y: rng.gen_range(0..height),
+        };
+        for i in 0..length {
+            let p = if horizontal {
+                Point { x: origin.x + i, y: origin.y }
+            } else {
+                Point { x: origin.x, y: origin.y + i }
+            };
+            if p.x < width && p.y < height && !keep_clear(p) && !obstacles.contains(&p) {
+                obstacles.push(p);
+            }
+        }
+    }
+    obstacles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "size 5 4\nwall 0 0\nwall 4 3\nsnake 2 1\nsnake 1 1\n";
+
+    #[test]
+    fn flip_y_mirrors_rows_and_undoes_itself() {
+        let p = Point { x: 3, y: 0 };
+        assert_eq!(flip_y(p, 4), Point { x: 3, y: 3 });
+        assert_eq!(flip_y(flip_y(p, 4), 4), p);
+    }
+
+    #[test]
+    fn y_down_levels_load_as_written() {
+        let level = Level::parse(SOURCE, false).unwrap();
+        assert_eq!((level.width, level.height), (5, 4));
+        assert_eq!(level.obstacles, vec![Point { x: 0, y: 0 }, Point { x: 4, y: 3 }]);
+        assert_eq!(level.snake, vec![Point { x: 2, y: 1 }, Point { x: 1, y: 1 }]);
+    }
+
+    #[test]
+    fn y_up_levels_are_flipped_onto_the_screen() {
+        // With Y up, row 0 is the bottom row, which the screen draws last.
+        let level = Level::parse(SOURCE, true).unwrap();
+        assert_eq!(level.obstacles, vec![Point { x: 0, y: 3 }, Point { x: 4, y: 0 }]);
+        assert_eq!(level.snake, vec![Point { x: 2, y: 2 }, Point { x: 1, y: 2 }]);
+    }
+
+    #[test]
+    fn cells_outside_the_grid_are_rejected_before_flipping() {
+        assert!(Level::parse("size 5 4\nwall 0 4\n", true).is_err());
+    }
+
+    #[test]
+    fn seed_strings_hash_the_same_every_time() {
+        // The FNV-1a offset basis, for the empty string, and its published value for "a".
+        assert_eq!(seed_from_str(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(seed_from_str("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(seed_from_str("maze"), seed_from_str("mazf"));
+    }
+
+    #[test]
+    fn the_same_seed_builds_the_same_maze() {
+        let start = Point { x: 15, y: 10 };
+        let maze = generate_maze(seed_from_str("friday"), 30, 20, start, Direction::Right);
+        assert!(!maze.is_empty());
+        assert_eq!(maze, generate_maze(seed_from_str("friday"), 30, 20, start, Direction::Right));
+        assert_ne!(maze, generate_maze(seed_from_str("monday"), 30, 20, start, Direction::Right));
+    }
+
+    #[test]
+    fn the_maze_keeps_the_start_line_clear() {
+        let start = Point { x: 15, y: 10 };
+        for seed in 0..20 {
+            let maze = generate_maze(seed, 30, 20, start, Direction::Right);
+            for x in start.x - MAZE_CLEAR_AHEAD..=start.x + MAZE_CLEAR_AHEAD {
+                assert!(!maze.contains(&Point { x, y: start.y }), "seed {seed} blocks ({x}, 10)");
+            }
+            assert!(maze.iter().all(|&p| ai::in_bounds(p, 30, 20)));
+        }
+    }
+}
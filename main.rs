@@ -1,249 +1,5291 @@
 use ggez::event::{self, EventHandler, KeyCode, KeyMods};
-use ggez::graphics::{self, Color, DrawMode, Mesh, Rect, Text};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Font, Mesh, PxScale, Rect, Text, TextFragment};
 use ggez::{Context, ContextBuilder, GameResult};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+mod ai;
+mod audio;
+mod cli;
+mod console;
+mod demo;
+mod dirty;
+mod hud;
+mod layout;
+mod level;
+mod particles;
+mod replay;
+mod save;
+mod scores;
+mod script;
+mod settings;
+mod spawn;
+mod sparkline;
+mod stats;
+mod streak;
+mod strings;
+#[cfg(feature = "terminal")]
+mod terminal;
+mod viewer;
+
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use audio::{Audio, Sound};
+use cli::Options;
+use console::Command;
+use demo::Swarm;
+use dirty::{BoardCache, CellKind};
+use hud::HudPosition;
+use layout::Layout;
+use level::Level;
+use particles::Particle;
+use replay::Replay;
+use save::SaveState;
+use scores::Scores;
+use script::ScriptCommand;
+use settings::Settings;
+use sparkline::Sparkline;
+use stats::RunStats;
+use streak::Streak;
+use strings::Strings;
+use viewer::ReplayViewer;
+
+/// Size of one grid cell in the initial window, in pixels.
+const CELL_SIZE: f32 = 20.0;
+/// Width of the --panel side panel, in pixels, and how far its text sits from its edges.
+const PANEL_WIDTH: f32 = 140.0;
+const PANEL_MARGIN: f32 = 8.0;
+/// Most frames --record-frames writes in one session, so a forgotten recording
+/// can't fill the disk (about a minute of play at 60 frames per second).
+const MAX_RECORDED_FRAMES: u32 = 3600;
+/// Segments the panel scrolls by per Page Up or Page Down press.
+const PANEL_SCROLL_STEP: usize = 10;
 
 /// Represents a point on the game grid.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 struct Point {
     x: i32,
     y: i32,
 }
 
 /// Represents the possible directions for snake movement.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
 enum Direction {
     Up,
     Down,
     Left,
+    // The snake starts heading right unless told otherwise.
+    #[default]
     Right,
 }
 
+impl Direction {
+    /// Returns the direction pointing the opposite way.
+    fn opposite(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    /// Returns a one-letter name for the direction, for compact readouts.
+    fn short(self) -> &'static str {
+        match self {
+            Direction::Up => "U",
+            Direction::Down => "D",
+            Direction::Left => "L",
+            Direction::Right => "R",
+        }
+    }
+}
+
+/// The reversal rule: a snake moving `current` may not turn straight back on itself,
+/// unless it is a lone head with no body behind it.
+fn turn_is_legal(current: Direction, dir: Direction, length: usize) -> bool {
+    dir != current.opposite() || length <= 1
+}
+
+/// Grid width and height, in cells, when the command line doesn't give them.
+const DEFAULT_GRID_SIZE: i32 = 20;
+/// Fewest cells a grid may have along either side: any fewer and the start cell
+/// can't have a cell between it and every edge.
+const MIN_GRID_SIZE: i32 = 3;
+
+/// Checks that a grid `width` by `height` cells is big enough to play on.
+///
+/// Every grid size read from outside (the command line, level files, saves and
+/// replays) is checked here, so a bad one is reported where it came from rather than
+/// panicking later when food is placed on a board with no cells.
+fn validate_dimensions(width: i32, height: i32) -> Result<(), String> {
+    if width < MIN_GRID_SIZE || height < MIN_GRID_SIZE {
+        return Err(format!(
+            "a {}x{} grid is too small: it needs at least {} cells each way",
+            width, height, MIN_GRID_SIZE
+        ));
+    }
+    Ok(())
+}
+
+/// Lays out a starting snake of up to `length` segments (at least one), with the head
+/// at `head` and the body trailing straight behind it, away from `direction`.
+///
+/// The body stops at the grid edge, so it may come out shorter than asked.
+fn initial_body(head: Point, direction: Direction, length: usize, grid_width: i32, grid_height: i32) -> Vec<Point> {
+    let mut body = vec![head];
+    while body.len() < length {
+        let next = ai::step(body[body.len() - 1], direction.opposite());
+        if !ai::in_bounds(next, grid_width, grid_height) {
+            break;
+        }
+        body.push(next);
+    }
+    body
+}
+
+/// Maps a pressed direction to the turn it makes, flipping it when controls are inverted.
+fn input_direction(pressed: Direction, invert_controls: bool) -> Direction {
+    if invert_controls { pressed.opposite() } else { pressed }
+}
+
+/// Formats the head's grid coordinate and the current and queued directions for the HUD.
+fn head_info_text(snake: &[Point], direction: Direction, next_direction: Direction) -> String {
+    let head = match snake.first() {
+        Some(head) => format!("({}, {})", head.x, head.y),
+        None => "(none)".to_string(),
+    };
+    format!("Head {}  dir {}  next {}", head, direction.short(), next_direction.short())
+}
+
+/// The kinds of food that can appear on the board.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum FoodKind {
+    // Grows the snake by one segment and scores a point.
+    Normal,
+    // Removes tail segments at a small score cost.
+    Poison,
+}
+
+/// How new food positions are chosen.
+#[derive(Clone, Copy, PartialEq, Default)]
+enum SpawnStrategy {
+    // Any free cell, with equal probability.
+    #[default]
+    Uniform,
+    // The free cell farthest from the head among a few random candidates.
+    Far,
+    // The free cell in the largest open area among a few random candidates, so food
+    // doesn't end up in small pockets between walls.
+    Open,
+    // The free cell nearest the tail among a few random candidates, so reaching it
+    // means doubling back along the whole body.
+    NearTail,
+}
+
+/// What each pass through the edge costs under --wall-toll.
+#[derive(Clone, Copy, PartialEq)]
+enum WallToll {
+    Points(u32),
+    // Only in time attack, where it comes off the time left.
+    Seconds(f32),
+}
+
+/// How the snake speeds up as the score goes up.
+#[derive(Clone, Copy, PartialEq)]
+enum SpeedCurve {
+    // The move period shrinks by the same amount for every point.
+    Linear,
+    // The move period shrinks by the same fraction for every point.
+    Exponential,
+    // The move period drops a notch every few points.
+    Stepped,
+}
+
+/// Seconds the linear speed curve takes off the move period per point.
+const LINEAR_SPEEDUP: f32 = 0.004;
+/// Factor the exponential speed curve multiplies the move period by per point.
+const EXPONENTIAL_SPEEDUP: f32 = 0.97;
+/// The stepped speed curve takes STEP_SPEEDUP seconds off the move period every
+/// STEP_SPEEDUP_POINTS points.
+const STEP_SPEEDUP: f32 = 0.02;
+const STEP_SPEEDUP_POINTS: u32 = 5;
+
+/// Returns the move period at `score` on `curve`, starting from `base` at a score of
+/// 0 and never dropping below the fastest allowed period.
+fn curve_period(curve: SpeedCurve, base: f32, score: u32) -> f32 {
+    let period = match curve {
+        SpeedCurve::Linear => base - LINEAR_SPEEDUP * score as f32,
+        SpeedCurve::Exponential => base * EXPONENTIAL_SPEEDUP.powi(score as i32),
+        SpeedCurve::Stepped => base - STEP_SPEEDUP * (score / STEP_SPEEDUP_POINTS) as f32,
+    };
+    period.max(MIN_MOVE_PERIOD)
+}
+
+/// Returns how far `score` is from the last speed-up of the stepped curve toward the
+/// next one, from 0 just after a step to nearly 1 just before it.
+///
+/// Once the curve has reached the fastest allowed period there are no more steps, and
+/// the bar stays full. A last step taking off less than half a notch counts as none,
+/// so float rounding near the floor doesn't leave one that changes nothing.
+fn speedup_progress(base: f32, score: u32) -> f32 {
+    let next_step = (score / STEP_SPEEDUP_POINTS + 1) * STEP_SPEEDUP_POINTS;
+    let speedup = curve_period(SpeedCurve::Stepped, base, score) - curve_period(SpeedCurve::Stepped, base, next_step);
+    if speedup < STEP_SPEEDUP / 2.0 {
+        return 1.0;
+    }
+    (score % STEP_SPEEDUP_POINTS) as f32 / STEP_SPEEDUP_POINTS as f32
+}
+
+/// Size of the HUD's speed-up progress bar, in pixels, and the gap above it.
+const SPEED_BAR_WIDTH: f32 = 120.0;
+const SPEED_BAR_HEIGHT: f32 = 6.0;
+const SPEED_BAR_GAP: f32 = 4.0;
+
+/// The edge warning shows once the head is this many cells from a wall, and is
+/// strongest beside it.
+const EDGE_WARNING_RANGE: i32 = 4;
+/// Opacity of the edge warning at its strongest, how deep its fade reaches into the
+/// grid (in cells) and in how many bands, and the reduced-motion line's thickness.
+const EDGE_WARNING_ALPHA: f32 = 0.5;
+const EDGE_WARNING_DEPTH: f32 = 2.0;
+const EDGE_WARNING_BANDS: usize = 6;
+const EDGE_WARNING_LINE: f32 = 3.0;
+
+/// Returns how many free cells lie between `head` and each edge of the grid, as the
+/// edge's side: 0 means the next step that way hits the wall.
+fn wall_distances(head: Point, grid_width: i32, grid_height: i32) -> [(Direction, i32); 4] {
+    [
+        (Direction::Up, head.y),
+        (Direction::Down, grid_height - 1 - head.y),
+        (Direction::Left, head.x),
+        (Direction::Right, grid_width - 1 - head.x),
+    ]
+}
+
+/// Returns how strongly to warn of a wall `distance` free cells away: 1 right beside
+/// it, fading linearly to 0 at EDGE_WARNING_RANGE and beyond.
+fn edge_warning_intensity(distance: i32) -> f32 {
+    (1.0 - distance as f32 / EDGE_WARNING_RANGE as f32).clamp(0.0, 1.0)
+}
+
+/// Grid sizes F5 cycles through: small, medium (the default) and large.
+const GRID_PRESETS: [(i32, i32); 3] = [(12, 12), (20, 20), (30, 30)];
+
+/// Returns the preset grid size after `current`: the smallest preset with more cells,
+/// or back to the first from the largest.
+fn next_grid_preset(current: (i32, i32)) -> (i32, i32) {
+    GRID_PRESETS
+        .into_iter()
+        .find(|&(w, h)| w * h > current.0 * current.1)
+        .unwrap_or(GRID_PRESETS[0])
+}
+
+/// Random guesses at a free cell before falling back to listing every free cell.
+///
+/// Guessing is the fastest of the strategies in `spawn` at every fill level
+/// `cargo bench --bench spawn` times: with 99% of a 20x20 board taken, a guessed
+/// cell averages about 4µs against 8µs to list the free ones, so the list is only
+/// there to give up cleanly on a full board.
+const RANDOM_SPAWN_TRIES: usize = 100;
+
+/// Number of random free cells the "far", "open" and "tail" spawn strategies choose between.
+const FAR_SPAWN_CANDIDATES: usize = 8;
+
+/// What ended a run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DeathCause {
+    // Ran off the edge of the grid.
+    Wall,
+    // Ran into one of the level's wall cells.
+    Obstacle,
+    // Ran into its own body.
+    SelfCollision,
+    // Ran out of time in time attack mode.
+    TimeUp,
+    // Filled the board, leaving no room for more food (a win).
+    BoardFull,
+}
+
+impl DeathCause {
+    /// Returns a short, stable name for the cause, as used in stats files.
+    fn label(self) -> &'static str {
+        match self {
+            DeathCause::Wall => "wall",
+            DeathCause::Obstacle => "obstacle",
+            DeathCause::SelfCollision => "self",
+            DeathCause::TimeUp => "time",
+            DeathCause::BoardFull => "board_full",
+        }
+    }
+}
+
+/// Maps a point that has left the grid back onto it from the opposite edge.
+fn wrap_point(p: Point, grid_width: i32, grid_height: i32) -> Point {
+    Point {
+        x: p.x.rem_euclid(grid_width),
+        y: p.y.rem_euclid(grid_height),
+    }
+}
+
+/// Returns the direction of a one-cell move from `from` to `to` that wrapped around
+/// the grid edge, or None if the move stayed on the grid (or wasn't a single step).
+fn wrap_crossing(from: Point, to: Point, grid_width: i32, grid_height: i32) -> Option<Direction> {
+    // On a very narrow grid a cell can be a neighbour both ways; prefer the plain step.
+    if ai::direction_between(from, to).is_some() {
+        return None;
+    }
+    ai::DIRECTIONS.into_iter().find(|&dir| {
+        let next = ai::step(from, dir);
+        !ai::in_bounds(next, grid_width, grid_height) && wrap_point(next, grid_width, grid_height) == to
+    })
+}
+
+/// Longest a single frame may advance the game, in seconds. After a stall (say the
+/// machine slept) the game carries on from where it was instead of jumping ahead.
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// Collects the cells taken by the snake or by anything in `others`, for spawning around.
+fn occupied_cells(snake: &[Point], others: &[Point]) -> HashSet<Point> {
+    snake.iter().chain(others).copied().collect()
+}
+
+/// Limits a frame's elapsed time to MAX_FRAME_TIME.
+fn clamp_frame_time(dt: f32) -> f32 {
+    dt.min(MAX_FRAME_TIME)
+}
+
+/// Shortest and longest allowed time between snake moves, in seconds.
+const MIN_MOVE_PERIOD: f32 = 0.02;
+const MAX_MOVE_PERIOD: f32 = 2.0;
+/// How much each speed key press changes the move period, in seconds.
+const MOVE_PERIOD_STEP: f32 = 0.02;
+/// Time between moves when none is configured, in seconds.
+const DEFAULT_MOVE_PERIOD: f32 = 0.2;
+
+/// Returns the snake's color at the given move period: plain green at the default
+/// speed or slower, warming towards orange as it speeds up to the fastest allowed.
+fn speed_color(move_period: f32) -> Color {
+    let heat = ((DEFAULT_MOVE_PERIOD - move_period) / (DEFAULT_MOVE_PERIOD - MIN_MOVE_PERIOD)).clamp(0.0, 1.0);
+    let (cool, warm) = ((0.0, 1.0, 0.0), (1.0, 0.6, 0.0));
+    let lerp = |a: f32, b: f32| a + (b - a) * heat;
+    Color::new(lerp(cool.0, warm.0), lerp(cool.1, warm.1), lerp(cool.2, warm.2), 1.0)
+}
+
+/// Opacity of the tail in trail fade mode; the head is fully opaque.
+const TRAIL_MIN_ALPHA: f32 = 0.15;
+
+/// Returns the opacity of segment `index` (0 is the head) of a snake `length` long in
+/// trail fade mode, falling evenly from the head to TRAIL_MIN_ALPHA at the tail.
+fn trail_alpha(index: usize, length: usize) -> f32 {
+    if length <= 1 {
+        return 1.0;
+    }
+    let along = index.min(length - 1) as f32 / (length - 1) as f32;
+    1.0 - along * (1.0 - TRAIL_MIN_ALPHA)
+}
+
+/// How long (in seconds) a step that would go straight waits for a late turn.
+const DEFAULT_GRACE: f32 = 0.03;
+
+/// Returns true if `item` is within the magnet's reach of `head`, measured in steps
+/// along the grid. A radius of 0 reaches only the head's own cell.
+fn within_magnet(head: Point, item: Point, radius: u32) -> bool {
+    (head.x - item.x).unsigned_abs() + (head.y - item.y).unsigned_abs() <= radius
+}
+
+/// Returns true if `item` is straight ahead of a head that has just moved onto `head`
+/// going `direction`: on the head's cell, or further along its line within `radius`.
+fn head_on(head: Point, direction: Direction, item: Point, radius: u32) -> bool {
+    let (ahead, across) = match direction {
+        Direction::Up => (head.y - item.y, item.x - head.x),
+        Direction::Down => (item.y - head.y, item.x - head.x),
+        Direction::Left => (head.x - item.x, item.y - head.y),
+        Direction::Right => (item.x - head.x, item.y - head.y),
+    };
+    across == 0 && ahead >= 0 && ahead.unsigned_abs() <= radius
+}
+
+/// Returns true if the next step is due `since_step` seconds after the last one.
+///
+/// A step with a turn waiting (pressed `turn_pressed_at` seconds after the last step)
+/// is taken once the period is up. One that would go straight is held for the grace
+/// window too, so a turn pressed just late still makes it.
+fn step_due(since_step: f32, period: f32, grace: f32, turn_pressed_at: Option<f32>) -> bool {
+    match turn_pressed_at {
+        Some(_) => since_step > period,
+        None => since_step > period + grace,
+    }
+}
+
+/// Returns the period of a move in `direction`: `base` horizontally, and `base` scaled
+/// by `vertical_factor` (0 meaning unscaled) vertically, kept within the allowed range.
+fn axis_period(base: f32, vertical_factor: f32, direction: Direction) -> f32 {
+    match direction {
+        Direction::Left | Direction::Right => base,
+        Direction::Up | Direction::Down if vertical_factor > 0.0 => {
+            (base * vertical_factor).clamp(MIN_MOVE_PERIOD, MAX_MOVE_PERIOD)
+        }
+        Direction::Up | Direction::Down => base,
+    }
+}
+
+/// Chance that eating a food brings out a bonus food, when bonuses are enabled.
+const BONUS_CHANCE: f64 = 0.25;
+/// Seconds a bonus food stays on the board.
+const BONUS_LIFETIME: f32 = 5.0;
+/// Points a bonus food is worth.
+const BONUS_POINTS: u32 = 5;
+
+/// Seconds between double food events, when they are enabled.
+const EXTRA_FOOD_INTERVAL: f32 = 20.0;
+/// Seconds the extra food of a double food event stays on the board.
+const EXTRA_FOOD_LIFETIME: f32 = 4.0;
+
+/// Returns how many times per second the bonus food blinks with `remaining` of its
+/// lifetime left (as a fraction from 1 down to 0).
+///
+/// It stays steady for the first half, blinks faster and faster after that, and
+/// flashes rapidly in the last fifth.
+fn bonus_blink_frequency(remaining: f32) -> f32 {
+    if remaining >= 0.5 {
+        0.0
+    } else if remaining >= 0.2 {
+        1.0 + (0.5 - remaining) / 0.3 * 3.0
+    } else {
+        8.0
+    }
+}
+
+/// What a food is worth under --length-decay while the snake is short, and how many
+/// segments it takes to knock a point off.
+const DECAY_BASE_POINTS: u32 = 5;
+const DECAY_LENGTH_STEP: usize = 10;
+
+/// Returns the points a food is worth to a snake `length` long: one with flat scoring,
+/// and with length decay DECAY_BASE_POINTS less a point per DECAY_LENGTH_STEP segments,
+/// but never less than one.
+fn food_points(length: usize, length_decay: bool) -> u32 {
+    if !length_decay {
+        return 1;
+    }
+    let decay = (length / DECAY_LENGTH_STEP).min(DECAY_BASE_POINTS as usize) as u32;
+    (DECAY_BASE_POINTS - decay).max(1)
+}
+
+/// Style points earned for each step that ends next to a wall.
+const STYLE_POINTS_PER_STEP: u32 = 1;
+
+/// How many ticks back a rewind jumps.
+const REWIND_TICKS: usize = 5;
+
+/// What the head ate on one step.
+#[derive(Clone, Copy, Default)]
+struct Eaten {
+    // The food, whether normal or poison.
+    food: bool,
+    extra_food: bool,
+    // Whether the snake grows from it (it never does from poison or the bonus).
+    grew: bool,
+}
+
+/// A copy of the state that changes from tick to tick, kept so a death can be rewound.
+#[derive(Clone)]
+struct Snapshot {
+    snake: Vec<Point>,
+    direction: Direction,
+    next_direction: Direction,
+    food: Option<Point>,
+    food_kind: FoodKind,
+    bonus: Option<Point>,
+    bonus_time_left: f32,
+    extra_food: Option<Point>,
+    extra_food_time_left: f32,
+    score: u32,
+    style_points: u32,
+    wall_passes: u32,
+    rng: StdRng,
+    elapsed: f32,
+    food_eaten: u32,
+    tick: u64,
+    recorded_steps: usize,
+}
+
+/// Returns the points a rewind costs when taken with the given score.
+fn rewind_penalty(score: u32) -> u32 {
+    score / 2
+}
+
+/// Chance that a newly spawned food is poison, when poison is enabled.
+const POISON_CHANCE: f64 = 0.1;
+/// Maximum number of tail segments removed by eating poison.
+const POISON_SHRINK: usize = 3;
+/// Points deducted for eating poison.
+const POISON_PENALTY: u32 = 1;
+
+/// Score a run needs to keep the daily streak going, unless set on the command line.
+const STREAK_THRESHOLD: u32 = 10;
+
+/// How long the new high score banner stays up, in seconds, and how strongly the
+/// board flashes gold when it appears.
+const CELEBRATION_TIME: f32 = 2.0;
+const CELEBRATION_FLASH_ALPHA: f32 = 0.25;
+
+/// How long (in seconds) the volume readout stays up after a change.
+const VOLUME_DISPLAY_TIME: f32 = 1.5;
+
+/// Vertical gap between lines of centered text, in pixels.
+const LINE_SPACING: f32 = 4.0;
+
+/// What the debug overlay prints in each grid cell.
+#[derive(Clone, Copy, PartialEq)]
+enum CellLabel {
+    Off,
+    // The cell's (x,y) grid coordinate.
+    Coordinates,
+    // The BFS path length from the cell to the food.
+    FoodDistance,
+}
+
+impl CellLabel {
+    /// Returns the next label mode in the cycle.
+    fn next(self) -> CellLabel {
+        match self {
+            CellLabel::Off => CellLabel::Coordinates,
+            CellLabel::Coordinates => CellLabel::FoodDistance,
+            CellLabel::FoodDistance => CellLabel::Off,
+        }
+    }
+}
+
+/// An entry in the pause menu.
+#[derive(Clone, Copy, PartialEq)]
+enum PauseItem {
+    Resume,
+    Restart,
+    Quit,
+}
+
+impl PauseItem {
+    /// Every entry, in the order the menu lists them.
+    const ALL: [PauseItem; 3] = [PauseItem::Resume, PauseItem::Restart, PauseItem::Quit];
+
+    /// Returns the entry below this one, wrapping from the bottom to the top.
+    fn next(self) -> PauseItem {
+        match self {
+            PauseItem::Resume => PauseItem::Restart,
+            PauseItem::Restart => PauseItem::Quit,
+            PauseItem::Quit => PauseItem::Resume,
+        }
+    }
+
+    /// Returns the entry above this one, wrapping from the top to the bottom.
+    fn prev(self) -> PauseItem {
+        match self {
+            PauseItem::Resume => PauseItem::Quit,
+            PauseItem::Restart => PauseItem::Resume,
+            PauseItem::Quit => PauseItem::Restart,
+        }
+    }
+
+    /// Returns the text the menu shows for the entry.
+    fn label(self) -> &'static str {
+        match self {
+            PauseItem::Resume => "Resume",
+            PauseItem::Restart => "Restart",
+            PauseItem::Quit => "Quit",
+        }
+    }
+}
+
+/// How much larger than the normal text the time attack countdown is drawn.
+const COUNTDOWN_SCALE: f32 = 2.0;
+
+/// The window's title when no run is being shown.
+const WINDOW_TITLE: &str = "Snake Game";
+
+/// Returns the window title showing a run's score and length, for streaming overlays
+/// that read it.
+fn live_title(score: u32, length: usize) -> String {
+    format!("{} — Score {}, Len {}", WINDOW_TITLE, score, length)
+}
+
+/// How long the fade-in from black that starts each run lasts, in seconds.
+const TRANSITION_TIME: f32 = 0.3;
+
+/// Slither animation: sideways sway as a fraction of a cell, its speed in radians per
+/// second, and the phase difference between neighbouring segments.
+const WIGGLE_AMPLITUDE: f32 = 0.1;
+const WIGGLE_SPEED: f32 = 8.0;
+const WIGGLE_PHASE_STEP: f32 = 0.7;
+
+/// How many ticks of snake length the sparkline shows.
+const SPARKLINE_TICKS: usize = 100;
+/// Size of the sparkline chart, and its distance from the grid's corner, in pixels.
+const SPARKLINE_SIZE: (f32, f32) = (80.0, 24.0);
+const SPARKLINE_MARGIN: f32 = 10.0;
+
+/// Height of the bonus lifetime bar, in pixels.
+const BONUS_BAR_HEIGHT: f32 = 3.0;
+
+/// Opacity of the marker at the next food's spawn; later previews are fainter.
+const PREVIEW_ALPHA: f32 = 0.3;
+/// Seconds the upcoming foods stay shown after eating, with `--reveal`.
+const REVEAL_TIME: f32 = 1.0;
+/// Seconds the streak to a respawned food takes to fade, and how many cells apart
+/// (across plus down) the old and new food must be for it to show.
+const FOOD_STREAK_TIME: f32 = 0.4;
+const FOOD_STREAK_MIN_DISTANCE: i32 = 6;
+
+/// Largest grid (in cells per side) on which the per-cell debug labels are drawn.
+const CELL_LABEL_MAX_GRID: i32 = 30;
+
+/// Opacity of the debug heatmap tint on the most visited cell.
+const HEATMAP_MAX_ALPHA: f32 = 0.5;
+
 /// The main game state struct containing all necessary fields.
 struct SnakeGame {
     // The snake is represented as a vector of Points; the first element is the head.
     snake: Vec<Point>,
+    // Where each segment was before the last move, for smooth movement.
+    prev_snake: Vec<Point>,
+    // Whether the run is under way; with --wait-for-input it waits for the first turn.
+    started: bool,
     // Current movement direction.
     direction: Direction,
     // Holds the next valid direction (set via user input) to avoid mid-frame reversal.
     next_direction: Direction,
-    // The current food position.
-    food: Point,
+    // The current food position (None once the board is full) and what kind of food it is.
+    food: Option<Point>,
+    food_kind: FoodKind,
+    // The short-lived bonus food, if one is out, and its remaining lifetime in seconds.
+    bonus: Option<Point>,
+    bonus_time_left: f32,
+    // The second food of a double food event, if one is out, its remaining lifetime,
+    // and the seconds until the next event.
+    extra_food: Option<Point>,
+    extra_food_time_left: f32,
+    extra_food_timer: f32,
+    // Wall cells that end the game when hit.
+    obstacles: Vec<Point>,
+    // The level this game was built from, kept so a restart can rebuild it.
+    level: Option<Level>,
+    // Seed the procedural maze (if any) is generated from.
+    level_seed: u64,
+    // Today's date (`YYYY-MM-DD`) when playing the daily challenge.
+    daily_date: Option<String>,
     // The player’s score.
     score: u32,
+    // Style points from steps that ended next to a wall, kept apart from the score.
+    style_points: u32,
+    // Passes through the edge this run that paid the wall toll.
+    wall_passes: u32,
     // Grid dimensions (number of cells horizontally and vertically).
     grid_width: i32,
     grid_height: i32,
     // Timer used to control snake movement timing.
     move_timer: f32,
+    // When the turn waiting in next_direction was pressed, on the move_timer; without
+    // one, a step that comes due is held for the grace window.
+    turn_pressed_at: Option<f32>,
     // Time between snake moves (in seconds).
     move_period: f32,
-    // Game-over flag.
+    // Game-over flag, and what caused it.
     game_over: bool,
+    death_cause: Option<DeathCause>,
+    // Random number generator for food placement, and the seed it started from.
+    rng: StdRng,
+    seed: u64,
+    // Separate randomness for the autopilot's mistakes, so they don't move the food.
+    ai_rng: StdRng,
+    // Seconds of play and number of foods eaten this run.
+    elapsed: f32,
+    food_eaten: u32,
+    // Steps taken this run, counting from 0 at the start: the clock to timestamp game
+    // events by. Only update_snake moves it forward.
+    tick: u64,
+    // Recent per-tick snapshots (oldest first), and how many rewinds are left this run.
+    history: VecDeque<Snapshot>,
+    rewinds_left: u32,
+    // Crashes left that respawn the snake rather than end the run.
+    lives_left: u32,
+    // When set, the AI steers the snake instead of the keyboard.
+    autopilot: bool,
+    // When set, the AI's planned route to the food is drawn over the board, and the
+    // route as planned after the last change to the board (None when not drawn).
+    show_ai_path: bool,
+    ai_path: Option<Vec<Point>>,
+    // In plan mode the snake is frozen while the player queues up moves.
+    planning: bool,
+    // Directions to take on upcoming ticks, one per tick, oldest first.
+    planned_turns: VecDeque<Direction>,
+    // While paused, the highlighted pause menu entry (None when playing).
+    pause_menu: Option<PauseItem>,
+    // The debug console's input line while it is open (only under --debug), and what
+    // the last command it ran said back.
+    console: Option<String>,
+    console_reply: String,
+    // What the debug overlay labels each cell with (only under --debug).
+    cell_label: CellLabel,
+    // How many times the head has stepped onto each cell this run (row-major), and
+    // whether the heatmap of those counts is drawn (only under --debug).
+    visit_counts: Vec<u32>,
+    show_heatmap: bool,
+    // Where the score HUD is drawn, and whether it is drawn at all.
+    hud_position: HudPosition,
+    hud_visible: bool,
+    // Whether the HUD also shows the head's coordinate and directions.
+    show_head_info: bool,
+    // Whether row and column numbers are drawn along the grid's top and left edges.
+    show_rulers: bool,
+    // Where the mouse is over the window, for --hover (None once it leaves).
+    mouse_position: Option<(f32, f32)>,
+    // How many segments down the side panel's list is scrolled.
+    panel_scroll: usize,
+    // Mirror mode: every arrow key steers the opposite way.
+    invert_controls: bool,
+    // Session options from the command line.
+    options: Options,
+    // Persistent preferences, and where to save them (None if there's nowhere to save).
+    settings: Settings,
+    settings_path: Option<PathBuf>,
+    // Whether SNAKE_REDUCED_MOTION asked for reduced motion when the game started.
+    // Kept out of the settings so that saving them doesn't store the variable's value.
+    env_reduced_motion: bool,
+    // Records from past runs, and where they are kept.
+    scores: Scores,
+    scores_path: Option<PathBuf>,
+    // Whether this run has passed the high score it started with, and the time left
+    // on the celebration shown when it did.
+    high_score_beaten: bool,
+    celebration_timer: f32,
+    // Time left on the flash of upcoming foods since the food was last eaten.
+    reveal_timer: f32,
+    // Where the food last jumped from and to, and the time left on the streak between them.
+    food_streak: Option<(Point, Point)>,
+    food_streak_timer: f32,
+    // What the snake burst into when it crashed, shown before the game-over screen.
+    particles: Vec<Particle>,
+    // Days in a row with a run above the streak threshold, and where they are kept.
+    streak: Streak,
+    streak_path: Option<PathBuf>,
+    // Loaded sounds, and the effects queued by the simulation for the next frame.
+    audio: Audio,
+    pending_sounds: Vec<Sound>,
+    // Time left to show the volume readout.
+    volume_display_timer: f32,
+    // While fullscreen, the window size to go back to (None when windowed).
+    windowed_size: Option<(f32, f32)>,
+    // The score and length the window title shows (None for the plain title), so it is
+    // only set again when they change, and whether something it shows may have changed
+    // since it was last looked at.
+    titled: Option<(u32, usize)>,
+    title_stale: bool,
+    // Where the run is autosaved (None when autosave is off), and time since the last autosave.
+    autosave_path: Option<PathBuf>,
+    // Where frames are dumped under --record-frames (None when off, or after a frame
+    // failed to write), and how many have been written this session.
+    frame_dir: Option<PathBuf>,
+    frames_recorded: u32,
+    autosave_timer: f32,
+    // Commands arriving from stdin under --stdin-input.
+    script_input: Option<mpsc::Receiver<ScriptCommand>>,
+    // An autosave found at startup, waiting for the player to resume or discard it.
+    resume_offer: Option<SaveState>,
+    // Seconds since the game started, driving purely cosmetic animation.
+    anim_time: f32,
+    // Time left in the fade-in that starts the run; the snake doesn't move until it ends.
+    transition_left: f32,
+    // The snake's length over recent ticks, for the sparkline overlay.
+    length_history: Sparkline,
+    // This run's movement (recorded under --ghost), the best recorded run replayed
+    // alongside it, and where the best run is kept.
+    recording: Replay,
+    ghost: Option<Replay>,
+    ghost_path: Option<PathBuf>,
+    // A 1x1 white square, stretched and tinted to draw every filled rectangle. It needs
+    // a graphics context, so it is built on the first frame.
+    unit_rect: Option<Mesh>,
+    // The board drawn so far under --dirty-rects, also built on the first frame.
+    board_cache: Option<BoardCache>,
+    // Text shown on screen, from the locale file if there is one.
+    strings: Strings,
+    // Font and pixel size used for all text.
+    font: Font,
+    font_size: f32,
 }
 
 impl SnakeGame {
     /// Creates a new game state with an initial snake position and randomly placed food.
-    fn new(grid_width: i32, grid_height: i32) -> SnakeGame {
+    fn new(grid_width: i32, grid_height: i32, options: Options) -> SnakeGame {
         // Start the snake in the center of the grid.
         let init_pos = Point {
             x: grid_width / 2,
             y: grid_height / 2,
         };
-        let snake = vec![init_pos];
-        let food = SnakeGame::generate_food(&snake, grid_width, grid_height);
-        SnakeGame {
+        let direction = options.start_dir;
+        let snake = initial_body(init_pos, direction, options.start_length, grid_width, grid_height);
+        // The daily challenge takes both seeds from the date, read afresh on each
+        // restart so a run started after midnight gets the new day's board.
+        let daily_date = options.daily.then(|| streak::date_string(streak::day_of(unix_time())));
+        let seed = match &daily_date {
+            Some(date) => daily_seed(date),
+            None => options.seed.unwrap_or_else(rand::random),
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        let font_size = options.font_size.unwrap_or(Font::DEFAULT_FONT_SCALE);
+        // The maze is generated from the maze seed string if there is one, and otherwise
+        // from the run's seed, so a fixed --seed also gives a fixed maze.
+        let level_seed = daily_date.as_deref().or(options.maze_seed.as_deref()).map_or(seed, level::seed_from_str);
+        let mut obstacles = if options.maze {
+            level::generate_maze(level_seed, grid_width, grid_height, init_pos, direction)
+        } else {
+            Vec::new()
+        };
+        // A long starting body may reach past the maze's cleared area.
+        obstacles.retain(|p| !snake.contains(p));
+        // Zen mode has no food at all, so the snake never grows.
+        let food = if options.zen {
+            None
+        } else {
+            SnakeGame::generate_food(&mut rng, options.spawn, &snake, &obstacles, grid_width, grid_height)
+        };
+        let recording = Replay::new(&snake, grid_width, grid_height);
+        let mut game = SnakeGame {
+            prev_snake: snake.clone(),
+            started: !options.wait_for_input,
             snake,
-            direction: Direction::Right,
-            next_direction: Direction::Right,
+            direction,
+            next_direction: direction,
             food,
+            food_kind: FoodKind::Normal,
+            bonus: None,
+            bonus_time_left: 0.0,
+            extra_food: None,
+            extra_food_time_left: 0.0,
+            extra_food_timer: EXTRA_FOOD_INTERVAL,
+            obstacles,
+            level: None,
+            level_seed,
+            daily_date,
             score: 0,
+            style_points: 0,
+            wall_passes: 0,
             grid_width,
             grid_height,
             move_timer: 0.0,
-            move_period: 0.2, // Move every 0.2 seconds.
+            turn_pressed_at: None,
+            move_period: MAX_MOVE_PERIOD, // Replaced below, through the clamp.
             game_over: false,
-        }
+            death_cause: None,
+            rng,
+            seed,
+            ai_rng: StdRng::seed_from_u64(seed.wrapping_add(1)),
+            elapsed: 0.0,
+            food_eaten: 0,
+            tick: 0,
+            history: VecDeque::new(),
+            rewinds_left: options.rewinds,
+            lives_left: options.lives,
+            autopilot: false,
+            show_ai_path: false,
+            ai_path: None,
+            planning: false,
+            planned_turns: VecDeque::new(),
+            pause_menu: None,
+            console: None,
+            console_reply: String::new(),
+            cell_label: CellLabel::Off,
+            visit_counts: vec![0; (grid_width * grid_height) as usize],
+            show_heatmap: false,
+            hud_position: HudPosition::TopLeft,
+            hud_visible: true,
+            show_head_info: options.head_info,
+            mouse_position: None,
+            show_rulers: options.rulers,
+            panel_scroll: 0,
+            invert_controls: options.invert_controls,
+            options,
+            settings: Settings::default(),
+            settings_path: None,
+            env_reduced_motion: settings::reduced_motion_from_env(),
+            scores: Scores::default(),
+            scores_path: None,
+            high_score_beaten: false,
+            celebration_timer: 0.0,
+            reveal_timer: 0.0,
+            food_streak: None,
+            food_streak_timer: 0.0,
+            particles: Vec::new(),
+            streak: Streak::default(),
+            streak_path: None,
+            audio: Audio::default(),
+            pending_sounds: Vec::new(),
+            volume_display_timer: 0.0,
+            windowed_size: None,
+            titled: None,
+            title_stale: true,
+            autosave_path: None,
+            frame_dir: None,
+            frames_recorded: 0,
+            autosave_timer: 0.0,
+            script_input: None,
+            resume_offer: None,
+            anim_time: 0.0,
+            transition_left: TRANSITION_TIME,
+            length_history: Sparkline::new(SPARKLINE_TICKS),
+            recording,
+            ghost: None,
+            ghost_path: None,
+            unit_rect: None,
+            board_cache: None,
+            strings: Strings::default(),
+            font: Font::default(),
+            font_size,
+        };
+        // Move every 0.2 seconds unless configured otherwise.
+        game.set_move_period(game.options.move_period.unwrap_or(DEFAULT_MOVE_PERIOD));
+        game
     }
 
-    /// Generates a new food location that is not currently occupied by the snake.
-    fn generate_food(snake: &Vec<Point>, grid_width: i32, grid_height: i32) -> Point {
-        let mut rng = rand::thread_rng();
-        loop {
-            let food = Point {
-                x: rng.gen_range(0..grid_width),
-                y: rng.gen_range(0..grid_height),
-            };
-            // Ensure the food does not appear on the snake.
-            if !snake.contains(&food) {
-                return food;
-            }
+    /// Sets the time between moves, clamped to a playable range.
+    ///
+    /// All changes to the speed go through here, so the snake can neither stall nor
+    /// move every single frame.
+    fn set_move_period(&mut self, period: f32) {
+        if period.is_nan() {
+            return;
         }
+        self.move_period = period.clamp(MIN_MOVE_PERIOD, MAX_MOVE_PERIOD);
     }
 
-    /// Updates the snake’s position and checks for collisions and food consumption.
-    fn update_snake(&mut self) {
-        if self.game_over {
-            return;
+    /// Sets the move period for the current score from the speed curve, if there is one.
+    ///
+    /// The curve starts from the configured move period, so this also undoes any
+    /// change made with the speed keys since the score last changed.
+    fn recompute_speed(&mut self) {
+        if let Some(curve) = self.options.speed_curve {
+            let base = self.options.move_period.unwrap_or(DEFAULT_MOVE_PERIOD);
+            self.set_move_period(curve_period(curve, base, self.score));
         }
+    }
 
-        // Update the current direction from the next_direction (set by user input).
-        self.direction = self.next_direction;
+    /// Returns the time the next move takes, which depends on the axis it moves along.
+    ///
+    /// Horizontal moves take the base move period and vertical ones take it scaled by
+    /// the vertical factor, so the two axes can run at different speeds.
+    fn step_period(&self) -> f32 {
+        axis_period(self.move_period, self.options.vertical_factor, self.next_direction)
+    }
 
-        // Compute the new head position based on the current direction.
-        let mut new_head = *self
-            .snake
-            .first()
-            .expect("Snake should always have at least one segment");
-        match self.direction {
-            Direction::Up => new_head.y -= 1,
-            Direction::Down => new_head.y += 1,
-            Direction::Left => new_head.x -= 1,
-            Direction::Right => new_head.x += 1,
+    /// Creates a new game laid out from a loaded level.
+    fn from_level(level: Level, options: Options) -> SnakeGame {
+        let mut game = SnakeGame::new(level.width, level.height, options);
+        game.obstacles = level.obstacles.clone();
+        if !level.snake.is_empty() {
+            game.snake = level.snake.clone();
+            game.prev_snake = level.snake.clone();
+            game.recording = Replay::new(&level.snake, level.width, level.height);
+            // Face away from the neck so the first step doesn't run into the body.
+            if let [head, neck, ..] = game.snake[..]
+                && let Some(dir) = ai::direction_between(neck, head)
+            {
+                game.direction = dir;
+                game.next_direction = dir;
+            }
         }
-
-        // Check for collision with the boundaries of the grid.
-        if new_head.x < 0
-            || new_head.x >= self.grid_width
-            || new_head.y < 0
-            || new_head.y >= self.grid_height
-        {
-            self.game_over = true;
-            return;
+        if !game.options.zen {
+            game.food = SnakeGame::generate_food(
+                &mut game.rng,
+                game.options.spawn,
+                &game.snake,
+                &game.obstacles,
+                level.width,
+                level.height,
+            );
         }
+        game.level = Some(level);
+        game
+    }
 
-        // Check for collision with the snake's own body.
-        if self.snake.contains(&new_head) {
-            self.game_over = true;
-            return;
+    /// Starts a fresh game on the same grid, keeping the player's toggles.
+    fn restart(&mut self) {
+        self.print_summary();
+        self.start_over();
+    }
+
+    /// Replaces the game with a fresh one on the current grid, carrying over the
+    /// player's toggles, records and loaded resources.
+    fn start_over(&mut self) {
+        let mut game = match &self.level {
+            Some(level) => SnakeGame::from_level(level.clone(), self.options.clone()),
+            None => SnakeGame::new(self.grid_width, self.grid_height, self.options.clone()),
+        };
+        game.autopilot = self.autopilot;
+        game.show_ai_path = self.show_ai_path;
+        game.cell_label = self.cell_label;
+        game.show_heatmap = self.show_heatmap;
+        game.hud_position = self.hud_position;
+        game.hud_visible = self.hud_visible;
+        game.show_head_info = self.show_head_info;
+        game.show_rulers = self.show_rulers;
+        game.mouse_position = self.mouse_position;
+        game.invert_controls = self.invert_controls;
+        game.settings = self.settings.clone();
+        game.settings_path = self.settings_path.take();
+        game.scores = self.scores.clone();
+        game.scores_path = self.scores_path.take();
+        game.streak = self.streak.clone();
+        game.streak_path = self.streak_path.take();
+        game.autosave_path = self.autosave_path.take();
+        game.frame_dir = self.frame_dir.take();
+        game.script_input = self.script_input.take();
+        game.frames_recorded = self.frames_recorded;
+        game.ghost = self.ghost.take();
+        game.ghost_path = self.ghost_path.take();
+        game.audio = std::mem::take(&mut self.audio);
+        game.strings = self.strings.clone();
+        game.font = self.font;
+        game.unit_rect = self.unit_rect.take();
+        game.board_cache = self.board_cache.take();
+        game.windowed_size = self.windowed_size;
+        game.titled = self.titled;
+        *self = game;
+        self.refresh_ai_path();
+    }
+
+    /// Generates a new food location that is not currently occupied by the snake or a wall.
+    ///
+    /// Returns None when there is no free cell left.
+    fn generate_food(
+        rng: &mut StdRng,
+        strategy: SpawnStrategy,
+        snake: &[Point],
+        obstacles: &[Point],
+        grid_width: i32,
+        grid_height: i32,
+    ) -> Option<Point> {
+        let occupied = occupied_cells(snake, obstacles);
+        let mut free_cell = || SnakeGame::random_free_cell(rng, &occupied, grid_width, grid_height);
+        match strategy {
+            SpawnStrategy::Uniform => free_cell(),
+            SpawnStrategy::Far => {
+                // Give the player more reaction time by spawning away from the head.
+                let Some(&head) = snake.first() else {
+                    return free_cell();
+                };
+                (0..FAR_SPAWN_CANDIDATES)
+                    .filter_map(|_| free_cell())
+                    .max_by_key(|p| (p.x - head.x).abs() + (p.y - head.y).abs())
+            }
+            SpawnStrategy::Open => {
+                // Keep food out of pockets that are hard to get into.
+                let blocked = |p: Point| occupied.contains(&p);
+                (0..FAR_SPAWN_CANDIDATES)
+                    .filter_map(|_| free_cell())
+                    .max_by_key(|&p| ai::region_size(p, grid_width, grid_height, blocked))
+            }
+            SpawnStrategy::NearTail => {
+                // Make the player cross back past their own body to eat.
+                let Some(&tail) = snake.last() else {
+                    return free_cell();
+                };
+                (0..FAR_SPAWN_CANDIDATES)
+                    .filter_map(|_| free_cell())
+                    .min_by_key(|p| (p.x - tail.x).abs() + (p.y - tail.y).abs())
+            }
         }
+    }
 
-        // Insert the new head position at the beginning of the snake vector.
-        self.snake.insert(0, new_head);
+    /// Picks a uniformly random cell that is not in `occupied`.
+    ///
+    /// Returns None when every cell is taken.
+    fn random_free_cell(
+        rng: &mut StdRng,
+        occupied: &HashSet<Point>,
+        grid_width: i32,
+        grid_height: i32,
+    ) -> Option<Point> {
+        // Guessing is quick while the board is mostly empty; on a crowded board,
+        // pick from the cells that are actually free.
+        spawn::by_rejection(rng, occupied, grid_width, grid_height, RANDOM_SPAWN_TRIES)
+            .or_else(|| spawn::by_collecting(rng, occupied, grid_width, grid_height))
+    }
 
-        // Check if the snake has eaten the food.
-        if new_head == self.food {
-            self.score += 1;
-            // Spawn new food at a random location.
-            self.food = SnakeGame::generate_food(&self.snake, self.grid_width, self.grid_height);
+    /// Places a fresh food on the board, occasionally making it poison when enabled.
+    ///
+    /// If there is nowhere left to put it, the run ends as a win.
+    fn spawn_food(&mut self) {
+        let old_food = self.food;
+        // Food never lands on the bonus or the extra food.
+        let mut taken = self.obstacles.clone();
+        taken.extend(self.bonus);
+        taken.extend(self.extra_food);
+        self.food = SnakeGame::generate_food(
+            &mut self.rng,
+            self.options.spawn,
+            &self.snake,
+            &taken,
+            self.grid_width,
+            self.grid_height,
+        );
+        if self.food.is_none() {
+            self.end_game(DeathCause::BoardFull);
+            return;
+        }
+        self.food_kind = if self.options.poison && self.rng.gen_bool(POISON_CHANCE) {
+            FoodKind::Poison
         } else {
-            // Remove the tail segment to move the snake forward.
-            self.snake.pop();
+            FoodKind::Normal
+        };
+        if self.options.food_streak
+            && !self.reduced_motion()
+            && let (Some(from), Some(to)) = (old_food, self.food)
+            && (to.x - from.x).abs() + (to.y - from.y).abs() >= FOOD_STREAK_MIN_DISTANCE
+        {
+            self.food_streak = Some((from, to));
+            self.food_streak_timer = FOOD_STREAK_TIME;
         }
     }
-}
 
-/// Implementing ggez’s EventHandler trait to define game behavior.
-impl EventHandler for SnakeGame {
-    /// Updates the game logic on each frame.
-    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        // Get the time elapsed since the last update.
-        let dt = ggez::timer::delta(ctx).as_secs_f32();
-        self.move_timer += dt;
-        // Move the snake when the move_timer exceeds move_period.
-        if self.move_timer > self.move_period {
-            self.move_timer = 0.0;
-            self.update_snake();
+    /// Runs down the streak to a respawned food, dropping it once it has faded out.
+    fn tick_food_streak(&mut self, dt: f32) {
+        self.food_streak_timer = (self.food_streak_timer - dt).max(0.0);
+        if self.food_streak_timer == 0.0 {
+            self.food_streak = None;
         }
-        Ok(())
     }
 
-    /// Draws the current game state.
-    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        // Clear the screen to black.
-        graphics::clear(ctx, Color::from_rgb(0, 0, 0));
+    /// Predicts where the next `count` foods will spawn, by replaying the food spawns
+    /// on a copy of the RNG.
+    ///
+    /// The first prediction assumes the snake eats the current food from where it is
+    /// now, so it is exact on the tick the food is eaten (if there is no magnet to
+    /// stop the head short of it). Later ones also assume the
+    /// snake grows without moving in between and that the bonus and any extra food
+    /// stay as they are, so they can change as the run goes on.
+    fn preview_foods(&self, count: usize) -> Vec<Point> {
+        let (width, height) = (self.grid_width, self.grid_height);
+        let mut rng = self.rng.clone();
+        let mut snake = self.snake.clone();
+        let mut bonus = self.bonus;
+        let mut food = self.food;
+        let mut kind = self.food_kind;
+        let mut previews = Vec::new();
+        while let Some(eaten) = food
+            && previews.len() < count
+        {
+            // Follow update_snake: the head moves onto the food, and poison (or any food
+            // under --no-grow) then takes the tail with it.
+            snake.insert(0, eaten);
+            if kind == FoodKind::Normal && self.options.no_grow {
+                snake.pop();
+            }
+            if kind == FoodKind::Poison {
+                snake.pop();
+                snake.truncate(snake.len() - POISON_SHRINK.min(snake.len() - 1));
+            }
+            // Then follow spawn_food and maybe_spawn_bonus, drawing from the RNG in the same order.
+            let mut taken = self.obstacles.clone();
+            taken.extend(bonus);
+            taken.extend(self.extra_food);
+            food = SnakeGame::generate_food(&mut rng, self.options.spawn, &snake, &taken, width, height);
+            previews.extend(food);
+            let eaten_kind = kind;
+            kind = if self.options.poison && rng.gen_bool(POISON_CHANCE) { FoodKind::Poison } else { FoodKind::Normal };
+            if eaten_kind == FoodKind::Normal && self.options.bonus && bonus.is_none() && rng.gen_bool(BONUS_CHANCE) {
+                taken.extend(food);
+                bonus = SnakeGame::random_free_cell(&mut rng, &occupied_cells(&snake, &taken), width, height);
+            }
+        }
+        previews
+    }
 
-        let cell_size = 20.0;
-        // Draw each segment of the snake.
-        for segment in &self.snake {
-            let rectangle = Mesh::new_rectangle(
-                ctx,
-                DrawMode::fill(),
-                Rect::new_i32(segment.x * cell_size as i32, segment.y * cell_size as i32, cell_size as i32, cell_size as i32),
-                Color::from_rgb(0, 255, 0),
-            )?;
-            graphics::draw(ctx, &rectangle, (ggez::mint::Point2 { x: 0.0, y: 0.0 },))?;
-        }
-
-        // Draw the food as a red square.
-        let food_rect = Mesh::new_rectangle(
-            ctx,
-            DrawMode::fill(),
-            Rect::new_i32(self.food.x * cell_size as i32, self.food.y * cell_size as i32, cell_size as i32, cell_size as i32),
-            Color::from_rgb(255, 0, 0),
-        )?;
-        graphics::draw(ctx, &food_rect, (ggez::mint::Point2 { x: 0.0, y: 0.0 },))?;
-
-        // Draw the current score in the top-left corner.
-        let score_text = Text::new(format!("Score: {}", self.score));
-        graphics::draw(ctx, &score_text, (ggez::mint::Point2 { x: 10.0, y: 10.0 }, Color::from_rgb(255, 255, 255)))?;
-
-        // If the game is over, display a game-over message.
-        if self.game_over {
-            let over_text = Text::new("Game Over! Press R to Restart");
-            let (w, h) = graphics::drawable_size(ctx);
-            let dest_point = ggez::mint::Point2 { x: w / 2.0 - 100.0, y: h / 2.0 };
-            graphics::draw(ctx, &over_text, (dest_point, Color::from_rgb(255, 255, 255)))?;
+    /// Returns how many upcoming foods to mark: the `--preview` count, or more while
+    /// the `--reveal` flash after eating is up.
+    fn preview_count(&self) -> usize {
+        if self.reveal_timer > 0.0 {
+            self.options.preview.max(self.options.reveal)
+        } else {
+            self.options.preview
         }
+    }
 
-        // Present the drawn frame on the screen.
-        graphics::present(ctx)?;
-        Ok(())
+    /// Removes up to `count` segments from the tail, never shrinking below one segment.
+    ///
+    /// Returns the number of segments actually removed.
+    fn shrink_tail(&mut self, count: usize) -> usize {
+        let removable = count.min(self.snake.len().saturating_sub(1));
+        self.snake.truncate(self.snake.len() - removable);
+        debug_assert!(!self.snake.is_empty(), "the snake lost its head");
+        removable
     }
 
-    /// Handles keyboard input for controlling the snake and restarting the game.
-    fn key_down_event(&mut self, _ctx: &mut Context, key: KeyCode, _mods: KeyMods, _repeat: bool) {
-        // Map key presses to direction changes.
-        let new_direction = match key {
-            KeyCode::Up => Some(Direction::Up),
-            KeyCode::Down => Some(Direction::Down),
-            KeyCode::Left => Some(Direction::Left),
-            KeyCode::Right => Some(Direction::Right),
-            // If the game is over, pressing 'R' restarts the game.
-            KeyCode::R if self.game_over => {
-                *self = SnakeGame::new(self.grid_width, self.grid_height);
-                None
-            },
-            _ => None,
-        };
+    /// Ends the current game, recording the run to the stats file if one is configured.
+    fn end_game(&mut self, cause: DeathCause) {
+        self.game_over = true;
+        self.death_cause = Some(cause);
+        self.title_stale = true;
+        // A finished run has nothing left to resume.
+        self.discard_autosave();
+        if cause != DeathCause::BoardFull {
+            self.pending_sounds.push(Sound::Death);
+        }
+        // A crash bursts the snake apart, one particle per segment.
+        let crashed = matches!(cause, DeathCause::Wall | DeathCause::Obstacle | DeathCause::SelfCollision);
+        if crashed && !self.reduced_motion() {
+            self.particles = particles::burst(&mut rand::thread_rng(), &self.snake);
+        }
 
-        if let Some(nd) = new_direction {
-            // Prevent the snake from reversing directly onto itself.
-            if (self.direction == Direction::Up && nd != Direction::Down)
-                && (self.direction == Direction::Down && nd != Direction::Up)
-                || (self.direction == Direction::Left && nd != Direction::Right)
-                || (self.direction == Direction::Right && nd != Direction::Left)
-            {
-                // A more robust check: ensure that the new direction isn’t directly opposite.
-                if !(self.direction == Direction::Up && nd == Direction::Down)
-                    && !(self.direction == Direction::Down && nd == Direction::Up)
-                    && !(self.direction == Direction::Left && nd == Direction::Right)
-                    && !(self.direction == Direction::Right && nd == Direction::Left)
-                {
-                    self.next_direction = nd;
-                }
-            } else if 
-                (self.direction == Direction::Up && nd != Direction::Down)
-                || (self.direction == Direction::Down && nd != Direction::Up)
-                || (self.direction == Direction::Left && nd != Direction::Right)
-                || (self.direction == Direction::Right && nd != Direction::Left)
+        if let Some(path) = &self.options.stats_file {
+            let stats = self.run_stats();
+            if let Err(e) = stats::append_run(path, &stats) {
+                eprintln!("could not write stats to {}: {}", path.display(), e);
+            }
+        }
+        self.keep_if_best_run();
+
+        // Zen runs have no score, so they'd only drag the average down.
+        if !self.options.zen {
+            self.scores.record(self.score);
+            if let Some(path) = &self.scores_path
+                && let Err(e) = self.scores.save(path)
             {
-                self.next_direction = nd;
+                eprintln!("could not save the scores to {}: {}", path.display(), e);
+            }
+        }
+
+        if let Some(path) = &self.streak_path {
+            let threshold = self.options.streak_threshold.unwrap_or(STREAK_THRESHOLD);
+            self.streak.record(streak::day_of(unix_time()), self.score, threshold);
+            if let Err(e) = self.streak.save(path) {
+                eprintln!("could not save the streak to {}: {}", path.display(), e);
             }
         }
     }
-}
 
-/// The main function sets up the game window and starts the event loop.
-fn main() -> GameResult {
-    // Create a new ggez Context and event loop.
-    let (mut ctx, event_loop) = ContextBuilder::new("snake_game", "Author")
-        .window_setup(ggez::conf::WindowSetup::default().title("Snake Game"))
-        .window_mode(ggez::conf::WindowMode::default().dimensions(400.0, 400.0))
-        .build()?;
+    /// Handles the snake crashing: it respawns if a life is left and there is a safe
+    /// place to put it, and otherwise the game ends.
+    fn crash(&mut self, cause: DeathCause) {
+        if self.lives_left == 0 {
+            self.end_game(cause);
+            return;
+        }
+        let Some((head, direction)) = self.safe_spawn() else {
+            self.end_game(cause);
+            return;
+        };
+        self.lives_left -= 1;
+        self.pending_sounds.push(Sound::Death);
+        self.snake = initial_body(head, direction, self.options.start_length, self.grid_width, self.grid_height);
+        self.prev_snake = self.snake.clone();
+        self.direction = direction;
+        self.next_direction = direction;
+        self.planned_turns.clear();
+        self.move_timer = 0.0;
+        self.turn_pressed_at = None;
+        // The snake jumps, which a replay can't show, so recording starts over here,
+        // and the history from before the crash is no use to rewind into.
+        self.recording = Replay::new(&self.snake, self.grid_width, self.grid_height);
+        self.recording.start_tick = self.tick;
+        self.history.clear();
+    }
 
-    // Our grid is 20x20 cells.
-    let game = SnakeGame::new(20, 20);
-    // Run the game event loop.
-    event::run(ctx, event_loop, game)
+    /// Picks where a respawned snake starts: the head's cell and the way it faces.
+    ///
+    /// The starting body must miss the walls and every food, and the cell ahead of
+    /// the head must open onto at least as many free cells as the snake is long, so it
+    /// isn't dropped into a dead end. The grid's center is tried first, then cells
+    /// further and further out, each facing the starting direction first. Returns None
+    /// if nowhere on the board qualifies.
+    fn safe_spawn(&self) -> Option<(Point, Direction)> {
+        let (w, h) = (self.grid_width, self.grid_height);
+        let center = Point { x: w / 2, y: h / 2 };
+        let mut cells: Vec<Point> = (0..h).flat_map(|y| (0..w).map(move |x| Point { x, y })).collect();
+        cells.sort_by_key(|p| (p.x - center.x).abs() + (p.y - center.y).abs());
+        let start_dir = self.options.start_dir;
+        let mut directions = vec![start_dir];
+        directions.extend(ai::DIRECTIONS.into_iter().filter(|&dir| dir != start_dir));
+        let taken = |p: Point| {
+            self.obstacles.contains(&p) || [self.food, self.extra_food, self.bonus].contains(&Some(p))
+        };
+        cells.into_iter().find_map(|head| {
+            directions.iter().copied().find_map(|dir| {
+                let body = initial_body(head, dir, self.options.start_length, w, h);
+                if body.iter().any(|&p| taken(p)) {
+                    return None;
+                }
+                let mut ahead = ai::step(head, dir);
+                if self.options.wrap {
+                    ahead = wrap_point(ahead, w, h);
+                }
+                if !self.in_bounds(ahead) || taken(ahead) || body.contains(&ahead) {
+                    return None;
+                }
+                let room = ai::region_size(ahead, w, h, |p| taken(p) || body.contains(&p));
+                (room >= body.len()).then_some((head, dir))
+            })
+        })
+    }
+
+    /// Makes this run the ghost to race, and saves it, if it beat the current best.
+    fn keep_if_best_run(&mut self) {
+        if !self.options.ghost || self.ghost.as_ref().is_some_and(|best| best.score >= self.score) {
+            return;
+        }
+        self.recording.score = self.score;
+        if let Some(path) = &self.ghost_path
+            && let Err(e) = self.recording.write(path)
+        {
+            eprintln!("could not save the best run to {}: {}", path.display(), e);
+        }
+        self.ghost = Some(self.recording.clone());
+    }
+
+    /// Collects the numbers recorded for this run so far.
+    ///
+    /// A run that hasn't ended yet is recorded as ended by quitting.
+    fn run_stats(&self) -> RunStats {
+        RunStats {
+            timestamp: unix_time(),
+            score: self.score,
+            length: self.snake.len(),
+            elapsed_secs: self.elapsed,
+            food_eaten: self.food_eaten,
+            death_cause: self.death_cause.map_or("quit", DeathCause::label),
+            seed: self.seed,
+        }
+    }
+
+    /// Prints the one-line summary of this run to stdout.
+    ///
+    /// It is printed when the run is left behind, by restarting or quitting, so a
+    /// rewound death doesn't print a second line for the same run.
+    fn print_summary(&self) {
+        println!("{}", stats::summary_line(&self.run_stats()));
+    }
+
+    /// Advances the run's clock, ending the game once a time limit runs out.
+    ///
+    /// The clock stops at the limit, so the run never lasts longer than allowed.
+    fn advance_clock(&mut self, dt: f32) {
+        if self.game_over {
+            return;
+        }
+        self.elapsed += dt;
+        if let Some(limit) = self.options.time_limit
+            && self.elapsed >= limit
+        {
+            self.elapsed = limit;
+            self.end_game(DeathCause::TimeUp);
+        }
+    }
+
+    /// Starts the celebration the first time this run's score passes the high score.
+    ///
+    /// There is nothing to celebrate before any run has scored.
+    fn check_high_score(&mut self) {
+        if self.high_score_beaten || self.scores.high_score == 0 || self.score <= self.scores.high_score {
+            return;
+        }
+        self.high_score_beaten = true;
+        if !self.options.no_celebration {
+            self.celebration_timer = CELEBRATION_TIME;
+        }
+    }
+
+    /// Sometimes puts a bonus food out on a free cell, if bonuses are on and none is out.
+    fn maybe_spawn_bonus(&mut self) {
+        if !self.options.bonus || self.bonus.is_some() || !self.rng.gen_bool(BONUS_CHANCE) {
+            return;
+        }
+        let mut taken = self.obstacles.clone();
+        taken.extend(self.food);
+        taken.extend(self.extra_food);
+        let occupied = occupied_cells(&self.snake, &taken);
+        self.bonus = SnakeGame::random_free_cell(&mut self.rng, &occupied, self.grid_width, self.grid_height);
+        self.bonus_time_left = BONUS_LIFETIME;
+    }
+
+    /// Counts down the bonus food's lifetime, removing it when it runs out.
+    fn tick_bonus(&mut self, dt: f32) {
+        if self.bonus.is_none() || self.game_over || self.planning {
+            return;
+        }
+        self.bonus_time_left = (self.bonus_time_left - dt).max(0.0);
+        if self.bonus_time_left == 0.0 {
+            self.bonus = None;
+        }
+    }
+
+    /// Runs the double food event, if enabled: every so often a second food comes out
+    /// for a few seconds, and it is removed again if it isn't eaten in time.
+    fn tick_extra_food(&mut self, dt: f32) {
+        if !self.options.double_food || self.options.zen || self.game_over || self.planning {
+            return;
+        }
+        if self.extra_food.is_some() {
+            self.extra_food_time_left = (self.extra_food_time_left - dt).max(0.0);
+            if self.extra_food_time_left == 0.0 {
+                self.extra_food = None;
+            }
+            return;
+        }
+        self.extra_food_timer -= dt;
+        if self.extra_food_timer > 0.0 {
+            return;
+        }
+        self.extra_food_timer = EXTRA_FOOD_INTERVAL;
+        let mut taken = self.obstacles.clone();
+        taken.extend(self.food);
+        taken.extend(self.bonus);
+        let occupied = occupied_cells(&self.snake, &taken);
+        let cell = SnakeGame::random_free_cell(&mut self.rng, &occupied, self.grid_width, self.grid_height);
+        self.extra_food = cell;
+        self.extra_food_time_left = EXTRA_FOOD_LIFETIME;
+    }
+
+    /// Returns the seconds left in time attack mode, or None without a time limit.
+    fn time_left(&self) -> Option<f32> {
+        self.options.time_limit.map(|limit| (limit - self.elapsed).max(0.0))
+    }
+
+    /// Captures the run so it can be resumed later.
+    fn save_state(&self) -> SaveState {
+        SaveState {
+            grid_width: self.grid_width,
+            grid_height: self.grid_height,
+            direction: self.direction,
+            next_direction: self.next_direction,
+            food_kind: self.food_kind,
+            score: self.score,
+            elapsed: self.elapsed,
+            food_eaten: self.food_eaten,
+            move_period: self.move_period,
+            rewinds_left: self.rewinds_left,
+            tick: self.tick,
+            seed: self.seed as i64,
+            // Drawn from a copy so saving doesn't disturb this run's food placement.
+            rng_seed: self.rng.clone().r#gen::<u64>() as i64,
+            food: self.food,
+            snake: self.snake.clone(),
+            obstacles: self.obstacles.clone(),
+        }
+    }
+
+    /// Resumes a saved run on this game's board.
+    ///
+    /// Fails, changing nothing, if the save was made on a different size of grid.
+    fn apply_save(&mut self, state: SaveState) -> Result<(), String> {
+        if state.grid_width != self.grid_width || state.grid_height != self.grid_height {
+            return Err(format!(
+                "the save is for a {}x{} grid, not {}x{}",
+                state.grid_width, state.grid_height, self.grid_width, self.grid_height
+            ));
+        }
+        self.prev_snake = state.snake.clone();
+        self.recording = Replay::new(&state.snake, self.grid_width, self.grid_height);
+        self.recording.start_tick = state.tick;
+        self.snake = state.snake;
+        self.direction = state.direction;
+        self.next_direction = state.next_direction;
+        self.food = state.food;
+        self.food_kind = state.food_kind;
+        self.obstacles = state.obstacles;
+        self.score = state.score;
+        self.elapsed = state.elapsed;
+        self.food_eaten = state.food_eaten;
+        self.tick = state.tick;
+        self.set_move_period(state.move_period);
+        self.rewinds_left = state.rewinds_left;
+        self.seed = state.seed as u64;
+        self.rng = StdRng::seed_from_u64(state.rng_seed as u64);
+        self.history.clear();
+        self.move_timer = 0.0;
+        self.refresh_ai_path();
+        Ok(())
+    }
+
+    /// Writes the run to the autosave file, if autosave is on.
+    fn autosave(&mut self) {
+        self.autosave_timer = 0.0;
+        if let Some(path) = &self.autosave_path
+            && let Err(e) = self.save_state().write(path)
+        {
+            eprintln!("could not autosave to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Counts down to the next periodic autosave, saving when it is due.
+    ///
+    /// Returns true if a save was written.
+    fn tick_autosave(&mut self, dt: f32) -> bool {
+        let Some(interval) = self.options.autosave_interval else {
+            return false;
+        };
+        if self.game_over || self.resume_offer.is_some() {
+            return false;
+        }
+        self.autosave_timer += dt;
+        if self.autosave_timer < interval {
+            return false;
+        }
+        self.autosave();
+        true
+    }
+
+    /// Deletes the autosave file, if there is one.
+    fn discard_autosave(&self) {
+        if let Some(path) = &self.autosave_path
+            && let Err(e) = std::fs::remove_file(path)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            eprintln!("could not remove autosave {}: {}", path.display(), e);
+        }
+    }
+
+    /// Plays the sound effects queued since the last frame.
+    fn play_pending_sounds(&mut self, ctx: &Context) {
+        for sound in self.pending_sounds.drain(..) {
+            self.audio.play(ctx, sound);
+        }
+    }
+
+    /// Pushes the configured music and effects volumes to the loaded sounds.
+    fn apply_volumes(&mut self) {
+        self.audio.apply_volumes(
+            audio::effective_volume(self.settings.music_volume, self.settings.music_muted),
+            audio::effective_volume(self.settings.sfx_volume, self.settings.sfx_muted),
+        );
+    }
+
+    /// Applies a changed volume setting: updates the sounds, shows the readout, and saves it.
+    fn volume_changed(&mut self) {
+        self.apply_volumes();
+        self.volume_display_timer = VOLUME_DISPLAY_TIME;
+        self.save_settings();
+    }
+
+    /// Writes the settings to their file, if they have one.
+    fn save_settings(&self) {
+        if let Some(path) = &self.settings_path
+            && let Err(e) = self.settings.save(path)
+        {
+            eprintln!("could not save settings to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Loads the settings from `path` and applies them, keeping `path` to save to.
+    ///
+    /// A file that can't be read or doesn't parse is left alone: the game runs on the
+    /// defaults and saves nothing this session, rather than overwriting the file.
+    fn load_settings(&mut self, path: PathBuf) {
+        match Settings::load(&path) {
+            Ok(settings) => {
+                self.settings = settings;
+                self.settings_path = Some(path);
+            }
+            Err(e) => eprintln!("ignoring settings file {} and not saving over it: {}", path.display(), e),
+        }
+        self.apply_settings();
+    }
+
+    /// Saves everything as it is now, including what the command line turned on for
+    /// this session, so it becomes the default for later sessions too.
+    ///
+    /// The environment's reduced motion is left out: it lasts only while it is set.
+    fn save_current_settings(&mut self) {
+        let settings = &mut self.settings;
+        settings.reduced_motion |= self.options.reduced_motion;
+        settings.hud_position = self.hud_position;
+        settings.hud_visible = self.hud_visible;
+        settings.head_info = self.show_head_info;
+        settings.rulers = self.show_rulers;
+        settings.invert_controls = self.invert_controls;
+        settings.checkerboard |= self.options.checkerboard;
+        settings.shadow_offset = self.options.shadow_offset.or(settings.shadow_offset);
+        settings.shadow_color = self.options.shadow_color.or(settings.shadow_color);
+        if self.level.is_none() {
+            (settings.grid_width, settings.grid_height) = (self.grid_width, self.grid_height);
+        }
+        self.save_settings();
+    }
+
+    /// Returns the saved grid size if the game should switch to it on startup: only
+    /// when neither the command line nor a level chose a size, and the saved size is
+    /// playable and not already the current one.
+    fn saved_grid_size(&self) -> Option<(i32, i32)> {
+        let size = (self.settings.grid_width, self.settings.grid_height);
+        let chosen = self.options.grid_width.is_some() || self.options.grid_height.is_some() || self.level.is_some();
+        (!chosen && size != (self.grid_width, self.grid_height) && validate_dimensions(size.0, size.1).is_ok())
+            .then_some(size)
+    }
+
+    /// Sets the display toggles from the saved settings, with the command line flags
+    /// turning theirs on regardless.
+    fn apply_settings(&mut self) {
+        self.hud_position = self.settings.hud_position;
+        self.hud_visible = self.settings.hud_visible;
+        self.show_head_info = self.options.head_info || self.settings.head_info;
+        self.show_rulers = self.options.rulers || self.settings.rulers;
+        self.invert_controls = self.options.invert_controls || self.settings.invert_controls;
+    }
+
+    /// Captures the current tick's state.
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            snake: self.snake.clone(),
+            direction: self.direction,
+            next_direction: self.next_direction,
+            food: self.food,
+            food_kind: self.food_kind,
+            bonus: self.bonus,
+            bonus_time_left: self.bonus_time_left,
+            extra_food: self.extra_food,
+            extra_food_time_left: self.extra_food_time_left,
+            score: self.score,
+            style_points: self.style_points,
+            wall_passes: self.wall_passes,
+            rng: self.rng.clone(),
+            elapsed: self.elapsed,
+            food_eaten: self.food_eaten,
+            tick: self.tick,
+            recorded_steps: self.recording.steps(),
+        }
+    }
+
+    /// Picks a 90° turn that keeps the head on the board and off anything solid.
+    ///
+    /// Of the two perpendicular directions, the one with more room before the next
+    /// edge is preferred, so the snake follows the wall away from the nearer corner.
+    /// Returns None when both are blocked.
+    fn safe_turn(&self) -> Option<Direction> {
+        let head = *self.snake.first()?;
+        let room = |dir: Direction| match dir {
+            Direction::Up => head.y,
+            Direction::Down => self.grid_height - 1 - head.y,
+            Direction::Left => head.x,
+            Direction::Right => self.grid_width - 1 - head.x,
+        };
+        let mut turns = match self.direction {
+            Direction::Up | Direction::Down => [Direction::Left, Direction::Right],
+            Direction::Left | Direction::Right => [Direction::Up, Direction::Down],
+        };
+        turns.sort_by_key(|&dir| std::cmp::Reverse(room(dir)));
+        turns.into_iter().find(|&dir| {
+            let next = ai::step(head, dir);
+            room(dir) > 0 && !self.obstacles.contains(&next) && !self.snake.contains(&next)
+        })
+    }
+
+    /// Returns the walls this game started with: the level's, the generated maze's, or none.
+    fn original_obstacles(&self) -> Vec<Point> {
+        match &self.level {
+            Some(level) => level.obstacles.clone(),
+            None if self.options.maze => {
+                let start = Point {
+                    x: self.grid_width / 2,
+                    y: self.grid_height / 2,
+                };
+                let (w, h, dir) = (self.grid_width, self.grid_height, self.options.start_dir);
+                let mut obstacles = level::generate_maze(self.level_seed, w, h, start, dir);
+                let body = initial_body(start, dir, self.options.start_length, w, h);
+                obstacles.retain(|p| !body.contains(p));
+                obstacles
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Runs a debug console command against the game, or says why it can't.
+    fn run_command(&mut self, command: Command) -> Result<(), String> {
+        match command {
+            Command::Grow(count) => {
+                // The new segments stack on the tail and unfold as the snake moves.
+                let tail = *self.snake.last().ok_or("the snake has no body")?;
+                let free = (0..self.grid_height)
+                    .flat_map(|y| (0..self.grid_width).map(move |x| Point { x, y }))
+                    .filter(|p| !self.snake.contains(p) && !self.obstacles.contains(p))
+                    .count();
+                if count > free {
+                    return Err(format!("there's only room to grow {} more", free));
+                }
+                self.snake.extend(std::iter::repeat_n(tail, count));
+            }
+            Command::Speed(secs) => self.set_move_period(secs),
+            Command::SpawnFood if self.options.zen => return Err("zen mode has no food".to_string()),
+            Command::SpawnFood => self.spawn_food(),
+            Command::Teleport(x, y) => {
+                let fits = |p: Point| self.in_bounds(p) && !self.obstacles.contains(&p);
+                self.snake = console::shifted(&self.snake, Point { x, y }, fits).ok_or("the snake doesn't fit there")?;
+            }
+        }
+        // The snake changed outside a move, so there is no last step to animate.
+        self.prev_snake = self.snake.clone();
+        self.refresh_ai_path();
+        Ok(())
+    }
+
+    /// Carries out the scripted commands that have arrived since the last frame, as
+    /// the matching keys would.
+    fn run_script_input(&mut self) {
+        let Some(input) = &self.script_input else {
+            return;
+        };
+        let commands: Vec<ScriptCommand> = input.try_iter().collect();
+        for command in commands {
+            self.title_stale = true;
+            // Like the keyboard, scripted input waits while an autosave is on offer.
+            if self.resume_offer.is_some() {
+                continue;
+            }
+            match command {
+                ScriptCommand::Steer(dir) if self.pause_menu.is_none() => self.steer(dir, false),
+                ScriptCommand::Steer(_) => {}
+                ScriptCommand::Pause if self.pause_menu.is_some() => self.pause_menu = None,
+                ScriptCommand::Pause if !self.game_over => self.pause_menu = Some(PauseItem::Resume),
+                ScriptCommand::Pause => {}
+                ScriptCommand::Restart if self.game_over => self.restart(),
+                ScriptCommand::Restart => {}
+            }
+        }
+    }
+
+    /// Removes every wall from the board (a debug aid).
+    fn clear_obstacles(&mut self) {
+        self.obstacles.clear();
+        self.refresh_ai_path();
+    }
+
+    /// Puts the original walls back, skipping any cell now taken by the snake or food.
+    fn regenerate_obstacles(&mut self) {
+        let mut obstacles = self.original_obstacles();
+        obstacles.retain(|p| !self.snake.contains(p) && Some(*p) != self.food && Some(*p) != self.bonus);
+        self.obstacles = obstacles;
+        self.refresh_ai_path();
+    }
+
+    /// Rewinds a lost game a few ticks and resumes play, at a score penalty.
+    ///
+    /// Returns false (and changes nothing) if the game isn't over, no rewinds are left,
+    /// the run ended by running out of time, or there is no history to rewind to.
+    fn rewind(&mut self) -> bool {
+        if !self.game_over || self.rewinds_left == 0 || self.death_cause == Some(DeathCause::TimeUp) {
+            return false;
+        }
+        let Some(snapshot) = self.history.pop_front() else {
+            return false;
+        };
+        let penalty = rewind_penalty(self.score);
+        self.history.clear();
+
+        self.prev_snake = snapshot.snake.clone();
+        self.snake = snapshot.snake;
+        self.direction = snapshot.direction;
+        self.next_direction = snapshot.next_direction;
+        self.food = snapshot.food;
+        self.food_kind = snapshot.food_kind;
+        self.score = snapshot.score.saturating_sub(penalty);
+        self.style_points = snapshot.style_points;
+        self.wall_passes = snapshot.wall_passes;
+        self.rng = snapshot.rng;
+        self.elapsed = snapshot.elapsed;
+        self.food_eaten = snapshot.food_eaten;
+        self.tick = snapshot.tick;
+        self.bonus = snapshot.bonus;
+        self.bonus_time_left = snapshot.bonus_time_left;
+        self.extra_food = snapshot.extra_food;
+        self.extra_food_time_left = snapshot.extra_food_time_left;
+        self.recording.truncate(snapshot.recorded_steps);
+        self.particles.clear();
+        self.recompute_speed();
+
+        self.game_over = false;
+        self.death_cause = None;
+        self.move_timer = 0.0;
+        self.turn_pressed_at = None;
+        self.rewinds_left -= 1;
+        self.refresh_ai_path();
+        true
+    }
+
+    /// Returns the score and length for the window title, or None for the plain title:
+    /// after game over, while a menu or the resume offer is up, and in zen mode, which
+    /// has no score.
+    fn shown_title(&self) -> Option<(u32, usize)> {
+        let plain = self.game_over || self.pause_menu.is_some() || self.resume_offer.is_some() || self.options.zen;
+        (!plain).then_some((self.score, self.snake.len()))
+    }
+
+    /// Brings the window title up to date once something it shows may have changed,
+    /// setting it only if what it shows did change.
+    fn update_title(&mut self, ctx: &Context) {
+        if !std::mem::take(&mut self.title_stale) {
+            return;
+        }
+        let shown = self.shown_title();
+        if shown == self.titled {
+            return;
+        }
+        self.titled = shown;
+        match shown {
+            Some((score, length)) => graphics::set_window_title(ctx, &live_title(score, length)),
+            None => graphics::set_window_title(ctx, WINDOW_TITLE),
+        }
+    }
+
+    /// Returns true if pressing `key` on the game-over screen starts a new game.
+    ///
+    /// R always does. With `any_key_restarts` on so does any other fresh press, except
+    /// B, which rewinds instead, F11, which only switches fullscreen, and F5, which
+    /// starts over on another grid size anyway.
+    fn restarts_game_over(&self, key: KeyCode, repeat: bool) -> bool {
+        key == KeyCode::R
+            || (self.settings.any_key_restarts && !repeat && !matches!(key, KeyCode::B | KeyCode::F11 | KeyCode::F5))
+    }
+
+    /// Updates the snake’s position and checks for collisions and food consumption.
+    fn update_snake(&mut self) {
+        if self.game_over {
+            return;
+        }
+
+        // The snake should never be empty, but if it somehow is, skip the move rather
+        // than panic.
+        let Some(&head) = self.snake.first() else {
+            return;
+        };
+
+        // Remember this tick in case a death gets rewound.
+        if self.options.rewinds > 0 {
+            if self.history.len() == REWIND_TICKS {
+                self.history.pop_front();
+            }
+            self.history.push_back(self.snapshot());
+        }
+        self.tick += 1;
+        // Eating, poison and the wall toll all happen on a step.
+        self.title_stale = true;
+
+        // Update the current direction from the next_direction (set by user input).
+        self.direction = self.next_direction;
+        self.turn_pressed_at = None;
+
+        // Compute the new head position based on the current direction.
+        let mut new_head = head;
+        match self.direction {
+            Direction::Up => new_head.y -= 1,
+            Direction::Down => new_head.y += 1,
+            Direction::Left => new_head.x -= 1,
+            Direction::Right => new_head.x += 1,
+        }
+
+        // In wrap mode the head comes back in on the opposite edge. This happens before
+        // any collision check, so walls and the body are tested at the wrapped cell.
+        let unwrapped = new_head;
+        if self.options.wrap {
+            new_head = wrap_point(new_head, self.grid_width, self.grid_height);
+        }
+        let crossed_edge = new_head != unwrapped;
+
+        // Check for collision with the boundaries of the grid.
+        if !self.in_bounds(new_head) {
+            // With bumpers on, the edge turns the snake instead of killing it.
+            match self.safe_turn() {
+                Some(turn) if self.options.bumpers => {
+                    self.direction = turn;
+                    self.next_direction = turn;
+                    new_head = ai::step(head, turn);
+                }
+                _ => {
+                    self.crash(DeathCause::Wall);
+                    return;
+                }
+            }
+        }
+
+        // Check for collision with a wall cell.
+        if self.obstacles.contains(&new_head) {
+            self.crash(DeathCause::Obstacle);
+            return;
+        }
+
+        // Check for collision with the snake's own body. Unless the snake is about to
+        // grow, its tail moves off its cell this same step, so the head may follow it in.
+        let grows = !self.options.no_grow
+            && ((self.reaches(new_head, self.food) && self.food_kind == FoodKind::Normal)
+                || self.reaches(new_head, self.extra_food));
+        let solid = if grows { self.snake.len() } else { self.snake.len() - 1 };
+        if self.snake[..solid].contains(&new_head) {
+            self.crash(DeathCause::SelfCollision);
+            return;
+        }
+
+        // Food is worth what the snake's length was before this step made it any longer.
+        let points = food_points(self.snake.len(), self.options.length_decay);
+        self.prev_snake = self.snake.clone();
+        // Insert the new head position at the beginning of the snake vector.
+        self.snake.insert(0, new_head);
+        if let Some(count) = self.visit_count_mut(new_head) {
+            *count += 1;
+        }
+
+        let score_before = self.score;
+
+        // Passing through the edge costs the wall toll, if there is one.
+        if crossed_edge {
+            self.pay_wall_toll();
+        }
+
+        let eaten = self.try_eat(new_head, points);
+        if !eaten.food && !eaten.extra_food {
+            // Remove the tail segment to move the snake forward.
+            self.snake.pop();
+        }
+        // Under --no-grow food only scores, so the tail moves on after eating too.
+        if self.options.no_grow && eaten.grew {
+            self.snake.pop();
+        }
+
+        // The new head joined the body before any food respawned, and spawning never
+        // picks a body cell, so fresh food can't land under the head (even on a wrapped
+        // move) and be eaten a second time this step.
+        debug_assert!(self.food != Some(new_head), "food respawned under the head");
+
+        if self.score != score_before {
+            self.recompute_speed();
+        }
+
+        // Style points reward hugging the walls.
+        if self.options.style_points && self.adjacent_to_wall(new_head) {
+            self.style_points += STYLE_POINTS_PER_STEP;
+        }
+
+        self.check_high_score();
+        self.length_history.push(self.snake.len() as u32);
+        if self.options.ghost {
+            self.recording.push(new_head, self.snake.len());
+        }
+
+        // Eating is progress worth keeping, so save right away.
+        if (eaten.food || eaten.extra_food) && !self.game_over {
+            self.autosave();
+        }
+    }
+
+    /// Returns true if a head that has just moved onto `head` eats `item` this step:
+    /// if the item is on its cell, or with a magnet within its reach (and, under
+    /// `--head-on`, straight ahead of it).
+    fn reaches(&self, head: Point, item: Option<Point>) -> bool {
+        let radius = self.options.magnet_radius;
+        item.is_some_and(|p| {
+            within_magnet(head, p, radius) && (!self.options.head_on || head_on(head, self.direction, p, radius))
+        })
+    }
+
+    /// Eats an item the head reaches on this step, once it has moved onto `head`,
+    /// scoring `points` if it is a food, and replaces the food if that was it.
+    ///
+    /// Called once per step, after the new head has joined the body and before the
+    /// tail moves on; the tail is left to update_snake unless poison took it.
+    fn try_eat(&mut self, head: Point, points: u32) -> Eaten {
+        // Only one item is eaten per step, even if a magnet reaches several or they
+        // share a cell: the food first, then the extra food, then the bonus. Whatever
+        // is left stays on the board for a later step.
+        let mut eaten = Eaten::default();
+        if self.reaches(head, self.food) {
+            eaten.food = true;
+            self.food_eaten += 1;
+            if self.options.reveal > 0 {
+                self.reveal_timer = REVEAL_TIME;
+            }
+            if self.food_kind == FoodKind::Poison {
+                // Poison doesn't grow the snake: move forward, then lose a few tail segments.
+                self.snake.pop();
+                self.shrink_tail(POISON_SHRINK);
+                self.score = self.score.saturating_sub(POISON_PENALTY);
+                self.spawn_food();
+            } else {
+                eaten.grew = true;
+                self.score += points;
+                self.pending_sounds.push(Sound::Eat);
+                // Spawn new food at a random location.
+                self.spawn_food();
+                self.maybe_spawn_bonus();
+            }
+        } else if self.reaches(head, self.extra_food) {
+            // The extra food counts as a normal food, but nothing replaces it.
+            eaten.extra_food = true;
+            eaten.grew = true;
+            self.extra_food = None;
+            self.food_eaten += 1;
+            self.score += points;
+            self.pending_sounds.push(Sound::Eat);
+        } else if self.reaches(head, self.bonus) {
+            // The bonus scores extra points but doesn't grow the snake.
+            self.bonus = None;
+            self.score += BONUS_POINTS;
+            self.pending_sounds.push(Sound::Eat);
+        }
+        eaten
+    }
+
+    /// Counts a pass through the edge and charges its toll under --wall-toll: points
+    /// off the score (which stops at zero) or seconds off the time attack clock.
+    fn pay_wall_toll(&mut self) {
+        let Some(toll) = self.options.wall_toll else {
+            return;
+        };
+        self.wall_passes += 1;
+        match toll {
+            WallToll::Points(points) => self.score = self.score.saturating_sub(points),
+            // Running the clock out ends the run on the next tick.
+            WallToll::Seconds(secs) => self.elapsed += secs,
+        }
+    }
+
+    /// Picks the autopilot's next direction, along with the route it plans to follow.
+    ///
+    /// The planned path runs from the head to the food. It is None when the food is
+    /// unreachable (or gone) and the autopilot is only trying to stay alive.
+    fn choose_ai_direction(&self) -> (Direction, Option<Vec<Point>>) {
+        let Some(&head) = self.snake.first() else {
+            return (self.direction, None);
+        };
+        let (w, h) = (self.grid_width, self.grid_height);
+        let blocked = |p: Point| self.snake.contains(&p) || self.obstacles.contains(&p);
+        let tie_break = self.options.ai_tie_break;
+        let safe = self.safe_moves();
+
+        // Score each safe move by how far it leaves the head from the food, and take
+        // the closest, breaking ties the configured way.
+        if let Some(food) = self.food {
+            let distances = ai::distance_field(food, w, h, blocked);
+            let candidates: Vec<(Direction, u32)> = safe
+                .iter()
+                .filter_map(|&dir| {
+                    let next = ai::step(head, dir);
+                    distances[(next.y * w + next.x) as usize].map(|distance| (dir, distance))
+                })
+                .collect();
+            if let Some(dir) = ai::best_move(&candidates, self.direction, tie_break) {
+                let mut path = vec![head];
+                path.extend(ai::find_path(ai::step(head, dir), food, w, h, blocked).unwrap_or_default());
+                return (dir, Some(path));
+            }
+        }
+
+        // No route to the food: take the most preferred move that doesn't immediately collide.
+        let dir = ai::preference_order(self.direction, tie_break)
+            .into_iter()
+            .find(|dir| safe.contains(dir))
+            .unwrap_or(self.direction);
+        (dir, None)
+    }
+
+    /// Plans the route drawn for the autopilot again, as the board now stands.
+    ///
+    /// The search runs once per change to the board rather than on every frame.
+    fn refresh_ai_path(&mut self) {
+        self.ai_path = if self.autopilot && self.show_ai_path && !self.game_over {
+            self.choose_ai_direction().1
+        } else {
+            None
+        };
+    }
+
+    /// Returns the moves that don't immediately collide with the edge, the body or a wall.
+    fn safe_moves(&self) -> Vec<Direction> {
+        let Some(&head) = self.snake.first() else {
+            return Vec::new();
+        };
+        ai::DIRECTIONS
+            .into_iter()
+            .filter(|&dir| {
+                let next = ai::step(head, dir);
+                self.is_legal_turn(dir)
+                    && self.in_bounds(next)
+                    && !self.snake.contains(&next)
+                    && !self.obstacles.contains(&next)
+            })
+            .collect()
+    }
+
+    /// Picks the autopilot's next move: its best one, except that below full skill it
+    /// sometimes makes a random safe move instead.
+    fn autopilot_move(&mut self) -> Direction {
+        let best = self.choose_ai_direction().0;
+        let Some(skill) = self.options.ai_skill else {
+            return best;
+        };
+        let safe = self.safe_moves();
+        ai::skilled_move(&mut self.ai_rng, best, &safe, skill)
+    }
+
+    /// Advances everything time-driven by `dt` seconds: clocks, timers and the snake's moves.
+    ///
+    /// This is the whole simulation step, shared by every frontend.
+    fn tick(&mut self, dt: f32) {
+        self.volume_display_timer = (self.volume_display_timer - dt).max(0.0);
+        self.celebration_timer = (self.celebration_timer - dt).max(0.0);
+        particles::update(&mut self.particles, dt);
+        self.tick_food_streak(dt);
+        self.anim_time += dt;
+
+        // Nothing moves while the player decides whether to resume an autosave.
+        if self.resume_offer.is_some() {
+            return;
+        }
+
+        // The game stands still while the pause menu or the debug console is open.
+        if self.pause_menu.is_some() || self.console.is_some() {
+            return;
+        }
+
+        // The run starts once the fade-in has finished, or right away without one.
+        if self.reduced_motion() {
+            self.transition_left = 0.0;
+        }
+        if self.transition_left > 0.0 {
+            self.transition_left = (self.transition_left - dt).max(0.0);
+            return;
+        }
+
+        // Until the first turn (or the autopilot) gets it going, nothing runs either.
+        if !self.started && !self.autopilot {
+            return;
+        }
+
+        self.advance_clock(dt);
+        self.reveal_timer = (self.reveal_timer - dt).max(0.0);
+        self.tick_bonus(dt);
+        self.tick_extra_food(dt);
+        self.tick_autosave(dt);
+
+        // In step-on-input mode the snake only moves when a key is pressed, and in
+        // plan mode it doesn't move at all.
+        if !self.options.step_on_input && !self.planning {
+            self.move_timer += dt;
+            // Move the snake when the move_timer exceeds the period for its next move.
+            let period = self.step_period();
+            let grace = self.options.grace.unwrap_or(DEFAULT_GRACE);
+            if step_due(self.move_timer, period, grace, self.turn_pressed_at) {
+                // Time spent holding a straight step for a turn comes out of the next one.
+                self.move_timer = (self.move_timer - period).min(grace);
+                if self.autopilot && !self.game_over {
+                    self.next_direction = self.autopilot_move();
+                } else if let Some(dir) = self.planned_turns.pop_front() {
+                    // Play back moves queued in plan mode, one per tick.
+                    self.next_direction = dir;
+                }
+                self.update_snake();
+                self.refresh_ai_path();
+            }
+        }
+    }
+
+    /// Handles a pressed arrow key: steers the snake, or queues the move in plan mode.
+    ///
+    /// `repeat` is set for auto-repeated presses, which don't advance a step-on-input game.
+    fn steer(&mut self, pressed: Direction, repeat: bool) {
+        let nd = input_direction(pressed, self.invert_controls);
+        if self.planning && !self.autopilot {
+            // In plan mode, presses queue moves instead of steering.
+            self.plan_turn(nd);
+        } else if !self.autopilot && self.is_legal_turn(nd) {
+            // Steering by hand (reversals excepted) abandons any remaining plan, and
+            // the first turn starts a run that is waiting for it.
+            self.next_direction = nd;
+            self.turn_pressed_at = (nd != self.direction).then_some(self.move_timer);
+            self.planned_turns.clear();
+            self.started = true;
+            // In step-on-input mode each fresh press advances the snake one cell.
+            if self.options.step_on_input && !repeat {
+                self.update_snake();
+            }
+        }
+    }
+
+    /// Returns the cells the head will visit if the planned turns are followed.
+    fn planned_path(&self) -> Vec<Point> {
+        let Some(mut cell) = self.snake.first().copied() else {
+            return Vec::new();
+        };
+        self.planned_turns
+            .iter()
+            .map(|&dir| {
+                cell = ai::step(cell, dir);
+                cell
+            })
+            .collect()
+    }
+
+    /// Returns true if the snake may turn to `dir` on its next move.
+    ///
+    /// Reversing is only illegal once there is a body to reverse into. What counts as
+    /// reversing is judged from where the neck actually is, so the guard holds even if
+    /// `direction` is stale, as it can be before the first move of a freshly laid out
+    /// snake.
+    fn is_legal_turn(&self, dir: Direction) -> bool {
+        let heading = match self.snake[..] {
+            // Across a wrapped edge the neck isn't adjacent; fall back to the last move.
+            [head, neck, ..] => ai::direction_between(neck, head).unwrap_or(self.direction),
+            _ => self.direction,
+        };
+        turn_is_legal(heading, dir, self.snake.len())
+    }
+
+    /// Queues a move for a future tick in plan mode, rejecting reversals of the previous move.
+    fn plan_turn(&mut self, dir: Direction) {
+        let last = self.planned_turns.back().copied().unwrap_or(self.next_direction);
+        if turn_is_legal(last, dir, self.snake.len()) {
+            self.planned_turns.push_back(dir);
+        }
+    }
+
+    /// Computes the BFS distance from every cell to the food, avoiding the snake and walls.
+    ///
+    /// Every cell is unreachable when there is no food.
+    fn food_distance_field(&self) -> Vec<Option<u32>> {
+        match self.food {
+            Some(food) => ai::distance_field(food, self.grid_width, self.grid_height, |p| {
+                self.snake.contains(&p) || self.obstacles.contains(&p)
+            }),
+            None => vec![None; (self.grid_width * self.grid_height) as usize],
+        }
+    }
+
+    /// Returns the debug label text for cell `p` under the given mode.
+    ///
+    /// `distances` is the precomputed food distance field, used by `FoodDistance`.
+    fn cell_label_text(&self, mode: CellLabel, distances: &[Option<u32>], p: Point) -> Option<String> {
+        match mode {
+            CellLabel::Off => None,
+            CellLabel::Coordinates => Some(format!("{},{}", p.x, p.y)),
+            CellLabel::FoodDistance => distances[(p.y * self.grid_width + p.x) as usize].map(|d| d.to_string()),
+        }
+    }
+
+    /// Returns true if `p` is on this game's grid.
+    fn in_bounds(&self, p: Point) -> bool {
+        ai::in_bounds(p, self.grid_width, self.grid_height)
+    }
+
+    /// Returns true if `p` is next to a wall: one of its four neighbours is a wall
+    /// cell, or off the grid when the edges don't wrap.
+    fn adjacent_to_wall(&self, p: Point) -> bool {
+        ai::DIRECTIONS.into_iter().map(|dir| ai::step(p, dir)).any(|next| {
+            (!self.options.wrap && !self.in_bounds(next)) || self.obstacles.contains(&next)
+        })
+    }
+
+    /// Returns where the best run's ghost is at this run's tick, or nothing without one.
+    ///
+    /// The ghost goes by the game tick rather than this run's recording, which starts
+    /// over when the snake respawns or a death is rewound.
+    fn ghost_body(&self) -> Vec<Point> {
+        match &self.ghost {
+            Some(ghost) => ghost.body_at(self.tick.saturating_sub(ghost.start_tick) as usize),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the visit counter for cell `p`, or None if `p` is off the grid.
+    fn visit_count_mut(&mut self, p: Point) -> Option<&mut u32> {
+        if !self.in_bounds(p) {
+            return None;
+        }
+        self.visit_counts.get_mut((p.y * self.grid_width + p.x) as usize)
+    }
+
+    /// Tints every visited cell by how often the head has been there, relative to the
+    /// most visited cell, if the debug heatmap is on.
+    fn draw_heatmap(&self, ctx: &mut Context, layout: &Layout) -> GameResult<()> {
+        if !self.options.debug || !self.show_heatmap {
+            return Ok(());
+        }
+        let max = self.visit_counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                let count = self.visit_counts[(y * self.grid_width + x) as usize];
+                if count > 0 {
+                    let alpha = count as f32 / max * HEATMAP_MAX_ALPHA;
+                    self.draw_cell(ctx, layout, Point { x, y }, Color::new(1.0, 0.4, 0.0, alpha))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Switches between a window and fullscreen, going back to the old window size on
+    /// the way out.
+    ///
+    /// If the switch fails, the game carries on in the mode it was in.
+    fn toggle_fullscreen(&mut self, ctx: &mut Context) {
+        let result = match self.windowed_size {
+            None => {
+                let size = graphics::drawable_size(ctx);
+                graphics::set_fullscreen(ctx, ggez::conf::FullscreenType::Desktop).map(|()| Some(size))
+            }
+            Some((width, height)) => graphics::set_fullscreen(ctx, ggez::conf::FullscreenType::Windowed)
+                .and_then(|()| graphics::set_drawable_size(ctx, width, height))
+                .map(|()| None),
+        };
+        match result {
+            Ok(windowed_size) => self.windowed_size = windowed_size,
+            Err(e) => eprintln!("could not switch fullscreen: {}", e),
+        }
+        // The grid is laid out from the drawable size each frame; keep drawing in its pixels.
+        let (width, height) = graphics::drawable_size(ctx);
+        let _ = graphics::set_screen_coordinates(ctx, Rect::new(0.0, 0.0, width, height));
+    }
+
+    /// Starts a fresh game on the next preset grid size, keeping the player's records
+    /// and toggles as a restart does.
+    ///
+    /// Levels have a fixed size, so this does nothing while one is loaded, and returns
+    /// false.
+    fn switch_grid_preset(&mut self) -> bool {
+        if self.level.is_some() {
+            return false;
+        }
+        self.print_summary();
+        self.set_grid_size(next_grid_preset((self.grid_width, self.grid_height)));
+        // The picked size is remembered for later sessions.
+        (self.settings.grid_width, self.settings.grid_height) = (self.grid_width, self.grid_height);
+        true
+    }
+
+    /// Starts a fresh game on a `size` grid, keeping the player's records and toggles.
+    ///
+    /// The best run can only be raced on the grid it was played on, so the ghost is
+    /// dropped (and no longer saved this session) once the size differs from its own.
+    fn set_grid_size(&mut self, size: (i32, i32)) {
+        (self.grid_width, self.grid_height) = size;
+        self.start_over();
+        // The cached board was drawn for the old grid.
+        self.board_cache = None;
+        if self.ghost.as_ref().is_some_and(|best| (best.grid_width, best.grid_height) != size) {
+            self.ghost = None;
+            self.ghost_path = None;
+        }
+    }
+
+    /// Switches to the next preset grid size and resizes the window to fit it.
+    ///
+    /// While fullscreen the grid is letterboxed instead, and the new size is used on
+    /// leaving fullscreen.
+    fn cycle_grid_size(&mut self, ctx: &mut Context) {
+        if self.switch_grid_preset() {
+            self.fit_window_to_grid(ctx);
+        }
+    }
+
+    /// Resizes the window to fit the grid, or when fullscreen remembers the size for
+    /// leaving it.
+    fn fit_window_to_grid(&mut self, ctx: &mut Context) {
+        let (width, height) = window_dimensions(self.grid_width, self.grid_height, &self.options);
+        if self.windowed_size.is_some() {
+            self.windowed_size = Some((width, height));
+        } else if let Err(e) = graphics::set_drawable_size(ctx, width, height) {
+            eprintln!("could not resize the window: {}", e);
+        }
+        let (width, height) = graphics::drawable_size(ctx);
+        let _ = graphics::set_screen_coordinates(ctx, Rect::new(0.0, 0.0, width, height));
+    }
+
+    /// Carries out the highlighted pause menu entry, closing the menu.
+    ///
+    /// Returns true if the player chose to quit; quitting needs the event loop, so
+    /// the caller does it.
+    fn choose_pause_item(&mut self) -> bool {
+        let Some(item) = self.pause_menu.take() else {
+            return false;
+        };
+        match item {
+            PauseItem::Resume => false,
+            PauseItem::Restart => {
+                self.restart();
+                false
+            }
+            PauseItem::Quit => true,
+        }
+    }
+
+    /// Removes the autosave on a normal quit (it is only there to recover from crashes)
+    /// and prints the run's summary.
+    fn finish_session(&self) {
+        self.discard_autosave();
+        self.save_settings();
+        // The run was never started if the player was still being offered the autosave.
+        if self.resume_offer.is_none() {
+            self.print_summary();
+        }
+    }
+
+    /// Reddens the edges of the grid the head is near, more strongly the nearer it is.
+    ///
+    /// The red fades out toward the middle of the grid. With reduced motion there is
+    /// no swelling fade, just a fixed thin line along any edge in range.
+    fn draw_edge_warning(&self, ctx: &mut Context, layout: &Layout) -> GameResult<()> {
+        if !self.options.edge_warning || self.options.wrap || self.game_over {
+            return Ok(());
+        }
+        let Some(&head) = self.snake.first() else {
+            return Ok(());
+        };
+        let band = layout.cell_size * EDGE_WARNING_DEPTH / EDGE_WARNING_BANDS as f32;
+        for (side, distance) in wall_distances(head, self.grid_width, self.grid_height) {
+            let intensity = edge_warning_intensity(distance);
+            if intensity == 0.0 {
+                continue;
+            }
+            if self.reduced_motion() {
+                let line = layout.edge_strip(side, 0.0, EDGE_WARNING_LINE);
+                self.draw_rect(ctx, line, Color::new(1.0, 0.0, 0.0, EDGE_WARNING_ALPHA))?;
+                continue;
+            }
+            for i in 0..EDGE_WARNING_BANDS {
+                let fade = 1.0 - i as f32 / EDGE_WARNING_BANDS as f32;
+                let strip = layout.edge_strip(side, i as f32 * band, band);
+                self.draw_rect(ctx, strip, Color::new(1.0, 0.0, 0.0, EDGE_WARNING_ALPHA * intensity * fade))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the per-cell debug labels, if enabled and the grid is small enough.
+    fn draw_cell_labels(&self, ctx: &mut Context, layout: &Layout) -> GameResult<()> {
+        if !self.options.debug
+            || self.cell_label == CellLabel::Off
+            || self.grid_width > CELL_LABEL_MAX_GRID
+            || self.grid_height > CELL_LABEL_MAX_GRID
+        {
+            return Ok(());
+        }
+        let distances = match self.cell_label {
+            CellLabel::FoodDistance => self.food_distance_field(),
+            _ => Vec::new(),
+        };
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                let Some(label) = self.cell_label_text(self.cell_label, &distances, Point { x, y }) else {
+                    continue;
+                };
+                let mut text = Text::new(label);
+                text.set_font(self.font, PxScale::from(layout.cell_size * 0.4));
+                let cell = layout.cell_rect(Point { x, y });
+                let dest = ggez::mint::Point2 { x: cell.x + 1.0, y: cell.y + 1.0 };
+                graphics::draw(ctx, &text, (dest, Color::new(1.0, 1.0, 1.0, 0.6)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the column numbers along the grid's top edge and the row numbers down its
+    /// left edge, faintly over the board, if they are turned on.
+    fn draw_rulers(&self, ctx: &mut Context, layout: &Layout) -> GameResult<()> {
+        if !self.show_rulers {
+            return Ok(());
+        }
+        let columns = (0..self.grid_width).map(|x| (x, layout.column_label_origin(x)));
+        let rows = (0..self.grid_height).map(|y| (y, layout.row_label_origin(y)));
+        for (number, dest) in columns.chain(rows) {
+            let mut text = Text::new(number.to_string());
+            text.set_font(self.font, PxScale::from(layout.cell_size * 0.4));
+            graphics::draw(ctx, &text, (dest, Color::new(1.0, 1.0, 1.0, 0.4)))?;
+        }
+        Ok(())
+    }
+
+    /// Builds a piece of text in the configured font and size.
+    fn text(&self, contents: impl Into<TextFragment>) -> Text {
+        let mut text = Text::new(contents);
+        text.set_font(self.font, PxScale::from(self.font_size));
+        text
+    }
+
+    /// Returns where the grid currently sits in the window.
+    fn layout(&self, ctx: &Context) -> Layout {
+        let panel_width = if self.options.panel { PANEL_WIDTH } else { 0.0 };
+        let border = self.options.border.unwrap_or(0.0);
+        Layout::fit_with_margin(
+            graphics::drawable_size(ctx),
+            panel_width,
+            border,
+            self.grid_width,
+            self.grid_height,
+            self.options.cell_scale,
+        )
+    }
+
+    /// Returns what is on cell `p`, the topmost thing if several share it.
+    fn cell_contents(&self, p: Point) -> &'static str {
+        if self.snake.first() == Some(&p) {
+            "snake head"
+        } else if self.snake.contains(&p) {
+            "snake"
+        } else if self.food == Some(p) {
+            match self.food_kind {
+                FoodKind::Normal => "food",
+                FoodKind::Poison => "poison",
+            }
+        } else if self.bonus == Some(p) {
+            "bonus"
+        } else if self.extra_food == Some(p) {
+            "extra food"
+        } else if self.obstacles.contains(&p) {
+            "wall"
+        } else {
+            "empty"
+        }
+    }
+
+    /// Returns the `--hover` readout for the cell under the mouse, or None without
+    /// `--hover` or when the mouse isn't over the grid.
+    fn hover_text(&self, ctx: &Context) -> Option<String> {
+        let (x, y) = self.mouse_position.filter(|_| self.options.hover)?;
+        let cell = self.layout(ctx).cell_at(x, y)?;
+        let values = [
+            ("x", cell.x.to_string()),
+            ("y", cell.y.to_string()),
+            ("contents", self.cell_contents(cell).to_string()),
+        ];
+        Some(strings::fill(&self.strings.hover_cell, &values))
+    }
+
+    /// Fills one grid cell with a solid color.
+    fn draw_cell(&self, ctx: &mut Context, layout: &Layout, p: Point, color: Color) -> GameResult<()> {
+        self.draw_rect(ctx, layout.cell_rect(p), color)
+    }
+
+    /// Fills `rect` by drawing the shared unit square scaled over it.
+    ///
+    /// Does nothing if the square couldn't be built.
+    fn draw_rect(&self, ctx: &mut Context, rect: Rect, color: Color) -> GameResult<()> {
+        let Some(unit) = &self.unit_rect else {
+            return Ok(());
+        };
+        let param = DrawParam::new().dest([rect.x, rect.y]).scale([rect.w, rect.h]).color(color);
+        graphics::draw(ctx, unit, param)
+    }
+
+    /// Draws a drop shadow `offset` (a fraction of a cell) down and right of every
+    /// snake segment and the food, clipped to the grid.
+    fn draw_shadows(&self, ctx: &mut Context, layout: &Layout, offset: f32) -> GameResult<()> {
+        let [r, g, b, a] = self.options.shadow_color.or(self.settings.shadow_color).unwrap_or([0, 0, 0, 128]);
+        let color = Color::from_rgba(r, g, b, a);
+        let mut rects: Vec<Rect> = (0..self.visible_segments()).flat_map(|i| self.segment_rects(layout, i)).collect();
+        if let Some(food) = self.food {
+            rects.push(layout::inset(layout.cell_rect(food), layout.padding(self.options.cell_padding)));
+        }
+        for rect in rects {
+            if let Some(shadow) = layout.clip(layout.shadow_rect(rect, offset)) {
+                self.draw_rect(ctx, shadow, color)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if a short-lived food with `remaining` of its lifetime left (as a
+    /// fraction) is shown this frame: it blinks faster as it runs out, unless motion
+    /// is reduced.
+    fn blink_visible(&self, remaining: f32) -> bool {
+        let frequency = bonus_blink_frequency(remaining);
+        self.reduced_motion() || frequency == 0.0 || (self.anim_time * frequency).fract() < 0.5
+    }
+
+    /// Brings the cached board up to date and draws it, for `--dirty-rects`.
+    ///
+    /// Only the cells whose contents changed since the last frame are redrawn onto the
+    /// cached canvas; all of them are when the window or the snake's color changes.
+    /// Cells are drawn in place, so the snake doesn't slide or wiggle.
+    fn draw_cached_board(&mut self, ctx: &mut Context, layout: &Layout, snake_color: Color) -> GameResult<()> {
+        let food = self.food.map(|food| (food, self.food_kind));
+        let bonus = self.bonus.filter(|_| self.blink_visible(self.bonus_time_left / BONUS_LIFETIME));
+        let extra_remaining = self.extra_food_time_left / EXTRA_FOOD_LIFETIME;
+        let extra_food = self.extra_food.filter(|_| self.blink_visible(extra_remaining));
+        let (width, height) = (self.grid_width, self.grid_height);
+        let snake = &self.snake[..self.visible_segments()];
+        let cells = dirty::cell_kinds(width, height, &self.obstacles, snake, food, extra_food, bonus);
+        let window = graphics::drawable_size(ctx);
+        let (cache, changed) = match self.board_cache.take() {
+            Some(cache)
+                if cache.window == window && cache.snake_color == snake_color && cache.cells.len() == cells.len() =>
+            {
+                let changed = dirty::changed_cells(&cache.cells, &cells, self.grid_width);
+                (cache, changed)
+            }
+            _ => {
+                let canvas = graphics::Canvas::with_window_size(ctx)?;
+                graphics::set_canvas(ctx, Some(&canvas));
+                graphics::clear(ctx, Color::from_rgb(0, 0, 0));
+                let all = (0..self.grid_height)
+                    .flat_map(|y| (0..self.grid_width).map(move |x| Point { x, y }))
+                    .collect();
+                (BoardCache { canvas, cells: Vec::new(), window, snake_color }, all)
+            }
+        };
+        graphics::set_canvas(ctx, Some(&cache.canvas));
+        let padding = layout.padding(self.options.cell_padding);
+        let result = changed.iter().try_for_each(|&p| {
+            let background = if self.checkerboard() { checker_color(p) } else { Color::from_rgb(0, 0, 0) };
+            self.draw_cell(ctx, layout, p, background)?;
+            let padded = layout::inset(layout.cell_rect(p), padding);
+            match cells[(p.y * self.grid_width + p.x) as usize] {
+                CellKind::Empty => Ok(()),
+                CellKind::Wall => self.draw_cell(ctx, layout, p, Color::from_rgb(128, 128, 128)),
+                CellKind::Snake => self.draw_rect(ctx, padded, snake_color),
+                CellKind::Food(kind) => self.draw_rect(ctx, padded, food_color(kind)),
+                CellKind::ExtraFood => self.draw_rect(ctx, padded, Color::from_rgb(255, 115, 190)),
+                CellKind::Bonus => self.draw_cell(ctx, layout, p, Color::from_rgb(255, 200, 0)),
+            }
+        });
+        graphics::set_canvas(ctx, None);
+        // If a cell failed to draw the cache is dropped, so the next frame redraws them all.
+        result?;
+        let cache = self.board_cache.insert(BoardCache { cells, ..cache });
+        graphics::draw(ctx, &cache.canvas, DrawParam::new())
+    }
+
+    /// Returns how far through the current move the snake is drawn, from 0 to 1.
+    ///
+    /// Without smooth movement (or while the snake isn't moving on the timer) the
+    /// snake is always drawn at its logical position.
+    fn move_progress(&self) -> f32 {
+        if self.options.smooth && !self.game_over && !self.options.step_on_input && !self.planning {
+            (self.move_timer / self.step_period()).clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns how many of the snake's segments are drawn: none while it is bursting
+    /// apart, and all of them otherwise.
+    fn visible_segments(&self) -> usize {
+        if self.particles.is_empty() { self.snake.len() } else { 0 }
+    }
+
+    /// Returns the rectangles to draw for snake segment `index`.
+    ///
+    /// With smooth movement the segment slides from its previous cell to its current
+    /// one. A segment that wrapped around the grid is drawn twice, leaving one edge
+    /// and entering at the other, each half clipped to the grid.
+    fn segment_rects(&self, layout: &Layout, index: usize) -> Vec<Rect> {
+        let to = self.snake[index];
+        let from = self.prev_snake.get(index).copied().unwrap_or(to);
+        let t = self.move_progress();
+        let shifted = |p: Point, dx: f32, dy: f32| {
+            let mut rect = layout.cell_rect(p);
+            rect.x += dx * layout.cell_size;
+            rect.y += dy * layout.cell_size;
+            rect
+        };
+        let rects = match wrap_crossing(from, to, self.grid_width, self.grid_height) {
+            Some(dir) => {
+                let delta = ai::step(Point { x: 0, y: 0 }, dir);
+                let (dx, dy) = (delta.x as f32, delta.y as f32);
+                [shifted(from, dx * t, dy * t), shifted(to, -dx * (1.0 - t), -dy * (1.0 - t))]
+                    .into_iter()
+                    .filter_map(|rect| layout.clip(rect))
+                    .collect()
+            }
+            None => vec![shifted(from, (to.x - from.x) as f32 * t, (to.y - from.y) as f32 * t)],
+        };
+        let padding = layout.padding(self.options.cell_padding);
+        rects
+            .into_iter()
+            .map(|rect| self.wiggle(layout::inset(rect, padding), layout, index))
+            .collect()
+    }
+
+    /// Returns true if cosmetic animation is turned off, by `--reduced-motion`, the
+    /// settings or the SNAKE_REDUCED_MOTION environment variable.
+    ///
+    /// Every animated effect checks this and draws its static form instead, so new
+    /// effects should too.
+    fn reduced_motion(&self) -> bool {
+        self.options.reduced_motion || self.settings.reduced_motion || self.env_reduced_motion
+    }
+
+    /// Returns true if the grid is shaded in a checkerboard, by `--checkerboard` or the
+    /// settings.
+    fn checkerboard(&self) -> bool {
+        self.options.checkerboard || self.settings.checkerboard
+    }
+
+    /// Returns how far the drop shadows fall, from `--shadow` or else the settings, or
+    /// None if there are no shadows.
+    fn shadow_offset(&self) -> Option<f32> {
+        self.options.shadow_offset.or(self.settings.shadow_offset)
+    }
+
+    /// Returns how opaque the fade-in's black cover is this frame (0 once it's over,
+    /// or with reduced motion).
+    fn transition_alpha(&self) -> f32 {
+        if self.reduced_motion() {
+            return 0.0;
+        }
+        self.transition_left / TRANSITION_TIME
+    }
+
+    /// Returns how strong the high score celebration's gold flash is this frame (0
+    /// once it has faded, or with reduced motion, which keeps only the banner).
+    fn celebration_flash_alpha(&self) -> f32 {
+        if self.reduced_motion() {
+            return 0.0;
+        }
+        CELEBRATION_FLASH_ALPHA * self.celebration_timer / CELEBRATION_TIME
+    }
+
+    /// Applies the slither wiggle to the rectangle drawn for snake segment `index`.
+    ///
+    /// While the snake is alive each segment sways sideways to its direction of travel,
+    /// in a wave running down the body. The segment is narrowed by the wiggle's
+    /// amplitude so it never leaves its cell.
+    fn wiggle(&self, mut rect: Rect, layout: &Layout, index: usize) -> Rect {
+        let segment = self.snake[index];
+        if self.game_over || self.reduced_motion() {
+            return rect;
+        }
+        // A segment travels from the one behind it; the tail and wrapped segments use the heading.
+        let dir = self
+            .snake
+            .get(index + 1)
+            .and_then(|&behind| ai::direction_between(behind, segment))
+            .unwrap_or(self.direction);
+        let amplitude = layout.cell_size * WIGGLE_AMPLITUDE;
+        let offset = amplitude * (self.anim_time * WIGGLE_SPEED - index as f32 * WIGGLE_PHASE_STEP).sin();
+        match dir {
+            Direction::Up | Direction::Down => {
+                rect.x += amplitude + offset;
+                rect.w -= 2.0 * amplitude;
+            }
+            Direction::Left | Direction::Right => {
+                rect.y += amplitude + offset;
+                rect.h -= 2.0 * amplitude;
+            }
+        }
+        rect
+    }
+
+    /// Writes the frame just drawn to the next numbered PNG in the frame directory.
+    ///
+    /// Frames are skipped while nothing is in play (paused, waiting to start or on the
+    /// resume offer) so the clip stays tight, and recording stops for good after
+    /// MAX_RECORDED_FRAMES frames or the first one that fails to write.
+    fn record_frame(&mut self, ctx: &mut Context) {
+        let Some(dir) = &self.frame_dir else {
+            return;
+        };
+        if self.pause_menu.is_some() || self.resume_offer.is_some() || (!self.started && !self.autopilot) {
+            return;
+        }
+        let path = dir.join(format!("frame_{:05}.png", self.frames_recorded));
+        let written = graphics::screenshot(ctx).and_then(|shot| {
+            let pixels = shot.to_rgba8(ctx)?;
+            image::save_buffer(&path, &pixels, shot.width().into(), shot.height().into(), image::ColorType::Rgba8)
+                .map_err(|e| ggez::GameError::RenderError(e.to_string()))
+        });
+        if let Err(e) = written {
+            eprintln!("could not write frame {}, so recording stops: {}", path.display(), e);
+            self.frame_dir = None;
+            return;
+        }
+        self.frames_recorded += 1;
+        if self.frames_recorded == MAX_RECORDED_FRAMES {
+            eprintln!("stopped recording frames after {}", MAX_RECORDED_FRAMES);
+            self.frame_dir = None;
+        }
+    }
+
+    /// Draws the side panel listing the snake's segments, if enabled, as many as fit
+    /// from the current scroll position.
+    fn draw_panel(&self, ctx: &mut Context) -> GameResult<()> {
+        if !self.options.panel {
+            return Ok(());
+        }
+        let line_height = self.text("0").height(ctx) + LINE_SPACING;
+        let (_, window_height) = graphics::drawable_size(ctx);
+        // One line goes to the header.
+        let rows = (((window_height - 2.0 * PANEL_MARGIN) / line_height) as usize).saturating_sub(1);
+        let text = self.text(hud::panel_lines(&self.snake, self.panel_scroll, rows).join("\n"));
+        let dest = ggez::mint::Point2 { x: PANEL_MARGIN, y: PANEL_MARGIN };
+        graphics::draw(ctx, &text, (dest, Color::from_rgb(200, 200, 200)))
+    }
+
+    /// Draws the length sparkline in the bottom-right corner of the grid, if enabled.
+    fn draw_sparkline(&self, ctx: &mut Context, layout: &Layout) -> GameResult<()> {
+        let area = layout.grid_rect();
+        let (w, h) = SPARKLINE_SIZE;
+        let chart = Rect::new(area.right() - w - SPARKLINE_MARGIN, area.bottom() - h - SPARKLINE_MARGIN, w, h);
+        let points = self.length_history.points(chart);
+        // A line needs at least two points.
+        if !self.options.sparkline || points.len() < 2 {
+            return Ok(());
+        }
+        let line = Mesh::new_line(ctx, &points, 1.5, Color::new(0.0, 1.0, 0.0, 0.6))?;
+        graphics::draw(ctx, &line, (ggez::mint::Point2 { x: 0.0, y: 0.0 },))
+    }
+
+    /// Draws lines of text as a block centered over the grid, each line centered on its own.
+    fn draw_centered_lines(&self, ctx: &mut Context, lines: &[Text]) -> GameResult<()> {
+        let area = self.layout(ctx).grid_rect();
+        let sizes: Vec<(f32, f32)> = lines
+            .iter()
+            .map(|line| {
+                let dims = line.dimensions(ctx);
+                (dims.w, dims.h)
+            })
+            .collect();
+        for (line, dest) in lines.iter().zip(hud::centered_line_origins(area, &sizes, LINE_SPACING)) {
+            graphics::draw(ctx, line, (dest, Color::from_rgb(255, 255, 255)))?;
+        }
+        Ok(())
+    }
+
+    /// Draws the score HUD at its configured position, unless it is hidden.
+    fn draw_hud(&self, ctx: &mut Context) -> GameResult<()> {
+        if !self.hud_visible {
+            return Ok(());
+        }
+        // Zen mode keeps no score.
+        let mut lines = Vec::new();
+        if let Some(date) = &self.daily_date {
+            lines.push(strings::fill(&self.strings.daily, &[("date", date.clone())]));
+        }
+        if !self.options.zen {
+            lines.push(strings::fill(&self.strings.score, &[("score", self.score.to_string())]));
+        }
+        if self.options.style_points {
+            lines.push(strings::fill(&self.strings.style, &[("points", self.style_points.to_string())]));
+        }
+        if self.options.wall_toll.is_some() {
+            lines.push(strings::fill(&self.strings.wall_passes, &[("passes", self.wall_passes.to_string())]));
+        }
+        if self.options.length_decay && !self.options.zen {
+            let points = food_points(self.snake.len(), true);
+            lines.push(strings::fill(&self.strings.food_points, &[("points", points.to_string())]));
+        }
+        if self.options.lives > 0 {
+            lines.push(strings::fill(&self.strings.lives, &[("lives", self.lives_left.to_string())]));
+        }
+        if self.show_head_info {
+            lines.push(head_info_text(&self.snake, self.direction, self.next_direction));
+        }
+        if let Some(line) = self.hover_text(ctx) {
+            lines.push(line);
+        }
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let score_text = self.text(lines.join("\n"));
+        let dims = score_text.dimensions(ctx);
+        // The speed bar sits under the text, and the two are placed as one block.
+        let bar_height = if self.options.speed_bar { SPEED_BAR_GAP + SPEED_BAR_HEIGHT } else { 0.0 };
+        let size = (dims.w.max(if self.options.speed_bar { SPEED_BAR_WIDTH } else { 0.0 }), dims.h + bar_height);
+        let dest = hud::hud_origin(self.hud_position, self.layout(ctx).grid_rect(), size);
+        graphics::draw(ctx, &score_text, (dest, Color::from_rgb(255, 255, 255)))?;
+        if self.options.speed_bar {
+            let base = self.options.move_period.unwrap_or(DEFAULT_MOVE_PERIOD);
+            let progress = speedup_progress(base, self.score);
+            let track = Rect::new(dest.x, dest.y + dims.h + SPEED_BAR_GAP, SPEED_BAR_WIDTH, SPEED_BAR_HEIGHT);
+            self.draw_rect(ctx, track, Color::new(1.0, 1.0, 1.0, 0.2))?;
+            let fill = Rect::new(track.x, track.y, track.w * progress, track.h);
+            if fill.w > 0.0 {
+                self.draw_rect(ctx, fill, Color::from_rgb(255, 255, 255))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Implementing ggez’s EventHandler trait to define game behavior.
+impl EventHandler for SnakeGame {
+    /// Updates the game logic on each frame.
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        // Get the time elapsed since the last update.
+        let dt = clamp_frame_time(ggez::timer::delta(ctx).as_secs_f32());
+        self.run_script_input();
+        self.tick(dt);
+
+        self.play_pending_sounds(ctx);
+        self.update_title(ctx);
+        Ok(())
+    }
+
+    /// Draws the current game state.
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        // Clear the screen to black.
+        graphics::clear(ctx, Color::from_rgb(0, 0, 0));
+
+        // Build the shared square once. If that fails, the frame is drawn without
+        // filled shapes and it is tried again next frame, rather than quitting.
+        if self.unit_rect.is_none() {
+            match Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(0.0, 0.0, 1.0, 1.0), Color::WHITE) {
+                Ok(mesh) => self.unit_rect = Some(mesh),
+                Err(e) => eprintln!("could not build the cell mesh: {}", e),
+            }
+        }
+
+        // Scale the grid to the window, centered, leaving black bars around it.
+        let layout = self.layout(ctx);
+        let snake_color = if self.options.no_speed_tint {
+            Color::from_rgb(0, 255, 0)
+        } else {
+            speed_color(self.move_period)
+        };
+        let cached = self.options.dirty_rects;
+        if cached {
+            // Draw the background, walls, snake, food and bonus from the cached board.
+            self.draw_cached_board(ctx, &layout, snake_color)?;
+        } else {
+            // Shade the background in a checkerboard before anything else goes on top.
+            if self.checkerboard() {
+                for y in 0..self.grid_height {
+                    for x in 0..self.grid_width {
+                        self.draw_cell(ctx, &layout, Point { x, y }, checker_color(Point { x, y }))?;
+                    }
+                }
+            }
+            // Draw the walls as grey squares.
+            for &wall in &self.obstacles {
+                self.draw_cell(ctx, &layout, wall, Color::from_rgb(128, 128, 128))?;
+            }
+        }
+
+        // Frame the arena, dashed in wrap mode to show the snake can pass through. This
+        // comes after the cached board, whose canvas covers the whole window.
+        if let Some(thickness) = self.options.border {
+            let [r, g, b, a] = self.options.border_color.unwrap_or([128, 128, 128, 255]);
+            for bar in layout.border_rects(thickness, self.options.wrap) {
+                self.draw_rect(ctx, bar, Color::from_rgba(r, g, b, a))?;
+            }
+        }
+
+        // Draw the debug heatmap of visited cells under the snake.
+        self.draw_heatmap(ctx, &layout)?;
+
+        // Draw the best run's ghost, in step with this run, as faint cells.
+        for cell in self.ghost_body() {
+            self.draw_cell(ctx, &layout, cell, Color::new(1.0, 1.0, 1.0, 0.25))?;
+        }
+
+        // Draw the drop shadows in a pass of their own, so they sit under everything
+        // they belong to. The cached board has no shadows.
+        if let Some(offset) = self.shadow_offset()
+            && !cached
+        {
+            self.draw_shadows(ctx, &layout, offset)?;
+        }
+
+        // Draw each segment of the snake, warmer the faster it goes, and in trail fade
+        // mode fainter towards the tail.
+        if !cached {
+            for i in 0..self.visible_segments() {
+                let mut color = snake_color;
+                if self.options.trail_fade {
+                    color.a *= trail_alpha(i, self.snake.len());
+                }
+                for rect in self.segment_rects(&layout, i) {
+                    self.draw_rect(ctx, rect, color)?;
+                }
+            }
+        }
+
+        // Draw the pieces of a crashed snake flying apart, shrinking as they go.
+        for particle in &self.particles {
+            let size = layout.cell_size * particle.size();
+            let x = layout.offset_x + particle.x * layout.cell_size - size / 2.0;
+            let y = layout.offset_y + particle.y * layout.cell_size - size / 2.0;
+            self.draw_rect(ctx, Rect::new(x, y, size, size), snake_color)?;
+        }
+
+        // Draw the route the autopilot is following as a faint line through cell centers.
+        if let Some(path) = &self.ai_path {
+            let points: Vec<ggez::mint::Point2<f32>> = path.iter().map(|&p| layout.cell_center(p)).collect();
+            let line = Mesh::new_line(ctx, &points, 2.0, Color::new(1.0, 1.0, 0.0, 0.35))?;
+            graphics::draw(ctx, &line, (ggez::mint::Point2 { x: 0.0, y: 0.0 },))?;
+        }
+
+        // Draw the moves queued in plan mode as translucent cells.
+        for cell in self.planned_path() {
+            self.draw_cell(ctx, &layout, cell, Color::new(0.0, 0.8, 1.0, 0.3))?;
+        }
+
+        // Draw the food as a red square (purple when it's poison).
+        if let Some(food) = self.food
+            && !cached
+        {
+            let rect = layout::inset(layout.cell_rect(food), layout.padding(self.options.cell_padding));
+            self.draw_rect(ctx, rect, food_color(self.food_kind))?;
+        }
+
+        // Draw the extra food of a double food event in pink, blinking as it runs out.
+        if let Some(extra_food) = self.extra_food
+            && self.blink_visible(self.extra_food_time_left / EXTRA_FOOD_LIFETIME)
+            && !cached
+        {
+            let rect = layout::inset(layout.cell_rect(extra_food), layout.padding(self.options.cell_padding));
+            self.draw_rect(ctx, rect, Color::from_rgb(255, 115, 190))?;
+        }
+
+        // Streak from the food just eaten to where the next one landed, fading out.
+        if let Some((from, to)) = self.food_streak {
+            let alpha = 0.6 * self.food_streak_timer / FOOD_STREAK_TIME;
+            let points = [layout.cell_center(from), layout.cell_center(to)];
+            let line = Mesh::new_line(ctx, &points, 2.0, Color::new(1.0, 1.0, 1.0, alpha))?;
+            graphics::draw(ctx, &line, (ggez::mint::Point2 { x: 0.0, y: 0.0 },))?;
+        }
+
+        // Mark where the next foods will appear, fainter the further ahead they are.
+        for (i, cell) in self.preview_foods(self.preview_count()).into_iter().enumerate() {
+            let alpha = PREVIEW_ALPHA / (i + 1) as f32;
+            self.draw_cell(ctx, &layout, cell, Color::new(1.0, 0.0, 0.0, alpha))?;
+        }
+
+        // Draw the bonus food in gold, blinking faster as it runs out, with a bar
+        // along the top of the grid showing its remaining lifetime.
+        if let Some(bonus) = self.bonus {
+            let remaining = self.bonus_time_left / BONUS_LIFETIME;
+            if self.blink_visible(remaining) && !cached {
+                self.draw_cell(ctx, &layout, bonus, Color::from_rgb(255, 200, 0))?;
+            }
+            let area = layout.grid_rect();
+            let bar = Rect::new(area.x, area.y, area.w * remaining, BONUS_BAR_HEIGHT);
+            if bar.w > 0.0 {
+                self.draw_rect(ctx, bar, Color::new(1.0, 0.8, 0.0, 0.7))?;
+            }
+        }
+
+        self.draw_edge_warning(ctx, &layout)?;
+
+        // Draw the debug cell labels and the rulers underneath the HUD.
+        self.draw_cell_labels(ctx, &layout)?;
+        self.draw_rulers(ctx, &layout)?;
+
+        // Draw the current score and the length sparkline.
+        self.draw_hud(ctx)?;
+        self.draw_sparkline(ctx, &layout)?;
+        self.draw_panel(ctx)?;
+
+        // In time attack mode, show the time left in large text at the top of the grid.
+        let area = layout.grid_rect();
+        let mut readout_y = area.y + 30.0;
+        if let Some(left) = self.time_left() {
+            let mut countdown = Text::new(format!("{:.1}", left));
+            countdown.set_font(self.font, PxScale::from(self.font_size * COUNTDOWN_SCALE));
+            let dims = countdown.dimensions(ctx);
+            let dest = ggez::mint::Point2 { x: area.x + (area.w - dims.w) / 2.0, y: area.y + 10.0 };
+            graphics::draw(ctx, &countdown, (dest, Color::from_rgb(255, 220, 0)))?;
+            readout_y = dest.y + dims.h + LINE_SPACING;
+        }
+
+        // Celebrate passing the high score with a banner and a fading gold flash.
+        if self.celebration_timer > 0.0 {
+            let alpha = self.celebration_flash_alpha();
+            if alpha > 0.0 {
+                self.draw_rect(ctx, area, Color::new(1.0, 0.85, 0.0, alpha))?;
+            }
+            let banner = self.text(self.strings.new_high_score.as_str());
+            let dest = ggez::mint::Point2 { x: area.x + (area.w - banner.width(ctx)) / 2.0, y: readout_y };
+            graphics::draw(ctx, &banner, (dest, Color::from_rgb(255, 220, 0)))?;
+            readout_y += banner.height(ctx) + LINE_SPACING;
+        }
+
+        // Briefly show the volume levels after they change.
+        if self.volume_display_timer > 0.0 {
+            let percent = |volume: f32, muted: bool| {
+                if muted { "muted".to_string() } else { format!("{:.0}%", volume * 100.0) }
+            };
+            let volume_text = self.text(format!(
+                "Music: {}  SFX: {}",
+                percent(self.settings.music_volume, self.settings.music_muted),
+                percent(self.settings.sfx_volume, self.settings.sfx_muted),
+            ));
+            let dest = ggez::mint::Point2 { x: area.x + (area.w - volume_text.width(ctx)) / 2.0, y: readout_y };
+            graphics::draw(ctx, &volume_text, (dest, Color::from_rgb(255, 255, 255)))?;
+        }
+
+        // Show the pause menu, with the highlighted entry in yellow between markers.
+        if let Some(selected) = self.pause_menu {
+            let mut lines = vec![self.text(self.strings.paused.as_str())];
+            for item in PauseItem::ALL {
+                lines.push(if item == selected {
+                    self.text(TextFragment::new(format!("> {} <", item.label())).color(Color::from_rgb(255, 220, 0)))
+                } else {
+                    self.text(item.label())
+                });
+            }
+            self.draw_centered_lines(ctx, &lines)?;
+        }
+
+        // Show the debug console along the bottom of the grid, with the last reply above it.
+        if let Some(line) = &self.console {
+            let reply = self.text(self.console_reply.as_str());
+            let prompt = self.text(format!("> {}_", line));
+            let height = reply.height(ctx) + prompt.height(ctx) + 3.0 * LINE_SPACING;
+            let strip = Rect::new(area.x, area.y + area.h - height, area.w, height);
+            self.draw_rect(ctx, strip, Color::new(0.0, 0.0, 0.0, 0.8))?;
+            let reply_dest = ggez::mint::Point2 { x: strip.x + LINE_SPACING, y: strip.y + LINE_SPACING };
+            graphics::draw(ctx, &reply, (reply_dest, Color::from_rgb(180, 180, 180)))?;
+            let prompt_y = reply_dest.y + reply.height(ctx) + LINE_SPACING;
+            let prompt_dest = ggez::mint::Point2 { x: reply_dest.x, y: prompt_y };
+            graphics::draw(ctx, &prompt, (prompt_dest, Color::from_rgb(255, 255, 255)))?;
+        }
+
+        // Until the run starts, say how to start it.
+        let waiting = !self.started && !self.autopilot && !self.game_over;
+        if waiting && self.resume_offer.is_none() && self.pause_menu.is_none() {
+            self.draw_centered_lines(ctx, &[self.text(self.strings.press_to_start.as_str())])?;
+        }
+
+        // Ask whether to resume the autosaved run.
+        if self.resume_offer.is_some() {
+            let lines = [
+                self.text(self.strings.resume_prompt.as_str()),
+                self.text(self.strings.resume_choices.as_str()),
+            ];
+            self.draw_centered_lines(ctx, &lines)?;
+        }
+
+        // If the game is over, display a game-over message once the snake has burst.
+        if self.game_over && self.particles.is_empty() {
+            let mut lines = Vec::new();
+            match self.death_cause {
+                Some(DeathCause::TimeUp) => lines.push(self.text(self.strings.time_up.as_str())),
+                Some(DeathCause::BoardFull) => lines.push(self.text(self.strings.board_full.as_str())),
+                _ => {}
+            }
+            lines.push(self.text(if self.settings.any_key_restarts {
+                self.strings.game_over_any_key.as_str()
+            } else {
+                self.strings.game_over.as_str()
+            }));
+            if self.streak_path.is_some() {
+                let days = self.streak.current(streak::day_of(unix_time()));
+                let threshold = self.options.streak_threshold.unwrap_or(STREAK_THRESHOLD);
+                let values = [("days", days.to_string()), ("threshold", threshold.to_string())];
+                lines.push(self.text(strings::fill(&self.strings.streak, &values)));
+            }
+            if let Some(maze_seed) = &self.options.maze_seed {
+                lines.push(self.text(strings::fill(&self.strings.maze_seed, &[("seed", maze_seed.clone())])));
+            }
+            if self.scores_path.is_some()
+                && !self.options.zen
+                && let Some(average) = self.scores.recent_average()
+            {
+                let values = [
+                    ("high", self.scores.high_score.to_string()),
+                    ("runs", self.scores.recent.len().to_string()),
+                    ("average", format!("{:.1}", average)),
+                ];
+                lines.push(self.text(strings::fill(&self.strings.recent_scores, &values)));
+            }
+            if self.rewinds_left > 0 && !self.history.is_empty() && self.death_cause != Some(DeathCause::TimeUp) {
+                let rewind = strings::fill(&self.strings.rewind, &[("left", self.rewinds_left.to_string())]);
+                lines.push(self.text(rewind));
+            }
+            self.draw_centered_lines(ctx, &lines)?;
+        }
+
+        // Fade the frame in from black at the start of a run.
+        let alpha = self.transition_alpha();
+        if alpha > 0.0 && self.resume_offer.is_none() {
+            let (w, h) = graphics::drawable_size(ctx);
+            self.draw_rect(ctx, Rect::new(0.0, 0.0, w, h), Color::new(0.0, 0.0, 0.0, alpha))?;
+        }
+
+        // Dump the frame for a clip, if recording, before presenting it.
+        self.record_frame(ctx);
+
+        // Present the drawn frame on the screen.
+        graphics::present(ctx)?;
+        Ok(())
+    }
+
+    /// Keeps track of the mouse for the `--hover` cell readout.
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        if self.options.hover {
+            self.mouse_position = Some((x, y));
+        }
+    }
+
+    /// Hides the `--hover` readout while the mouse is outside the window.
+    fn mouse_enter_or_leave(&mut self, _ctx: &mut Context, entered: bool) {
+        if !entered {
+            self.mouse_position = None;
+        }
+    }
+
+    /// Types into the debug console while it is open.
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        // The backtick that opened the console arrives as text too.
+        if let Some(line) = &mut self.console
+            && !character.is_control()
+            && character != '`'
+        {
+            line.push(character);
+        }
+    }
+
+    /// Tidies up when the window closes.
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        self.finish_session();
+        false
+    }
+
+    /// Keeps drawing in window pixels when the window is resized, so `draw` can letterbox the grid.
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        let _ = graphics::set_screen_coordinates(ctx, Rect::new(0.0, 0.0, width, height));
+    }
+
+    /// Handles keyboard input for controlling the snake and restarting the game.
+    fn key_down_event(&mut self, ctx: &mut Context, key: KeyCode, mods: KeyMods, repeat: bool) {
+        // A key may open or close a menu, restart, rewind or resume, so the title is
+        // looked at again on the next update.
+        self.title_stale = true;
+        // While an autosave is on offer, only the answer keys do anything.
+        if self.resume_offer.is_some() {
+            match key {
+                KeyCode::Y => {
+                    let state = self.resume_offer.take().expect("offer checked above");
+                    if let Err(e) = self.apply_save(state) {
+                        eprintln!("could not resume the autosave: {}", e);
+                    }
+                }
+                KeyCode::N => {
+                    self.resume_offer = None;
+                    self.discard_autosave();
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        // Under --debug, backtick opens and closes the console. While it is open, Enter
+        // runs the typed command, Backspace deletes, Escape closes it, and no other key
+        // reaches the game.
+        if self.options.debug && key == KeyCode::Grave {
+            self.console = if self.console.is_some() { None } else { Some(String::new()) };
+            return;
+        }
+        if let Some(line) = &mut self.console {
+            match key {
+                KeyCode::Return | KeyCode::NumpadEnter => {
+                    let line = std::mem::take(line);
+                    self.console_reply = match console::parse(&line).and_then(|command| self.run_command(command)) {
+                        Ok(()) => format!("ok: {}", line.trim()),
+                        Err(e) => e,
+                    };
+                }
+                KeyCode::Back => {
+                    line.pop();
+                }
+                KeyCode::Escape => self.console = None,
+                _ => {}
+            }
+            return;
+        }
+
+        // While paused, the keys drive the menu; P and Escape also resume.
+        if let Some(selected) = self.pause_menu {
+            match key {
+                KeyCode::Up => self.pause_menu = Some(selected.prev()),
+                KeyCode::Down => self.pause_menu = Some(selected.next()),
+                KeyCode::Return | KeyCode::NumpadEnter => {
+                    let quit = self.choose_pause_item();
+                    if quit {
+                        self.finish_session();
+                        event::quit(ctx);
+                    }
+                }
+                KeyCode::P | KeyCode::Escape => self.pause_menu = None,
+                _ => {}
+            }
+            return;
+        }
+
+        // Any key skips the fade-in, and then acts as usual.
+        self.transition_left = 0.0;
+
+        if self.game_over && self.restarts_game_over(key, repeat) {
+            self.restart();
+            return;
+        }
+
+        // Map key presses to direction changes.
+        let new_direction = match key {
+            KeyCode::Up => Some(Direction::Up),
+            KeyCode::Down => Some(Direction::Down),
+            KeyCode::Left => Some(Direction::Left),
+            KeyCode::Right => Some(Direction::Right),
+            // F5 starts over on the next preset grid size.
+            KeyCode::F5 => {
+                self.cycle_grid_size(ctx);
+                None
+            },
+            // F11 switches between a window and fullscreen.
+            KeyCode::F11 => {
+                self.toggle_fullscreen(ctx);
+                None
+            },
+            // 'P' or Escape pauses a game in progress.
+            KeyCode::P | KeyCode::Escape if !self.game_over => {
+                self.pause_menu = Some(PauseItem::Resume);
+                None
+            },
+            // '+' and '-' speed the snake up and slow it down.
+            KeyCode::Equals | KeyCode::NumpadAdd => {
+                self.set_move_period(self.move_period - MOVE_PERIOD_STEP);
+                None
+            },
+            KeyCode::Minus | KeyCode::NumpadSubtract => {
+                self.set_move_period(self.move_period + MOVE_PERIOD_STEP);
+                None
+            },
+            // 'B' rewinds a lost game, if any rewinds are left.
+            KeyCode::B if self.game_over => {
+                self.rewind();
+                None
+            },
+            // 'A' hands control to the autopilot (or takes it back).
+            KeyCode::A => {
+                self.autopilot = !self.autopilot;
+                self.refresh_ai_path();
+                None
+            },
+            // The display toggles below are remembered in the settings, which are saved on quit.
+            // 'H' hides or shows the HUD; Tab moves it around the screen.
+            KeyCode::H => {
+                self.hud_visible = !self.hud_visible;
+                self.settings.hud_visible = self.hud_visible;
+                None
+            },
+            KeyCode::Tab => {
+                self.hud_position = self.hud_position.next();
+                self.settings.hud_position = self.hud_position;
+                None
+            },
+            // 'X' toggles mirror mode.
+            KeyCode::X => {
+                self.invert_controls = !self.invert_controls;
+                self.settings.invert_controls = self.invert_controls;
+                None
+            },
+            // 'G' shows or hides the row and column numbers.
+            KeyCode::G => {
+                self.show_rulers = !self.show_rulers;
+                self.settings.rulers = self.show_rulers;
+                None
+            },
+            // 'I' shows or hides the head coordinate readout.
+            KeyCode::I => {
+                self.show_head_info = !self.show_head_info;
+                self.settings.head_info = self.show_head_info;
+                None
+            },
+            // Ctrl+S saves everything as it is now, command line flags included.
+            KeyCode::S if mods.contains(KeyMods::CTRL) => {
+                self.save_current_settings();
+                None
+            },
+            // Debug: F1 clears the walls and F2 puts them back.
+            KeyCode::F1 if self.options.debug => {
+                self.clear_obstacles();
+                None
+            },
+            KeyCode::F2 if self.options.debug => {
+                self.regenerate_obstacles();
+                None
+            },
+            // F3 shows or hides the debug heatmap of visited cells.
+            KeyCode::F3 if self.options.debug => {
+                self.show_heatmap = !self.show_heatmap;
+                None
+            },
+            // 'L' cycles the debug cell labels.
+            KeyCode::L if self.options.debug => {
+                self.cell_label = self.cell_label.next();
+                None
+            },
+            // Volume: ',' and '.' adjust the music, '[' and ']' the effects; 'M' and 'N' mute them.
+            KeyCode::Comma | KeyCode::Period => {
+                let delta = if key == KeyCode::Comma { -audio::VOLUME_STEP } else { audio::VOLUME_STEP };
+                self.settings.music_volume = audio::adjust_volume(self.settings.music_volume, delta);
+                self.volume_changed();
+                None
+            },
+            KeyCode::LBracket | KeyCode::RBracket => {
+                let delta = if key == KeyCode::LBracket { -audio::VOLUME_STEP } else { audio::VOLUME_STEP };
+                self.settings.sfx_volume = audio::adjust_volume(self.settings.sfx_volume, delta);
+                self.volume_changed();
+                None
+            },
+            KeyCode::M => {
+                self.settings.music_muted = !self.settings.music_muted;
+                self.volume_changed();
+                None
+            },
+            KeyCode::N => {
+                self.settings.sfx_muted = !self.settings.sfx_muted;
+                self.volume_changed();
+                None
+            },
+            // Space enters or leaves plan mode; leaving it plays back the queued moves.
+            KeyCode::Space if !self.game_over => {
+                self.planning = !self.planning;
+                None
+            },
+            // Page Up and Page Down scroll the side panel.
+            KeyCode::PageUp if self.options.panel => {
+                self.panel_scroll = self.panel_scroll.saturating_sub(PANEL_SCROLL_STEP);
+                None
+            },
+            KeyCode::PageDown if self.options.panel => {
+                let last = self.snake.len().saturating_sub(1);
+                self.panel_scroll = (self.panel_scroll + PANEL_SCROLL_STEP).min(last);
+                None
+            },
+            // 'V' toggles drawing the autopilot's planned path.
+            KeyCode::V => {
+                self.show_ai_path = !self.show_ai_path;
+                self.refresh_ai_path();
+                None
+            },
+            _ => None,
+        };
+
+        if let Some(dir) = new_direction {
+            self.steer(dir, repeat);
+        }
+        self.play_pending_sounds(ctx);
+    }
+}
+
+/// Loads a TTF font from an arbitrary file, falling back to the default font on failure.
+fn load_font(ctx: &mut Context, path: &std::path::Path) -> Font {
+    let font = std::fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| Font::new_glyph_font_bytes(ctx, &bytes).map_err(|e| e.to_string()));
+    font.unwrap_or_else(|e| {
+        eprintln!("could not load font {}: {}; using the default font", path.display(), e);
+        Font::default()
+    })
+}
+
+/// Returns the background shade of cell `p` on the checkerboard: cells alternate
+/// between two near-black greys.
+fn checker_color(p: Point) -> Color {
+    if (p.x + p.y).rem_euclid(2) == 0 {
+        Color::from_rgb(18, 18, 18)
+    } else {
+        Color::from_rgb(30, 30, 30)
+    }
+}
+
+/// Returns the color food of the given kind is drawn in: red, or purple for poison.
+fn food_color(kind: FoodKind) -> Color {
+    match kind {
+        FoodKind::Normal => Color::from_rgb(255, 0, 0),
+        FoodKind::Poison => Color::from_rgb(160, 0, 200),
+    }
+}
+
+/// Returns the seed for the daily challenge on `date`, the same for everyone that day.
+///
+/// Hashed with a prefix so the food sequence doesn't follow the maze seed, which is
+/// the bare date.
+fn daily_seed(date: &str) -> u64 {
+    level::seed_from_str(&format!("daily {}", date))
+}
+
+/// Returns the current time in seconds since the Unix epoch (0 if the clock is before it).
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Returns the initial window size for a grid, with each logical cell drawn
+/// `cell_scale` screen cells wide.
+///
+/// If that window would be larger than `max_window` in either direction, the cells
+/// shrink (to whole pixels, and no smaller than one) until the grid fits inside it.
+fn window_size(grid_width: i32, grid_height: i32, cell_scale: u32, max_window: Option<(f32, f32)>) -> (f32, f32) {
+    let mut cell = CELL_SIZE * cell_scale.max(1) as f32;
+    if let Some((max_width, max_height)) = max_window {
+        let fit = (max_width / grid_width as f32).min(max_height / grid_height as f32);
+        cell = cell.min(fit.floor().max(1.0));
+    }
+    (grid_width as f32 * cell, grid_height as f32 * cell)
+}
+
+/// Returns the window size for a grid of the given dimensions, with room for the side
+/// panel and the border if they are on.
+fn window_dimensions(grid_width: i32, grid_height: i32, options: &Options) -> (f32, f32) {
+    // The side panel sits beside the grid and the border around it, so they take
+    // their room out of the maximum.
+    let panel_width = if options.panel { PANEL_WIDTH } else { 0.0 };
+    let margin = 2.0 * options.border.unwrap_or(0.0);
+    let max_window = options.max_window.map(|(w, h)| ((w - panel_width - margin).max(1.0), (h - margin).max(1.0)));
+    let (grid_window_width, grid_window_height) = window_size(grid_width, grid_height, options.cell_scale, max_window);
+    (grid_window_width + panel_width + margin, grid_window_height + margin)
+}
+
+/// Creates the window, sized to fit a grid of the given dimensions, and its event loop.
+fn build_context(grid_width: i32, grid_height: i32, options: &Options) -> GameResult<(Context, event::EventLoop<()>)> {
+    let (window_width, window_height) = window_dimensions(grid_width, grid_height, options);
+    let mut context_builder = ContextBuilder::new("snake_game", "Author")
+        .window_setup(ggez::conf::WindowSetup::default().title(WINDOW_TITLE))
+        .window_mode(ggez::conf::WindowMode::default().dimensions(window_width, window_height).resizable(true));
+    // When run through cargo, look for sounds and other assets in the crate's resources directory.
+    if let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") {
+        context_builder = context_builder.add_resource_path(PathBuf::from(manifest_dir).join("resources"));
+    }
+    context_builder.build()
+}
+
+/// The main function sets up the game window and starts the event loop.
+fn main() -> GameResult {
+    let options = Options::parse(std::env::args().skip(1)).map_err(ggez::GameError::ConfigError)?;
+
+    // The swarm demo replaces the game entirely.
+    if let Some(count) = options.demo {
+        let width = options.grid_width.unwrap_or(DEFAULT_GRID_SIZE);
+        let height = options.grid_height.unwrap_or(DEFAULT_GRID_SIZE);
+        let swarm = Swarm::new(count, width, height, options.seed.unwrap_or_else(rand::random));
+        let (ctx, event_loop) = build_context(swarm.grid_width, swarm.grid_height, &options)?;
+        event::run(ctx, event_loop, swarm)
+    }
+
+    // So does watching a recorded run.
+    if let Some(path) = &options.replay_path {
+        let replay = Replay::load(path).map_err(ggez::GameError::ResourceLoadError)?;
+        let viewer = ReplayViewer::new(replay);
+        let (ctx, event_loop) = build_context(viewer.replay.grid_width, viewer.replay.grid_height, &options)?;
+        event::run(ctx, event_loop, viewer)
+    }
+
+    // Build the game first so the window can be sized to its grid.
+    let mut game = match &options.level_path {
+        Some(path) => {
+            let level = Level::load(path, options.level_y_up).map_err(ggez::GameError::ResourceLoadError)?;
+            SnakeGame::from_level(level, options)
+        }
+        None => {
+            let width = options.grid_width.unwrap_or(DEFAULT_GRID_SIZE);
+            let height = options.grid_height.unwrap_or(DEFAULT_GRID_SIZE);
+            SnakeGame::new(width, height, options)
+        }
+    };
+
+    if let Some(path) = &game.options.locale_path {
+        match Strings::load(path) {
+            Ok(strings) => game.strings = strings,
+            Err(e) => eprintln!("ignoring locale file {}: {}", path.display(), e),
+        }
+    }
+
+    // The terminal frontend runs the same game without opening a window.
+    if game.options.terminal {
+        #[cfg(feature = "terminal")]
+        return terminal::run(game).map_err(ggez::GameError::from);
+        #[cfg(not(feature = "terminal"))]
+        return Err(ggez::GameError::ConfigError(
+            "built without terminal support; rebuild with --features terminal".to_string(),
+        ));
+    }
+
+    if game.options.stdin_input {
+        game.script_input = Some(script::read_stdin());
+    }
+
+    // Create a new ggez Context and event loop.
+    let (mut ctx, event_loop) = build_context(game.grid_width, game.grid_height, &game.options)?;
+
+    // Load saved preferences and whatever sounds are available.
+    game.load_settings(ggez::filesystem::user_config_dir(&ctx).join("config.toml"));
+    // The grid size picked last session applies unless this one chose its own. The
+    // window was sized before the settings could be found, so it is resized.
+    if let Some(size) = game.saved_grid_size() {
+        game.set_grid_size(size);
+        game.fit_window_to_grid(&mut ctx);
+    }
+    let scores_path = ggez::filesystem::user_data_dir(&ctx).join("scores.toml");
+    game.scores = Scores::load(&scores_path);
+    game.scores_path = Some(scores_path);
+    let streak_path = ggez::filesystem::user_data_dir(&ctx).join("streak.toml");
+    game.streak = Streak::load(&streak_path);
+    game.streak_path = Some(streak_path);
+    game.audio = Audio::load(&mut ctx);
+    if game.options.autosave_interval.is_some() {
+        let autosave_path = ggez::filesystem::user_data_dir(&ctx).join("autosave.toml");
+        if autosave_path.exists() {
+            match SaveState::load(&autosave_path) {
+                Ok(state) => game.resume_offer = Some(state),
+                Err(e) => eprintln!("ignoring unreadable autosave {}: {}", autosave_path.display(), e),
+            }
+        }
+        game.autosave_path = Some(autosave_path);
+    }
+    if game.options.ghost {
+        let ghost_path = ggez::filesystem::user_data_dir(&ctx).join("best_run.toml");
+        if ghost_path.exists() {
+            match Replay::load(&ghost_path) {
+                Ok(best) if (best.grid_width, best.grid_height) == (game.grid_width, game.grid_height) => {
+                    game.ghost = Some(best)
+                }
+                Ok(_) => eprintln!("ignoring best run {}: it was played on another grid size", ghost_path.display()),
+                Err(e) => eprintln!("ignoring unreadable best run {}: {}", ghost_path.display(), e),
+            }
+        }
+        game.ghost_path = Some(ghost_path);
+    }
+    if let Some(dir) = &game.options.record_frames {
+        match std::fs::create_dir_all(dir) {
+            Ok(()) => {
+                eprintln!(
+                    "recording frames to {}; this slows the game down and is meant for short clips",
+                    dir.display()
+                );
+                game.frame_dir = Some(dir.clone());
+            }
+            Err(e) => eprintln!("not recording frames: could not create {}: {}", dir.display(), e),
+        }
+    }
+    if let Some(path) = &game.options.font_path {
+        game.font = load_font(&mut ctx, path);
+    }
+    game.apply_volumes();
+    game.audio.start_music(&ctx);
+
+    // Run the game event loop.
+    event::run(ctx, event_loop, game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses command-line style `args` into options.
+    pub(crate) fn options(args: &[&str]) -> Options {
+        Options::parse(args.iter().map(|s| s.to_string())).unwrap()
+    }
+
+    /// Builds a game on a `width` by `height` grid from command-line style `args`.
+    pub(crate) fn game(width: i32, height: i32, args: &[&str]) -> SnakeGame {
+        SnakeGame::new(width, height, options(args))
+    }
+
+    /// Builds a game like `game`, past its opening fade so `tick` moves the snake.
+    pub(crate) fn running(width: i32, height: i32, args: &[&str]) -> SnakeGame {
+        let mut game = game(width, height, args);
+        game.transition_left = 0.0;
+        game
+    }
+
+    #[test]
+    fn autopilot_path_runs_from_head_to_food() {
+        let mut game = game(20, 20, &["--seed", "1"]);
+        game.food = Some(Point { x: 3, y: 15 });
+        let (dir, path) = game.choose_ai_direction();
+        let path = path.unwrap();
+        assert_eq!(path.first(), game.snake.first());
+        assert_eq!(path.last(), game.food.as_ref());
+        assert_eq!(path[1], ai::step(game.snake[0], dir));
+    }
+
+    #[test]
+    fn autopilot_has_no_path_to_walled_off_food() {
+        let mut game = game(20, 20, &["--seed", "1"]);
+        let food = Point { x: 0, y: 0 };
+        game.food = Some(food);
+        game.obstacles = vec![Point { x: 1, y: 0 }, Point { x: 0, y: 1 }];
+        let (dir, path) = game.choose_ai_direction();
+        assert!(path.is_none());
+        assert!(game.safe_moves().contains(&dir));
+    }
+
+    #[test]
+    fn the_drawn_autopilot_path_is_planned_once_per_step() {
+        let mut game = running(20, 20, &["--seed", "1"]);
+        game.autopilot = true;
+        game.show_ai_path = true;
+        take_steps(&mut game, 1);
+        assert_eq!(game.ai_path, game.choose_ai_direction().1);
+        assert_eq!(game.ai_path.as_ref().and_then(|path| path.first()), game.snake.first());
+        game.show_ai_path = false;
+        game.refresh_ai_path();
+        assert_eq!(game.ai_path, None);
+    }
+
+    #[test]
+    fn step_on_input_waits_for_a_press() {
+        let mut game = running(20, 20, &["--seed", "1", "--step-on-input"]);
+        let start = game.snake.clone();
+        for _ in 0..50 {
+            game.tick(0.1);
+        }
+        assert_eq!(game.snake, start);
+    }
+
+    #[test]
+    fn step_on_input_moves_one_cell_per_press() {
+        let mut game = running(20, 20, &["--seed", "1", "--step-on-input", "--start-length", "3"]);
+        let head = game.snake[0];
+        game.steer(Direction::Right, false);
+        assert_eq!(game.snake[0], Point { x: head.x + 1, y: head.y });
+        game.steer(Direction::Down, false);
+        assert_eq!(game.snake[0], Point { x: head.x + 1, y: head.y + 1 });
+        // Held keys repeat, but a repeat doesn't take another step.
+        game.steer(Direction::Down, true);
+        assert_eq!(game.snake[0], Point { x: head.x + 1, y: head.y + 1 });
+        // Nor does a reversal, which isn't a legal turn.
+        game.steer(Direction::Up, false);
+        assert_eq!(game.snake[0], Point { x: head.x + 1, y: head.y + 1 });
+    }
+
+    /// Puts food of `kind` on the cell straight ahead of the head.
+    fn food_ahead(game: &mut SnakeGame, kind: FoodKind) -> Point {
+        let cell = ai::step(game.snake[0], game.direction);
+        game.food = Some(cell);
+        game.food_kind = kind;
+        cell
+    }
+
+    #[test]
+    fn poison_shrinks_the_snake_by_its_amount() {
+        let mut game = game(20, 20, &["--seed", "1", "--poison", "--start-length", "8"]);
+        game.score = 4;
+        food_ahead(&mut game, FoodKind::Poison);
+        game.update_snake();
+        assert_eq!(game.snake.len(), 8 - POISON_SHRINK);
+        assert_eq!(game.score, 4 - POISON_PENALTY);
+        assert!(!game.game_over);
+    }
+
+    #[test]
+    fn poison_never_shrinks_the_snake_below_its_head() {
+        let mut game = game(20, 20, &["--seed", "1", "--poison", "--start-length", "2"]);
+        let cell = food_ahead(&mut game, FoodKind::Poison);
+        game.update_snake();
+        assert_eq!(game.snake, vec![cell]);
+        // The score doesn't go below zero either.
+        assert_eq!(game.score, 0);
+    }
+
+    #[test]
+    fn cell_labels_show_coordinates_or_food_distance() {
+        let mut game = game(5, 5, &["--seed", "1"]);
+        game.food = Some(Point { x: 0, y: 0 });
+        let distances = game.food_distance_field();
+        let cell = Point { x: 1, y: 2 };
+        assert_eq!(game.cell_label_text(CellLabel::Off, &distances, cell), None);
+        assert_eq!(game.cell_label_text(CellLabel::Coordinates, &distances, cell).as_deref(), Some("1,2"));
+        assert_eq!(game.cell_label_text(CellLabel::FoodDistance, &distances, cell).as_deref(), Some("3"));
+        // The snake's own cells can't be walked through, so they have no distance.
+        let head = game.snake[0];
+        assert_eq!(game.cell_label_text(CellLabel::FoodDistance, &distances, head), None);
+    }
+
+    /// Runs `game` until the snake has taken `steps` more steps.
+    fn take_steps(game: &mut SnakeGame, steps: u64) {
+        let target = game.tick + steps;
+        while game.tick < target && !game.game_over {
+            game.tick(game.move_period);
+        }
+    }
+
+    #[test]
+    fn planned_turns_wait_for_the_freeze_to_end() {
+        let mut game = running(20, 20, &["--seed", "1", "--start-length", "3"]);
+        let head = game.snake[0];
+        game.planning = true;
+        for dir in [Direction::Up, Direction::Left, Direction::Left, Direction::Down] {
+            game.steer(dir, false);
+        }
+        game.tick(1.0);
+        assert_eq!(game.snake[0], head);
+        assert_eq!(game.planned_turns.len(), 4);
+
+        game.planning = false;
+        let mut visited = Vec::new();
+        for _ in 0..4 {
+            take_steps(&mut game, 1);
+            visited.push(game.snake[0]);
+        }
+        let (x, y) = (head.x, head.y);
+        let expected = [(x, y - 1), (x - 1, y - 1), (x - 2, y - 1), (x - 2, y)];
+        assert_eq!(visited, expected.map(|(x, y)| Point { x, y }));
+        assert!(game.planned_turns.is_empty());
+    }
+
+    #[test]
+    fn planning_rejects_reversing_the_previous_planned_move() {
+        let mut game = running(20, 20, &["--seed", "1", "--start-length", "3"]);
+        game.planning = true;
+        game.steer(Direction::Up, false);
+        game.steer(Direction::Down, false);
+        assert_eq!(game.planned_turns, [Direction::Up]);
+    }
+
+    #[test]
+    fn rewinding_a_crash_restores_the_run_at_a_cost() {
+        let mut game = game(20, 20, &["--seed", "1", "--rewinds", "2", "--start-length", "3"]);
+        game.score = 10;
+        game.food = None;
+        while !game.game_over {
+            game.update_snake();
+        }
+        assert_eq!(game.death_cause, Some(DeathCause::Wall));
+        let crashed_at = game.snake[0];
+
+        assert!(game.rewind());
+        assert!(!game.game_over);
+        assert_eq!(game.death_cause, None);
+        assert_eq!(game.score, 10 - rewind_penalty(10));
+        assert_eq!(game.rewinds_left, 1);
+        // The snake is back where it was REWIND_TICKS steps before the crash.
+        assert_eq!(game.snake[0], Point { x: crashed_at.x - REWIND_TICKS as i32 + 1, y: crashed_at.y });
+        assert_eq!(game.snake.len(), 3);
+    }
+
+    #[test]
+    fn rewinding_needs_a_lost_game_and_a_rewind_left() {
+        let mut game = game(20, 20, &["--seed", "1", "--rewinds", "1"]);
+        assert!(!game.rewind());
+        game.food = None;
+        while !game.game_over {
+            game.update_snake();
+        }
+        assert!(game.rewind());
+        while !game.game_over {
+            game.update_snake();
+        }
+        assert!(!game.rewind());
+        assert!(game.game_over);
+    }
+
+    #[test]
+    fn move_period_is_clamped_to_both_bounds() {
+        let mut game = game(20, 20, &[]);
+        game.set_move_period(0.0);
+        assert_eq!(game.move_period, MIN_MOVE_PERIOD);
+        game.set_move_period(-1.0);
+        assert_eq!(game.move_period, MIN_MOVE_PERIOD);
+        game.set_move_period(60.0);
+        assert_eq!(game.move_period, MAX_MOVE_PERIOD);
+        game.set_move_period(0.5);
+        assert_eq!(game.move_period, 0.5);
+        // NaN would poison the clamp, so it leaves the period alone.
+        game.set_move_period(f32::NAN);
+        assert_eq!(game.move_period, 0.5);
+    }
+
+    /// Puts a one-cell snake at `head` heading `direction` and takes one step.
+    fn bump(game: &mut SnakeGame, head: Point, direction: Direction) {
+        game.snake = vec![head];
+        game.prev_snake = game.snake.clone();
+        game.direction = direction;
+        game.next_direction = direction;
+        game.food = None;
+        game.update_snake();
+    }
+
+    #[test]
+    fn bumpers_turn_the_snake_along_each_wall() {
+        let mut game = game(10, 10, &["--bumpers"]);
+        let cases = [
+            (Point { x: 3, y: 0 }, Direction::Up, Direction::Right),
+            (Point { x: 6, y: 9 }, Direction::Down, Direction::Left),
+            (Point { x: 0, y: 6 }, Direction::Left, Direction::Up),
+            (Point { x: 9, y: 3 }, Direction::Right, Direction::Down),
+        ];
+        for (head, direction, turn) in cases {
+            bump(&mut game, head, direction);
+            assert!(!game.game_over, "{direction:?} at {head:?}");
+            assert_eq!(game.direction, turn, "{direction:?} at {head:?}");
+            assert_eq!(game.snake[0], ai::step(head, turn));
+        }
+    }
+
+    #[test]
+    fn bumpers_turn_out_of_each_corner() {
+        let mut game = game(10, 10, &["--bumpers"]);
+        let cases = [
+            (Point { x: 0, y: 0 }, Direction::Up, Direction::Right),
+            (Point { x: 9, y: 0 }, Direction::Right, Direction::Down),
+            (Point { x: 9, y: 9 }, Direction::Down, Direction::Left),
+            (Point { x: 0, y: 9 }, Direction::Left, Direction::Up),
+        ];
+        for (head, direction, turn) in cases {
+            bump(&mut game, head, direction);
+            assert!(!game.game_over, "{direction:?} at {head:?}");
+            assert_eq!(game.direction, turn, "{direction:?} at {head:?}");
+        }
+    }
+
+    #[test]
+    fn bumpers_still_kill_in_a_blocked_corner() {
+        let mut game = game(10, 10, &["--bumpers"]);
+        game.obstacles = vec![Point { x: 1, y: 0 }];
+        bump(&mut game, Point { x: 0, y: 0 }, Direction::Up);
+        assert!(game.game_over);
+        assert_eq!(game.death_cause, Some(DeathCause::Wall));
+    }
+
+    #[test]
+    fn without_bumpers_the_wall_kills() {
+        let mut game = game(10, 10, &[]);
+        bump(&mut game, Point { x: 3, y: 0 }, Direction::Up);
+        assert!(game.game_over);
+        assert_eq!(game.death_cause, Some(DeathCause::Wall));
+    }
+
+    /// Averages `measure` over food spawned with `strategy` from many seeds.
+    fn mean_spawn(
+        strategy: SpawnStrategy,
+        snake: &[Point],
+        obstacles: &[Point],
+        measure: impl Fn(Point) -> i32,
+    ) -> f32 {
+        let total: i32 = (0..200)
+            .map(|seed| {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let food = SnakeGame::generate_food(&mut rng, strategy, snake, obstacles, 20, 20).unwrap();
+                assert!(!snake.contains(&food) && !obstacles.contains(&food));
+                measure(food)
+            })
+            .sum();
+        total as f32 / 200.0
+    }
+
+    #[test]
+    fn far_spawning_lands_farther_from_the_head() {
+        let snake = [Point { x: 4, y: 4 }, Point { x: 3, y: 4 }, Point { x: 2, y: 4 }];
+        let from_head = |p: Point| (p.x - 4).abs() + (p.y - 4).abs();
+        let uniform = mean_spawn(SpawnStrategy::Uniform, &snake, &[], from_head);
+        let far = mean_spawn(SpawnStrategy::Far, &snake, &[], from_head);
+        assert!(far > uniform + 5.0, "far {far} vs uniform {uniform}");
+    }
+
+    #[test]
+    fn cleared_obstacles_no_longer_block() {
+        let mut game = game(20, 20, &["--maze", "--seed", "3"]);
+        assert!(!game.obstacles.is_empty());
+        let wall = game.obstacles[0];
+        game.clear_obstacles();
+        assert!(game.obstacles.is_empty());
+
+        // Drive a one-cell snake straight into where the wall was.
+        let (head, direction) = if wall.x > 0 {
+            (Point { x: wall.x - 1, y: wall.y }, Direction::Right)
+        } else {
+            (Point { x: wall.x + 1, y: wall.y }, Direction::Left)
+        };
+        bump(&mut game, head, direction);
+        assert!(!game.game_over);
+        assert_eq!(game.snake[0], wall);
+
+        game.regenerate_obstacles();
+        assert!(!game.obstacles.is_empty());
+        assert!(!game.obstacles.contains(&wall), "walls never come back under the snake");
+    }
+
+    #[test]
+    fn time_attack_ends_exactly_at_the_limit() {
+        let mut game = game(20, 20, &["--time-limit", "1"]);
+        game.advance_clock(0.75);
+        assert!(!game.game_over);
+        assert_eq!(game.time_left(), Some(0.25));
+        // Overshooting the limit in one frame still stops the clock right on it.
+        game.advance_clock(0.5);
+        assert!(game.game_over);
+        assert_eq!(game.death_cause, Some(DeathCause::TimeUp));
+        assert_eq!(game.elapsed, 1.0);
+        assert_eq!(game.time_left(), Some(0.0));
+        game.advance_clock(5.0);
+        assert_eq!(game.time_left(), Some(0.0));
+    }
+
+    #[test]
+    fn untimed_games_have_no_time_left() {
+        let mut game = game(20, 20, &[]);
+        game.advance_clock(1000.0);
+        assert!(!game.game_over);
+        assert_eq!(game.time_left(), None);
+    }
+
+    #[test]
+    fn points_off_each_edge_wrap_to_the_opposite_one() {
+        assert_eq!(wrap_point(Point { x: -1, y: 4 }, 10, 8), Point { x: 9, y: 4 });
+        assert_eq!(wrap_point(Point { x: 10, y: 4 }, 10, 8), Point { x: 0, y: 4 });
+        assert_eq!(wrap_point(Point { x: 3, y: -1 }, 10, 8), Point { x: 3, y: 7 });
+        assert_eq!(wrap_point(Point { x: 3, y: 8 }, 10, 8), Point { x: 3, y: 0 });
+        assert_eq!(wrap_point(Point { x: 3, y: 4 }, 10, 8), Point { x: 3, y: 4 });
+    }
+
+    #[test]
+    fn an_obstacle_at_the_wrap_destination_still_kills() {
+        let mut game = game(10, 10, &["--wrap"]);
+        game.obstacles = vec![Point { x: 0, y: 5 }];
+        bump(&mut game, Point { x: 9, y: 5 }, Direction::Right);
+        assert!(game.game_over);
+        assert_eq!(game.death_cause, Some(DeathCause::Obstacle));
+    }
+
+    #[test]
+    fn a_clear_wrap_destination_is_safe() {
+        let mut game = game(10, 10, &["--wrap"]);
+        bump(&mut game, Point { x: 5, y: 0 }, Direction::Up);
+        assert!(!game.game_over);
+        assert_eq!(game.snake[0], Point { x: 5, y: 9 });
+    }
+
+    #[test]
+    fn a_full_board_has_nowhere_for_food() {
+        let snake = [Point { x: 0, y: 0 }, Point { x: 1, y: 0 }, Point { x: 1, y: 1 }];
+        let obstacles = [Point { x: 0, y: 1 }];
+        let mut rng = StdRng::seed_from_u64(1);
+        for strategy in [SpawnStrategy::Uniform, SpawnStrategy::Far, SpawnStrategy::Open, SpawnStrategy::NearTail] {
+            assert_eq!(SnakeGame::generate_food(&mut rng, strategy, &snake, &obstacles, 2, 2), None);
+        }
+    }
+
+    #[test]
+    fn filling_the_board_ends_the_run() {
+        let mut game = game(20, 20, &[]);
+        game.grid_width = 2;
+        game.grid_height = 2;
+        game.snake = vec![Point { x: 0, y: 0 }, Point { x: 1, y: 0 }, Point { x: 1, y: 1 }];
+        game.obstacles = vec![Point { x: 0, y: 1 }];
+        game.spawn_food();
+        assert_eq!(game.food, None);
+        assert!(game.game_over);
+        assert_eq!(game.death_cause, Some(DeathCause::BoardFull));
+    }
+
+    #[test]
+    fn crossings_are_detected_off_each_edge() {
+        let crossing = |from: (i32, i32), to: (i32, i32)| {
+            wrap_crossing(Point { x: from.0, y: from.1 }, Point { x: to.0, y: to.1 }, 10, 8)
+        };
+        assert_eq!(crossing((9, 3), (0, 3)), Some(Direction::Right));
+        assert_eq!(crossing((0, 3), (9, 3)), Some(Direction::Left));
+        assert_eq!(crossing((4, 0), (4, 7)), Some(Direction::Up));
+        assert_eq!(crossing((4, 7), (4, 0)), Some(Direction::Down));
+    }
+
+    #[test]
+    fn ordinary_steps_and_jumps_are_not_crossings() {
+        assert_eq!(wrap_crossing(Point { x: 4, y: 3 }, Point { x: 5, y: 3 }, 10, 8), None);
+        assert_eq!(wrap_crossing(Point { x: 4, y: 3 }, Point { x: 7, y: 3 }, 10, 8), None);
+        // On a two-wide grid the other column is next door both ways; that's a plain step.
+        assert_eq!(wrap_crossing(Point { x: 1, y: 3 }, Point { x: 0, y: 3 }, 2, 8), None);
+    }
+
+    #[test]
+    fn a_straight_step_is_held_for_the_grace_window() {
+        // Around a 0.2s step with a 0.03s grace window: a turn pressed before the step
+        // is taken on time, and one pressed during the hold takes the held step at once.
+        assert!(!step_due(0.19, 0.2, 0.03, Some(0.1)));
+        assert!(step_due(0.21, 0.2, 0.03, Some(0.1)));
+        assert!(step_due(0.22, 0.2, 0.03, Some(0.22)));
+        assert!(!step_due(0.21, 0.2, 0.03, None));
+        assert!(!step_due(0.23, 0.2, 0.03, None));
+        assert!(step_due(0.231, 0.2, 0.03, None));
+        // Without a window a straight step isn't held.
+        assert!(step_due(0.201, 0.2, 0.0, None));
+    }
+
+    #[test]
+    fn a_turn_just_after_a_step_comes_due_still_makes_it() {
+        let mut game = running(20, 20, &["--seed", "1", "--start-length", "3", "--move-period", "0.2"]);
+        game.food = None;
+        let (head, tick) = (game.snake[0], game.tick);
+        let turn = Direction::Up;
+        game.tick(0.21);
+        assert_eq!(game.snake[0], head, "the straight step is held");
+        game.steer(turn, false);
+        game.tick(0.01);
+        assert_eq!(game.tick, tick + 1);
+        assert_eq!(game.snake[0], ai::step(head, turn));
+        // The next step keeps to the schedule: due 0.2s after the first, then held.
+        game.tick(0.2);
+        assert_eq!(game.tick, tick + 1);
+        game.tick(0.02);
+        assert_eq!(game.tick, tick + 2);
+    }
+
+    #[test]
+    fn a_turn_after_the_grace_window_waits_for_the_next_step() {
+        let mut game = running(20, 20, &["--seed", "1", "--start-length", "3", "--move-period", "0.2", "--grace", "0.01"]);
+        game.food = None;
+        let (head, tick) = (game.snake[0], game.tick);
+        let turn = Direction::Up;
+        game.tick(0.22);
+        assert_eq!(game.snake[0], ai::step(head, game.direction), "the step went straight");
+        game.steer(turn, false);
+        assert_eq!(game.tick, tick + 1);
+        assert_eq!(game.next_direction, turn);
+    }
+
+    #[test]
+    fn head_info_shows_the_head_and_both_directions() {
+        let snake = [Point { x: 3, y: 7 }, Point { x: 2, y: 7 }];
+        assert_eq!(head_info_text(&snake, Direction::Right, Direction::Up), "Head (3, 7)  dir R  next U");
+        assert_eq!(head_info_text(&[], Direction::Left, Direction::Down), "Head (none)  dir L  next D");
+    }
+
+    #[test]
+    fn inverted_controls_flip_every_press() {
+        for dir in ai::DIRECTIONS {
+            assert_eq!(input_direction(dir, false), dir);
+            assert_eq!(input_direction(dir, true), dir.opposite());
+        }
+    }
+
+    #[test]
+    fn an_inverted_press_still_cannot_reverse_the_snake() {
+        // Heading right, pressing Up asks for Down.
+        let mut game = running(20, 20, &["--seed", "1", "--start-length", "3", "--mirror"]);
+        assert_eq!(game.direction, Direction::Right);
+        game.steer(Direction::Up, false);
+        assert_eq!(game.next_direction, Direction::Down);
+        // Pressing Right asks for Left, a reversal, which is refused.
+        game.steer(Direction::Right, false);
+        assert_eq!(game.next_direction, Direction::Down);
+    }
+
+    #[test]
+    fn only_a_lone_head_may_reverse() {
+        for current in ai::DIRECTIONS {
+            for dir in ai::DIRECTIONS {
+                let reversal = dir == current.opposite();
+                assert!(turn_is_legal(current, dir, 0));
+                assert!(turn_is_legal(current, dir, 1));
+                assert_eq!(turn_is_legal(current, dir, 2), !reversal, "{current:?} to {dir:?}");
+                assert_eq!(turn_is_legal(current, dir, 5), !reversal, "{current:?} to {dir:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn reversal_is_judged_from_the_neck() {
+        let mut game = game(20, 20, &[]);
+        // The last move was up, even though `direction` still says right.
+        game.snake = vec![Point { x: 5, y: 4 }, Point { x: 5, y: 5 }, Point { x: 4, y: 5 }];
+        game.direction = Direction::Right;
+        assert!(!game.is_legal_turn(Direction::Down));
+        assert!(game.is_legal_turn(Direction::Left));
+        assert!(game.is_legal_turn(Direction::Up));
+
+        game.snake.truncate(1);
+        for dir in ai::DIRECTIONS {
+            assert!(game.is_legal_turn(dir));
+        }
+    }
+
+    /// Returns a path in the temp directory unique to this test process and `name`.
+    pub(crate) fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("snake-test-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn autosave_waits_for_its_interval_and_round_trips() {
+        let path = temp_path("autosave.toml");
+        let mut original = running(20, 20, &["--seed", "4", "--start-length", "3", "--autosave", "1"]);
+        take_steps(&mut original, 3);
+        original.score = 7;
+        original.autosave_path = Some(path.clone());
+        original.autosave_timer = 0.0;
+
+        assert!(!original.tick_autosave(0.5));
+        assert!(!path.exists());
+        assert!(original.tick_autosave(0.6));
+        assert_eq!(original.autosave_timer, 0.0);
+
+        let mut resumed = game(20, 20, &[]);
+        resumed.apply_save(SaveState::load(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.snake, original.snake);
+        assert_eq!(resumed.food, original.food);
+        assert_eq!(resumed.score, 7);
+        assert_eq!(resumed.direction, original.direction);
+        assert_eq!(resumed.move_period, original.move_period);
+    }
+
+    #[test]
+    fn a_save_for_another_grid_size_is_refused() {
+        let saved = game(20, 20, &[]).save_state();
+        let mut other = game(10, 10, &[]);
+        assert!(other.apply_save(saved).is_err());
+    }
+
+    #[test]
+    fn vertical_moves_take_the_scaled_period() {
+        assert_eq!(axis_period(0.1, 2.0, Direction::Left), 0.1);
+        assert_eq!(axis_period(0.1, 2.0, Direction::Right), 0.1);
+        assert_eq!(axis_period(0.1, 2.0, Direction::Up), 0.2);
+        assert_eq!(axis_period(0.1, 2.0, Direction::Down), 0.2);
+        // No factor leaves both axes alike, and a scaled period stays in range.
+        assert_eq!(axis_period(0.1, 0.0, Direction::Up), 0.1);
+        assert_eq!(axis_period(0.1, 0.01, Direction::Up), MIN_MOVE_PERIOD);
+        assert_eq!(axis_period(1.5, 4.0, Direction::Down), MAX_MOVE_PERIOD);
+    }
+
+    #[test]
+    fn the_step_period_follows_the_queued_direction() {
+        let mut game = game(20, 20, &["--vertical-factor", "2"]);
+        game.set_move_period(0.1);
+        game.next_direction = Direction::Right;
+        assert_eq!(game.step_period(), 0.1);
+        game.next_direction = Direction::Up;
+        assert_eq!(game.step_period(), 0.2);
+    }
+
+    #[test]
+    fn the_bonus_blinks_faster_as_it_runs_out() {
+        assert_eq!(bonus_blink_frequency(1.0), 0.0);
+        assert_eq!(bonus_blink_frequency(0.5), 0.0);
+        assert!((bonus_blink_frequency(0.35) - 2.5).abs() < 1e-5);
+        assert!((bonus_blink_frequency(0.2) - 4.0).abs() < 1e-5);
+        assert_eq!(bonus_blink_frequency(0.19), 8.0);
+        assert_eq!(bonus_blink_frequency(0.0), 8.0);
+        let mut last = 0.0;
+        for step in (0..=100).rev() {
+            let frequency = bonus_blink_frequency(step as f32 / 100.0);
+            assert!(frequency >= last, "slower at {step}%");
+            last = frequency;
+        }
+    }
+
+    #[test]
+    fn every_start_direction_gives_a_playable_body() {
+        for (name, dir) in [
+            ("up", Direction::Up),
+            ("down", Direction::Down),
+            ("left", Direction::Left),
+            ("right", Direction::Right),
+        ] {
+            let game = game(10, 10, &["--start-dir", name, "--start-length", "4"]);
+            assert_eq!(game.direction, dir);
+            assert_eq!(game.snake.len(), 4);
+            assert!(game.snake.iter().all(|&p| game.in_bounds(p)));
+            for (i, p) in game.snake.iter().enumerate() {
+                assert!(!game.snake[i + 1..].contains(p), "{name}: overlapping body");
+            }
+            // The body trails behind the head, so the first step goes to a free cell.
+            assert_eq!(game.snake[1], ai::step(game.snake[0], dir.opposite()));
+            assert!(!game.snake.contains(&ai::step(game.snake[0], dir)));
+        }
+    }
+
+    #[test]
+    fn the_start_body_stops_at_the_edge() {
+        let body = initial_body(Point { x: 1, y: 5 }, Direction::Right, 5, 10, 10);
+        assert_eq!(body, vec![Point { x: 1, y: 5 }, Point { x: 0, y: 5 }]);
+        assert_eq!(initial_body(Point { x: 1, y: 5 }, Direction::Right, 0, 10, 10).len(), 1);
+    }
+
+    #[test]
+    fn each_step_counts_a_visit_to_the_new_head_cell() {
+        let mut game = game(10, 10, &[]);
+        let at = |game: &SnakeGame, x: i32, y: i32| game.visit_counts[(y * game.grid_width + x) as usize];
+        bump(&mut game, Point { x: 2, y: 2 }, Direction::Right);
+        game.next_direction = Direction::Left;
+        game.update_snake();
+        game.next_direction = Direction::Right;
+        game.update_snake();
+        assert_eq!(at(&game, 3, 2), 2);
+        assert_eq!(at(&game, 2, 2), 1);
+        assert_eq!(game.visit_counts.iter().sum::<u32>(), 3);
+        assert!(game.visit_count_mut(Point { x: 10, y: 0 }).is_none());
+        assert!(game.visit_count_mut(Point { x: 0, y: -1 }).is_none());
+    }
+
+    #[test]
+    fn nothing_moves_during_the_fade_in() {
+        let mut game = game(20, 20, &["--seed", "1"]);
+        let head = game.snake[0];
+        let mut waited = 0.0;
+        while game.transition_left > 0.0 {
+            game.tick(game.move_period);
+            waited += game.move_period;
+            assert_eq!(game.snake[0], head);
+            assert_eq!(game.tick, 0);
+            assert_eq!(game.elapsed, 0.0);
+        }
+        assert!(waited >= TRANSITION_TIME);
+        take_steps(&mut game, 1);
+        assert_ne!(game.snake[0], head);
+    }
+
+    #[test]
+    fn reduced_motion_skips_the_fade_in() {
+        let mut game = game(20, 20, &["--seed", "1", "--reduced-motion"]);
+        game.tick(0.01);
+        assert_eq!(game.transition_left, 0.0);
+        assert_eq!(game.elapsed, 0.01);
+    }
+
+    #[test]
+    fn the_ghost_is_where_the_best_run_was_at_the_same_tick() {
+        let mut best = running(20, 20, &["--seed", "2", "--ghost", "--start-length", "3"]);
+        best.food = None;
+        take_steps(&mut best, 6);
+        let ghost = best.recording.clone();
+
+        let mut game = running(20, 20, &["--seed", "2", "--ghost", "--start-length", "3"]);
+        game.ghost = Some(ghost.clone());
+        for tick in 0..6 {
+            assert_eq!(game.ghost_body(), ghost.body_at(tick));
+            take_steps(&mut game, 1);
+        }
+        // A fresh recording, as after a respawn, doesn't set the ghost back.
+        game.recording = Replay::new(&game.snake, 20, 20);
+        assert_eq!(game.ghost_body(), ghost.body_at(6));
+    }
+
+    #[test]
+    fn the_window_fits_the_grid_at_full_cell_size() {
+        assert_eq!(window_size(20, 10, 1, None), (20.0 * CELL_SIZE, 10.0 * CELL_SIZE));
+        assert_eq!(window_size(20, 10, 2, None), (40.0 * CELL_SIZE, 20.0 * CELL_SIZE));
+        assert_eq!(window_size(20, 10, 0, None), window_size(20, 10, 1, None));
+    }
+
+    #[test]
+    fn a_capped_window_shrinks_the_cells_to_fit() {
+        // 100 cells of at most 7.5 pixels: the cells shrink to 7.
+        assert_eq!(window_size(100, 40, 1, Some((750.0, 1000.0))), (700.0, 280.0));
+        // A cap roomier than the grid changes nothing.
+        assert_eq!(window_size(10, 10, 1, Some((5000.0, 5000.0))), window_size(10, 10, 1, None));
+        // Cells never shrink below a pixel.
+        assert_eq!(window_size(100, 100, 1, Some((10.0, 10.0))), (100.0, 100.0));
+    }
+
+    #[test]
+    fn shrinking_stops_at_the_head() {
+        let mut game = game(20, 20, &["--start-length", "4"]);
+        assert_eq!(game.shrink_tail(2), 2);
+        assert_eq!(game.snake.len(), 2);
+        assert_eq!(game.shrink_tail(5), 1);
+        assert_eq!(game.snake.len(), 1);
+        assert_eq!(game.shrink_tail(1), 0);
+        assert_eq!(game.snake.len(), 1);
+    }
+
+    #[test]
+    fn an_empty_snake_is_left_alone() {
+        let mut game = game(20, 20, &["--seed", "1"]);
+        game.snake.clear();
+        let (tick, score) = (game.tick, game.score);
+        game.update_snake();
+        assert!(game.snake.is_empty());
+        assert!(!game.game_over);
+        assert_eq!((game.tick, game.score), (tick, score));
+    }
+
+    #[test]
+    fn items_sharing_a_cell_are_eaten_one_per_step() {
+        let mut game = game(20, 20, &["--seed", "1"]);
+        let cell = Point { x: 5, y: 5 };
+        game.food = Some(cell);
+        game.food_kind = FoodKind::Normal;
+        game.extra_food = Some(cell);
+        game.bonus = Some(cell);
+
+        let first = game.try_eat(cell, 1);
+        assert!(first.food && !first.extra_food);
+        assert_eq!((game.score, game.food_eaten), (1, 1));
+        assert_eq!((game.extra_food, game.bonus), (Some(cell), Some(cell)));
+
+        let second = game.try_eat(cell, 1);
+        assert!(second.extra_food && !second.food);
+        assert_eq!((game.score, game.food_eaten), (2, 2));
+        assert_eq!((game.extra_food, game.bonus), (None, Some(cell)));
+
+        let third = game.try_eat(cell, 1);
+        assert!(!third.food && !third.extra_food && !third.grew);
+        assert_eq!((game.score, game.food_eaten), (2 + BONUS_POINTS, 2));
+        assert_eq!(game.bonus, None);
+    }
+
+    #[test]
+    fn a_magnet_in_reach_of_several_items_still_eats_one() {
+        let mut game = game(20, 20, &["--seed", "1", "--magnet", "1"]);
+        let head = Point { x: 5, y: 5 };
+        game.food = Some(Point { x: 6, y: 5 });
+        game.food_kind = FoodKind::Normal;
+        game.bonus = Some(Point { x: 5, y: 6 });
+        let eaten = game.try_eat(head, 1);
+        assert!(eaten.food);
+        assert_eq!(game.score, 1);
+        assert_eq!(game.bonus, Some(Point { x: 5, y: 6 }));
+    }
+
+    #[test]
+    fn checker_colors_alternate_between_neighbours() {
+        let origin = checker_color(Point { x: 0, y: 0 });
+        assert!(origin != checker_color(Point { x: 1, y: 0 }));
+        assert!(origin != checker_color(Point { x: 0, y: 1 }));
+        assert!(origin == checker_color(Point { x: 1, y: 1 }));
+        assert!(origin == checker_color(Point { x: 4, y: 2 }));
+        // Off-grid cells, left of or above the board, keep the pattern going.
+        assert!(origin == checker_color(Point { x: -1, y: -1 }));
+        assert!(origin != checker_color(Point { x: -1, y: 0 }));
+    }
+
+    #[test]
+    fn the_pause_menu_wraps_around_both_ways() {
+        let mut item = PauseItem::Resume;
+        for expected in [PauseItem::Restart, PauseItem::Quit, PauseItem::Resume] {
+            item = item.next();
+            assert!(item == expected);
+        }
+        for expected in [PauseItem::Quit, PauseItem::Restart, PauseItem::Resume] {
+            item = item.prev();
+            assert!(item == expected);
+        }
+        for item in PauseItem::ALL {
+            assert!(item.next().prev() == item);
+        }
+    }
+
+    #[test]
+    fn choosing_a_pause_item_closes_the_menu_and_acts_on_it() {
+        let mut game = game(20, 20, &["--seed", "1"]);
+        assert!(!game.choose_pause_item(), "nothing to choose with the menu closed");
+
+        game.pause_menu = Some(PauseItem::Resume);
+        game.score = 3;
+        assert!(!game.choose_pause_item());
+        assert!(game.pause_menu.is_none());
+        assert_eq!(game.score, 3);
+
+        game.pause_menu = Some(PauseItem::Restart);
+        assert!(!game.choose_pause_item());
+        assert!(game.pause_menu.is_none());
+        assert_eq!(game.score, 0);
+
+        game.pause_menu = Some(PauseItem::Quit);
+        assert!(game.choose_pause_item());
+    }
+
+    #[test]
+    fn the_first_preview_is_where_the_next_food_spawns() {
+        for seed in ["1", "2", "3"] {
+            let mut game = running(20, 20, &["--seed", seed, "--preview", "3"]);
+            food_ahead(&mut game, FoodKind::Normal);
+            let previews = game.preview_foods(3);
+            assert_eq!(previews.len(), 3);
+            take_steps(&mut game, 1);
+            assert_eq!(game.food_eaten, 1);
+            assert_eq!(game.food, Some(previews[0]));
+        }
+    }
+
+    #[test]
+    fn the_snake_warms_from_green_to_orange_as_it_speeds_up() {
+        let rgb = |period: f32| {
+            let color = speed_color(period);
+            (color.r, color.g, color.b)
+        };
+        assert_eq!(rgb(DEFAULT_MOVE_PERIOD), (0.0, 1.0, 0.0));
+        assert_eq!(rgb(MAX_MOVE_PERIOD), (0.0, 1.0, 0.0));
+        assert_eq!(rgb(MIN_MOVE_PERIOD), (1.0, 0.6, 0.0));
+        let (r, g, b) = rgb((DEFAULT_MOVE_PERIOD + MIN_MOVE_PERIOD) / 2.0);
+        assert!((r - 0.5).abs() < 1e-5 && (g - 0.8).abs() < 1e-5 && b == 0.0, "({r}, {g}, {b})");
+        assert_eq!(speed_color(0.0).a, 1.0);
+    }
+
+    #[test]
+    fn open_spawning_keeps_food_out_of_small_pockets() {
+        // A wall down column 2 shuts off a two-column pocket on the left.
+        let wall: Vec<Point> = (0..20).map(|y| Point { x: 2, y }).collect();
+        let snake = [Point { x: 10, y: 10 }];
+        let in_pocket = |p: Point| i32::from(p.x < 2);
+        let uniform = mean_spawn(SpawnStrategy::Uniform, &snake, &wall, in_pocket);
+        let open = mean_spawn(SpawnStrategy::Open, &snake, &wall, in_pocket);
+        assert!(uniform > 0.05, "uniform spawns reach the pocket ({uniform})");
+        assert_eq!(open, 0.0);
+    }
+
+    #[test]
+    fn an_immediate_reversal_is_refused() {
+        let mut game = running(20, 20, &["--seed", "1", "--start-length", "3"]);
+        assert_eq!(game.next_direction, game.direction);
+        let reverse = game.direction.opposite();
+        game.steer(reverse, false);
+        assert_eq!(game.next_direction, game.direction);
+
+        let forward = game.direction;
+        game.steer(forward, false);
+        take_steps(&mut game, 1);
+        assert!(!game.game_over);
+        assert_eq!(game.snake.len(), 3);
+    }
+
+    #[test]
+    fn zen_mode_glides_forever_at_one_length() {
+        let mut game = running(12, 12, &["--seed", "1", "--zen", "--start-length", "4"]);
+        assert_eq!(game.food, None);
+        for _ in 0..100 {
+            take_steps(&mut game, 1);
+        }
+        assert_eq!(game.tick, 100);
+        assert!(!game.game_over);
+        assert_eq!(game.snake.len(), 4);
+        assert_eq!(game.food, None);
+    }
+
+    #[test]
+    fn a_scripted_game_eats_three_foods_then_hits_the_wall() {
+        let mut game = running(10, 10, &["--seed", "7", "--start-length", "3"]);
+        let mut turns = VecDeque::from([
+            (0, Direction::Up),
+            (5, Direction::Left),
+            (9, Direction::Down),
+            (12, Direction::Left),
+            (13, Direction::Up),
+        ]);
+        while !game.game_over && game.tick < 100 {
+            // Press keys partway between steps.
+            game.tick(0.05);
+            if let Some(&(tick, dir)) = turns.front()
+                && tick == game.tick
+            {
+                game.steer(dir, false);
+                turns.pop_front();
+            }
+            take_steps(&mut game, 1);
+        }
+        assert!(turns.is_empty());
+        assert!(game.game_over);
+        assert_eq!(game.death_cause, Some(DeathCause::Wall));
+        assert_eq!(game.food_eaten, 3);
+        assert_eq!(game.score, 3);
+        assert_eq!(game.snake.len(), 6);
+        assert_eq!(game.snake[0], Point { x: 0, y: 0 });
+    }
+
+    #[test]
+    fn the_head_may_follow_the_tail_round_a_tight_loop() {
+        let mut game = game(10, 10, &[]);
+        game.snake = vec![Point { x: 0, y: 1 }, Point { x: 1, y: 1 }, Point { x: 1, y: 0 }, Point { x: 0, y: 0 }];
+        game.direction = Direction::Left;
+        game.food = None;
+        let turns = [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+        for dir in turns.iter().cycle().take(40) {
+            game.next_direction = *dir;
+            game.update_snake();
+            assert!(!game.game_over, "died heading {dir:?}");
+            assert_eq!(game.snake.len(), 4);
+        }
+    }
+
+    #[test]
+    fn the_tail_stays_put_on_a_step_that_grows() {
+        // A magnet lets the head eat food from a distance, so it can grow on the step
+        // it enters the tail's cell; the tail then doesn't move off it.
+        let mut game = game(10, 10, &["--magnet", "2"]);
+        game.snake = vec![Point { x: 0, y: 1 }, Point { x: 1, y: 1 }, Point { x: 1, y: 0 }, Point { x: 0, y: 0 }];
+        game.direction = Direction::Left;
+        game.next_direction = Direction::Up;
+        game.food = Some(Point { x: 2, y: 0 });
+        game.food_kind = FoodKind::Normal;
+        game.update_snake();
+        assert!(game.game_over);
+        assert_eq!(game.death_cause, Some(DeathCause::SelfCollision));
+    }
+
+    #[test]
+    fn passing_the_high_score_is_celebrated_once() {
+        let mut game = game(20, 20, &[]);
+        game.scores.high_score = 5;
+        game.score = 5;
+        game.check_high_score();
+        assert!(!game.high_score_beaten, "equalling it isn't enough");
+
+        game.score = 6;
+        game.check_high_score();
+        assert!(game.high_score_beaten);
+        assert_eq!(game.celebration_timer, CELEBRATION_TIME);
+
+        game.celebration_timer = 0.0;
+        game.score = 9;
+        game.check_high_score();
+        assert_eq!(game.celebration_timer, 0.0);
+    }
+
+    #[test]
+    fn there_is_no_celebration_without_a_high_score_or_when_turned_off() {
+        let mut unrecorded = game(20, 20, &[]);
+        unrecorded.score = 3;
+        unrecorded.check_high_score();
+        assert!(!unrecorded.high_score_beaten);
+
+        let mut quiet = game(20, 20, &["--no-celebration"]);
+        quiet.scores.high_score = 1;
+        quiet.score = 3;
+        quiet.check_high_score();
+        assert!(quiet.high_score_beaten);
+        assert_eq!(quiet.celebration_timer, 0.0);
+    }
+
+    #[test]
+    fn the_magnet_reaches_cells_within_its_radius() {
+        let head = Point { x: 5, y: 5 };
+        assert!(within_magnet(head, head, 0));
+        assert!(!within_magnet(head, Point { x: 6, y: 5 }, 0));
+        assert!(within_magnet(head, Point { x: 6, y: 5 }, 1));
+        assert!(within_magnet(head, Point { x: 5, y: 4 }, 1));
+        assert!(!within_magnet(head, Point { x: 6, y: 6 }, 1), "diagonals are two steps away");
+        assert!(within_magnet(head, Point { x: 6, y: 6 }, 2));
+    }
+
+    #[test]
+    fn a_radius_one_magnet_eats_food_beside_the_head() {
+        let mut magnet = game(20, 20, &["--seed", "1", "--magnet", "1"]);
+        bump_towards_food(&mut magnet);
+        assert_eq!(magnet.food_eaten, 1);
+        assert_eq!(magnet.snake.len(), 2);
+        assert_eq!(magnet.snake[0], Point { x: 6, y: 5 }, "the head stops short of the food's cell");
+
+        let mut exact = game(20, 20, &["--seed", "1"]);
+        bump_towards_food(&mut exact);
+        assert_eq!(exact.food_eaten, 0);
+        assert_eq!(exact.snake.len(), 1);
+    }
+
+    /// Steps a one-cell snake right from (5, 5) with the food just above its new head.
+    fn bump_towards_food(game: &mut SnakeGame) {
+        game.snake = vec![Point { x: 5, y: 5 }];
+        game.direction = Direction::Right;
+        game.next_direction = Direction::Right;
+        game.food = Some(Point { x: 6, y: 4 });
+        game.food_kind = FoodKind::Normal;
+        game.update_snake();
+    }
+
+    /// Returns what the cached board would show for `game`, row by row.
+    fn board(game: &SnakeGame) -> Vec<CellKind> {
+        let food = game.food.map(|food| (food, game.food_kind));
+        dirty::cell_kinds(
+            game.grid_width,
+            game.grid_height,
+            &game.obstacles,
+            &game.snake,
+            food,
+            game.extra_food,
+            game.bonus,
+        )
+    }
+
+    /// Returns the cells whose contents differ between two lists from `cell_contents`.
+    fn contents_changes(before: &[&str], after: &[&str], grid_width: i32) -> Vec<Point> {
+        // The board draws the head like the rest of the body.
+        let plain = |s: &str| if s == "snake head" { "snake" } else { s }.to_string();
+        (0..before.len())
+            .filter(|&i| plain(before[i]) != plain(after[i]))
+            .map(|i| Point { x: i as i32 % grid_width, y: i as i32 / grid_width })
+            .collect()
+    }
+
+    #[test]
+    fn the_changed_cells_are_exactly_what_a_step_changed() {
+        let mut game = game(12, 12, &["--seed", "5", "--start-length", "3", "--maze"]);
+        let contents = |game: &SnakeGame| -> Vec<&'static str> {
+            (0..game.grid_height)
+                .flat_map(|y| (0..game.grid_width).map(move |x| Point { x, y }))
+                .map(|p| game.cell_contents(p))
+                .collect()
+        };
+        let mut eaten = 0;
+        for _ in 0..60 {
+            let (before, before_contents) = (board(&game), contents(&game));
+            let tail = *game.snake.last().unwrap();
+            game.next_direction = game.choose_ai_direction().0;
+            game.update_snake();
+            if game.game_over {
+                break;
+            }
+            let changed = dirty::changed_cells(&before, &board(&game), game.grid_width);
+            assert_eq!(changed, contents_changes(&before_contents, &contents(&game), game.grid_width));
+            assert!(changed.contains(&game.snake[0]));
+            if game.food_eaten > eaten {
+                eaten = game.food_eaten;
+            } else {
+                // A plain step only changes the new head's cell and the one the tail left.
+                assert_eq!(changed.len(), 2);
+                assert!(changed.contains(&tail));
+            }
+        }
+        assert!(eaten > 0, "the run should have eaten something");
+    }
+
+    #[test]
+    fn the_extra_food_comes_out_on_its_interval_and_expires() {
+        let mut game = game(20, 20, &["--seed", "1", "--double-food"]);
+        game.extra_food_timer = 0.5;
+        game.tick_extra_food(0.4);
+        assert_eq!(game.extra_food, None);
+        game.tick_extra_food(0.2);
+        let cell = game.extra_food.expect("the extra food is out");
+        assert!(!game.snake.contains(&cell) && game.food != Some(cell));
+
+        game.tick_extra_food(EXTRA_FOOD_LIFETIME - 0.1);
+        assert_eq!(game.extra_food, Some(cell));
+        game.tick_extra_food(0.2);
+        assert_eq!(game.extra_food, None);
+        assert_eq!((game.score, game.food_eaten), (0, 0));
+    }
+
+    #[test]
+    fn eating_the_extra_food_scores_and_grows_like_food() {
+        let mut game = game(20, 20, &["--seed", "1", "--double-food", "--start-length", "3"]);
+        let cell = ai::step(game.snake[0], game.direction);
+        game.extra_food = Some(cell);
+        game.extra_food_time_left = EXTRA_FOOD_LIFETIME;
+        let food = game.food;
+        game.update_snake();
+        assert_eq!(game.extra_food, None);
+        assert_eq!(game.food, food, "the main food stays put");
+        assert_eq!((game.score, game.food_eaten), (1, 1));
+        assert_eq!(game.snake.len(), 4);
+    }
+
+    #[test]
+    fn any_key_restarts_only_when_turned_on() {
+        let mut game = game(20, 20, &[]);
+        game.settings.any_key_restarts = false;
+        assert!(game.restarts_game_over(KeyCode::R, false));
+        assert!(game.restarts_game_over(KeyCode::R, true));
+        assert!(!game.restarts_game_over(KeyCode::Space, false));
+
+        game.settings.any_key_restarts = true;
+        assert!(game.restarts_game_over(KeyCode::Space, false));
+        assert!(game.restarts_game_over(KeyCode::Up, false));
+        assert!(!game.restarts_game_over(KeyCode::Space, true), "a held key doesn't restart");
+        for key in [KeyCode::B, KeyCode::F11, KeyCode::F5] {
+            assert!(!game.restarts_game_over(key, false));
+        }
+    }
+
+    #[test]
+    fn the_live_title_shows_score_and_length() {
+        assert_eq!(live_title(0, 1), "Snake Game — Score 0, Len 1");
+        assert_eq!(live_title(42, 17), "Snake Game — Score 42, Len 17");
+    }
+
+    #[test]
+    fn the_title_goes_back_to_plain_on_game_over_and_in_the_menu() {
+        let mut game = running(20, 20, &["--seed", "1", "--start-length", "3"]);
+        assert_eq!(game.shown_title(), Some((0, 3)));
+        game.pause_menu = Some(PauseItem::Resume);
+        assert_eq!(game.shown_title(), None);
+        game.pause_menu = None;
+        game.title_stale = false;
+        take_steps(&mut game, 1);
+        assert!(game.title_stale, "a step may change the score or length");
+        game.end_game(DeathCause::Wall);
+        assert_eq!(game.shown_title(), None);
+    }
+
+    #[test]
+    fn a_waiting_run_starts_with_the_first_turn() {
+        let mut game = running(20, 20, &["--seed", "1", "--wait-for-input"]);
+        let head = game.snake[0];
+        for _ in 0..10 {
+            game.tick(game.move_period);
+        }
+        assert_eq!(game.snake[0], head);
+        assert_eq!(game.elapsed, 0.0);
+
+        game.steer(Direction::Down, false);
+        assert!(game.started);
+        take_steps(&mut game, 1);
+        assert_eq!(game.direction, Direction::Down);
+        assert_eq!(game.snake[0], ai::step(head, Direction::Down));
+    }
+
+    #[test]
+    fn cells_beside_walls_and_edges_are_adjacent_to_a_wall() {
+        let mut walled = game(10, 10, &[]);
+        walled.obstacles = vec![Point { x: 5, y: 5 }];
+        assert!(walled.adjacent_to_wall(Point { x: 0, y: 4 }));
+        assert!(walled.adjacent_to_wall(Point { x: 9, y: 9 }));
+        assert!(walled.adjacent_to_wall(Point { x: 5, y: 4 }));
+        assert!(walled.adjacent_to_wall(Point { x: 6, y: 5 }));
+        assert!(!walled.adjacent_to_wall(Point { x: 6, y: 6 }), "diagonal walls don't count");
+        assert!(!walled.adjacent_to_wall(Point { x: 2, y: 2 }));
+
+        // With wrapping edges, only the walls count.
+        let mut wrapped = game(10, 10, &["--wrap"]);
+        wrapped.obstacles = vec![Point { x: 5, y: 5 }];
+        assert!(!wrapped.adjacent_to_wall(Point { x: 0, y: 4 }));
+        assert!(wrapped.adjacent_to_wall(Point { x: 5, y: 6 }));
+    }
+
+    #[test]
+    fn style_points_come_from_steps_along_a_wall() {
+        let mut stylish = game(10, 10, &["--style-points"]);
+        bump(&mut stylish, Point { x: 3, y: 1 }, Direction::Up);
+        assert_eq!(stylish.style_points, STYLE_POINTS_PER_STEP);
+        bump(&mut stylish, Point { x: 3, y: 4 }, Direction::Right);
+        assert_eq!(stylish.style_points, STYLE_POINTS_PER_STEP);
+
+        let mut plain = game(10, 10, &[]);
+        bump(&mut plain, Point { x: 3, y: 1 }, Direction::Up);
+        assert_eq!(plain.style_points, 0);
+    }
+
+    #[test]
+    fn each_speed_curve_starts_from_the_base_period() {
+        for curve in [SpeedCurve::Linear, SpeedCurve::Exponential, SpeedCurve::Stepped] {
+            assert_eq!(curve_period(curve, 0.2, 0), 0.2);
+        }
+    }
+
+    #[test]
+    fn each_speed_curve_speeds_up_its_own_way() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+        assert!(close(curve_period(SpeedCurve::Linear, 0.2, 10), 0.2 - 10.0 * LINEAR_SPEEDUP));
+        assert!(close(curve_period(SpeedCurve::Exponential, 0.2, 2), 0.2 * EXPONENTIAL_SPEEDUP * EXPONENTIAL_SPEEDUP));
+        // The stepped curve holds between notches.
+        let notch = STEP_SPEEDUP_POINTS;
+        assert_eq!(curve_period(SpeedCurve::Stepped, 0.2, notch - 1), 0.2);
+        assert!(close(curve_period(SpeedCurve::Stepped, 0.2, notch), 0.2 - STEP_SPEEDUP));
+        assert!(close(curve_period(SpeedCurve::Stepped, 0.2, 2 * notch + 1), 0.2 - 2.0 * STEP_SPEEDUP));
+    }
+
+    #[test]
+    fn no_speed_curve_goes_below_the_fastest_period() {
+        for curve in [SpeedCurve::Linear, SpeedCurve::Exponential, SpeedCurve::Stepped] {
+            assert_eq!(curve_period(curve, 0.2, 10_000), MIN_MOVE_PERIOD);
+        }
+    }
+
+    #[test]
+    fn near_tail_spawning_lands_closer_to_the_tail() {
+        let snake: Vec<Point> = (0..6).rev().map(|x| Point { x: x + 2, y: 10 }).collect();
+        let tail = *snake.last().unwrap();
+        let from_tail = |p: Point| (p.x - tail.x).abs() + (p.y - tail.y).abs();
+        let uniform = mean_spawn(SpawnStrategy::Uniform, &snake, &[], from_tail);
+        let near = mean_spawn(SpawnStrategy::NearTail, &snake, &[], from_tail);
+        assert!(near + 4.0 < uniform, "near tail {near} vs uniform {uniform}");
+    }
+
+    #[test]
+    fn reduced_motion_holds_every_effect_still() {
+        let mut game = game(20, 20, &["--reduced-motion", "--start-length", "3"]);
+        game.anim_time = 1.3;
+        game.celebration_timer = CELEBRATION_TIME;
+        let layout = Layout::fit((400.0, 400.0), 20, 20);
+        for index in 0..3 {
+            let cell = layout.cell_rect(game.snake[index]);
+            assert_eq!(game.wiggle(cell, &layout, index), cell);
+        }
+        assert_eq!(game.transition_alpha(), 0.0);
+        assert_eq!(game.celebration_flash_alpha(), 0.0);
+        assert!(game.blink_visible(0.1));
+    }
+
+    #[test]
+    fn without_reduced_motion_the_effects_move() {
+        let mut game = game(20, 20, &["--start-length", "3"]);
+        game.anim_time = 1.3;
+        game.celebration_timer = CELEBRATION_TIME;
+        let layout = Layout::fit((400.0, 400.0), 20, 20);
+        let cell = layout.cell_rect(game.snake[0]);
+        assert!(game.wiggle(cell, &layout, 0) != cell);
+        assert_eq!(game.transition_alpha(), 1.0);
+        assert!(game.celebration_flash_alpha() > 0.0);
+    }
+
+    #[test]
+    fn the_reduced_motion_variable_is_not_saved() {
+        let path = temp_path("motion-env").join("config.toml");
+        Settings::default().save(&path).unwrap();
+        let mut game = game(20, 20, &[]);
+        game.load_settings(path.clone());
+        // As if SNAKE_REDUCED_MOTION had been set when the game started.
+        game.env_reduced_motion = true;
+        assert!(game.reduced_motion());
+        game.save_settings();
+        assert!(!Settings::load(&path).unwrap().reduced_motion);
+        game.save_current_settings();
+        let saved = Settings::load(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(!saved.reduced_motion);
+    }
+
+    #[test]
+    fn session_flags_are_saved_only_when_asked() {
+        let path = temp_path("session-settings").join("config.toml");
+        let mut game = game(20, 20, &["--checkerboard", "--rulers", "--shadow", "0.2"]);
+        game.load_settings(path.clone());
+        assert!(game.checkerboard() && game.show_rulers);
+        // Quitting saves the settings without what the flags turned on.
+        game.save_settings();
+        let on_quit = Settings::load(&path).unwrap();
+        assert!(!on_quit.checkerboard && !on_quit.rulers);
+        assert_eq!(on_quit.shadow_offset, None);
+        game.save_current_settings();
+        let saved = Settings::load(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(saved.checkerboard && saved.rulers);
+        assert_eq!(saved.shadow_offset, Some(0.2));
+        assert_eq!((saved.grid_width, saved.grid_height), (20, 20));
+    }
+
+    #[test]
+    fn a_malformed_settings_file_is_not_saved_over() {
+        let path = temp_path("kept-settings").join("config.toml");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "hud_visible = maybe\n").unwrap();
+        let mut game = game(20, 20, &[]);
+        game.load_settings(path.clone());
+        assert!(game.settings == Settings::default());
+        game.settings.rulers = true;
+        game.save_settings();
+        game.save_current_settings();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(text, "hud_visible = maybe\n");
+    }
+
+    #[test]
+    fn the_saved_grid_size_applies_unless_one_is_chosen() {
+        let mut game = game(20, 20, &[]);
+        game.settings.grid_width = 30;
+        game.settings.grid_height = 12;
+        assert_eq!(game.saved_grid_size(), Some((30, 12)));
+        game.set_grid_size((30, 12));
+        assert_eq!((game.grid_width, game.grid_height), (30, 12));
+        assert_eq!(game.saved_grid_size(), None);
+
+        let mut chosen = running(20, 20, &["--width", "20"]);
+        chosen.settings.grid_width = 30;
+        assert_eq!(chosen.saved_grid_size(), None);
+        let mut unplayable = running(20, 20, &[]);
+        unplayable.settings.grid_width = 1;
+        assert_eq!(unplayable.saved_grid_size(), None);
+    }
+
+    #[test]
+    fn safe_spawn_avoids_obstacles_and_leaves_a_move() {
+        let mut crowded = game(10, 10, &["--start-length", "3"]);
+        crowded.food = Some(Point { x: 5, y: 5 });
+        crowded.obstacles = (2..8).flat_map(|y| (3..8).map(move |x| Point { x, y })).collect();
+        let (head, dir) = crowded.safe_spawn().unwrap();
+        let body = initial_body(head, dir, 3, 10, 10);
+        assert!(body.iter().all(|p| !crowded.obstacles.contains(p) && crowded.food != Some(*p)));
+        let ahead = ai::step(head, dir);
+        assert!(crowded.in_bounds(ahead) && !crowded.obstacles.contains(&ahead) && !body.contains(&ahead));
+    }
+
+    #[test]
+    fn safe_spawn_gives_up_on_a_full_board() {
+        let mut full = game(6, 6, &["--start-length", "3"]);
+        full.obstacles = (0..6).flat_map(|y| (0..6).map(move |x| Point { x, y })).collect();
+        assert!(full.safe_spawn().is_none());
+    }
+
+    #[test]
+    fn console_grow_is_held_to_the_free_cells() {
+        let mut small = game(4, 4, &["--start-length", "3"]);
+        let free = 16 - 3 - small.obstacles.len();
+        assert!(small.run_command(Command::Grow(free + 1)).is_err());
+        assert_eq!(small.snake.len(), 3);
+        small.run_command(Command::Grow(free)).unwrap();
+        assert_eq!(small.snake.len(), 3 + free);
+    }
+
+    #[test]
+    fn no_grow_scores_without_growing() {
+        let mut fixed = running(20, 20, &["--seed", "2", "--no-grow", "--start-length", "4"]);
+        for eaten in 1..=3 {
+            let cell = food_ahead(&mut fixed, FoodKind::Normal);
+            fixed.update_snake();
+            assert_eq!(fixed.snake[0], cell);
+            assert_eq!(fixed.snake.len(), 4);
+            assert_eq!(fixed.score, eaten);
+        }
+    }
+
+    #[test]
+    fn frame_time_is_capped_after_a_stall() {
+        assert_eq!(clamp_frame_time(0.016), 0.016);
+        assert_eq!(clamp_frame_time(MAX_FRAME_TIME), MAX_FRAME_TIME);
+        assert_eq!(clamp_frame_time(30.0), MAX_FRAME_TIME);
+    }
+
+    #[test]
+    fn grid_presets_cycle_smallest_to_largest_and_back() {
+        assert_eq!(next_grid_preset((12, 12)), (20, 20));
+        assert_eq!(next_grid_preset((20, 20)), (30, 30));
+        assert_eq!(next_grid_preset((30, 30)), (12, 12));
+        assert_eq!(next_grid_preset((15, 10)), (20, 20));
+    }
+
+    #[test]
+    fn every_grid_preset_starts_a_playable_game() {
+        let mut switching = game(20, 20, &["--seed", "6"]);
+        for _ in GRID_PRESETS {
+            assert!(switching.switch_grid_preset());
+            let size = (switching.grid_width, switching.grid_height);
+            assert!(GRID_PRESETS.contains(&size));
+            assert!(!switching.game_over);
+            assert!(switching.snake.iter().all(|&p| switching.in_bounds(p)));
+            let food = switching.food.unwrap();
+            assert!(switching.in_bounds(food) && !switching.snake.contains(&food));
+            let ahead = ai::step(switching.snake[0], switching.direction);
+            assert!(switching.in_bounds(ahead) && !switching.snake.contains(&ahead));
+        }
+        assert_eq!((switching.grid_width, switching.grid_height), (20, 20));
+    }
+
+    #[test]
+    fn trail_fades_evenly_from_head_to_tail() {
+        assert_eq!(trail_alpha(0, 5), 1.0);
+        assert!((trail_alpha(4, 5) - TRAIL_MIN_ALPHA).abs() < 1e-6);
+        assert!((trail_alpha(2, 5) - (1.0 + TRAIL_MIN_ALPHA) / 2.0).abs() < 1e-6);
+        let alphas: Vec<f32> = (0..5).map(|index| trail_alpha(index, 5)).collect();
+        assert!(alphas.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn a_lone_head_is_opaque_and_overruns_clamp_to_the_tail() {
+        assert_eq!(trail_alpha(0, 1), 1.0);
+        assert_eq!(trail_alpha(0, 0), 1.0);
+        assert_eq!(trail_alpha(9, 5), trail_alpha(4, 5));
+    }
+
+    #[test]
+    fn respawned_food_never_lands_under_the_head() {
+        for seed in 0..200 {
+            let seed = seed.to_string();
+            let mut small = running(5, 5, &["--seed", &seed, "--wrap", "--start-length", "2"]);
+            // Three foods grow the snake to fill its row, wrapping round to its own tail.
+            for _ in 0..3 {
+                // Ahead may be over the edge, so put the food where the head wraps to.
+                let cell = wrap_point(food_ahead(&mut small, FoodKind::Normal), 5, 5);
+                small.food = Some(cell);
+                let score = small.score;
+                small.update_snake();
+                assert_eq!(small.snake[0], cell);
+                assert_eq!(small.score, score + 1, "food eaten twice in one step with seed {}", seed);
+                assert!(small.food != Some(small.snake[0]), "food respawned under the head with seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn length_decay_loses_a_point_per_step_but_never_drops_below_one() {
+        assert_eq!(food_points(3, true), DECAY_BASE_POINTS);
+        assert_eq!(food_points(DECAY_LENGTH_STEP, true), DECAY_BASE_POINTS - 1);
+        assert_eq!(food_points(3 * DECAY_LENGTH_STEP + 1, true), DECAY_BASE_POINTS - 3);
+        for length in [4 * DECAY_LENGTH_STEP, 5 * DECAY_LENGTH_STEP, 1000, usize::MAX] {
+            assert_eq!(food_points(length, true), 1);
+        }
+        assert_eq!(food_points(1000, false), 1);
+    }
+
+    #[test]
+    fn the_wall_toll_is_charged_once_per_crossing_and_stops_at_zero() {
+        let mut tolled = running(5, 5, &["--wrap", "--wall-toll", "2"]);
+        tolled.score = 3;
+        bump(&mut tolled, Point { x: 4, y: 2 }, Direction::Right);
+        assert_eq!(tolled.snake[0], Point { x: 0, y: 2 });
+        assert_eq!((tolled.score, tolled.wall_passes), (1, 1));
+        for _ in 0..4 {
+            tolled.update_snake();
+        }
+        assert_eq!(tolled.snake[0], Point { x: 4, y: 2 });
+        assert_eq!((tolled.score, tolled.wall_passes), (1, 1));
+        tolled.update_snake();
+        assert_eq!((tolled.score, tolled.wall_passes), (0, 2));
+        assert!(!tolled.game_over);
+    }
+
+    #[test]
+    fn the_daily_seed_is_stable_and_changes_with_the_date() {
+        // Everyone gets the same challenge, so the seed mustn't change between builds.
+        assert_eq!(daily_seed("2026-10-14"), 0xc42d_528f_9336_73d4);
+        assert!(daily_seed("2026-10-14") != daily_seed("2026-10-15"));
+        assert!(daily_seed("2026-10-14") != level::seed_from_str("2026-10-14"));
+    }
+
+    #[test]
+    fn speedup_progress_fills_towards_each_step_then_stays_full() {
+        assert_eq!(speedup_progress(0.2, 0), 0.0);
+        assert_eq!(speedup_progress(0.2, 3), 3.0 / STEP_SPEEDUP_POINTS as f32);
+        assert_eq!(speedup_progress(0.2, STEP_SPEEDUP_POINTS), 0.0);
+        // From 0.2 a step at a time, the period reaches the floor at a score of 45.
+        assert_eq!(speedup_progress(0.2, 44), 4.0 / STEP_SPEEDUP_POINTS as f32);
+        assert_eq!(speedup_progress(0.2, 45), 1.0);
+        assert_eq!(speedup_progress(0.2, 200), 1.0);
+        assert_eq!(speedup_progress(MIN_MOVE_PERIOD, 0), 1.0);
+    }
+
+    #[test]
+    fn the_tick_counts_moves_and_survives_a_save() {
+        let path = temp_path("tick.toml");
+        let mut original = running(20, 20, &["--seed", "5", "--start-length", "3"]);
+        assert_eq!(original.tick, 0);
+        for tick in 1..=4 {
+            original.update_snake();
+            assert_eq!(original.tick, tick);
+        }
+        original.save_state().write(&path).unwrap();
+        let mut resumed = game(20, 20, &[]);
+        resumed.apply_save(SaveState::load(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.tick, 4);
+        resumed.update_snake();
+        assert_eq!(resumed.tick, 5);
+    }
+
+    #[test]
+    fn wall_distances_count_the_free_cells_to_each_edge() {
+        let distances = wall_distances(Point { x: 2, y: 0 }, 10, 6);
+        let expected = [(Direction::Up, 0), (Direction::Down, 5), (Direction::Left, 2), (Direction::Right, 7)];
+        assert_eq!(distances, expected);
+    }
+
+    #[test]
+    fn the_edge_warning_fades_out_over_its_range() {
+        assert_eq!(edge_warning_intensity(0), 1.0);
+        assert_eq!(edge_warning_intensity(EDGE_WARNING_RANGE / 2), 0.5);
+        assert_eq!(edge_warning_intensity(EDGE_WARNING_RANGE), 0.0);
+        assert_eq!(edge_warning_intensity(EDGE_WARNING_RANGE + 5), 0.0);
+        assert_eq!(edge_warning_intensity(-1), 1.0);
+    }
+
+    #[test]
+    fn revealed_foods_are_where_the_food_respawns() {
+        let mut revealing = running(20, 20, &["--seed", "8", "--reveal", "3", "--start-length", "3"]);
+        assert_eq!(revealing.preview_count(), 0);
+        food_ahead(&mut revealing, FoodKind::Normal);
+        for eaten in 1..=3 {
+            let revealed = revealing.preview_foods(3);
+            revealing.update_snake();
+            assert_eq!(revealing.score, eaten);
+            assert_eq!(revealing.preview_count(), 3);
+            assert_eq!(revealing.food, Some(revealed[0]));
+            // Eat the new food where it is, with the head stepping onto it.
+            let food = revealing.food.unwrap();
+            revealing.snake[0] = ai::step(food, revealing.direction.opposite());
+        }
+    }
+
+    #[test]
+    fn the_reveal_clears_after_its_time() {
+        let mut revealing = running(20, 20, &["--seed", "8", "--reveal", "3", "--start-length", "3"]);
+        food_ahead(&mut revealing, FoodKind::Normal);
+        revealing.update_snake();
+        assert_eq!(revealing.reveal_timer, REVEAL_TIME);
+        revealing.tick(REVEAL_TIME / 2.0);
+        assert_eq!(revealing.preview_count(), 3);
+        revealing.tick(REVEAL_TIME / 2.0 + 0.01);
+        assert_eq!(revealing.reveal_timer, 0.0);
+        assert_eq!(revealing.preview_count(), 0);
+    }
+
+    #[test]
+    fn grids_need_three_cells_each_way() {
+        assert!(validate_dimensions(MIN_GRID_SIZE, MIN_GRID_SIZE).is_ok());
+        assert!(validate_dimensions(40, MIN_GRID_SIZE).is_ok());
+        assert_eq!(
+            validate_dimensions(2, 10),
+            Err("a 2x10 grid is too small: it needs at least 3 cells each way".to_string())
+        );
+        assert!(validate_dimensions(10, 0).is_err());
+        assert!(validate_dimensions(-4, 10).is_err());
+    }
+
+    #[test]
+    fn width_and_height_are_checked_and_exclusive_with_a_level() {
+        let sized = options(&["--width", "8", "--height", "6"]);
+        assert_eq!((sized.grid_width, sized.grid_height), (Some(8), Some(6)));
+        let parse = |args: &[&str]| Options::parse(args.iter().map(|s| s.to_string())).err();
+        assert!(parse(&["--width", "2"]).is_some());
+        assert!(parse(&["--height", "1", "--demo", "3"]).is_some());
+        assert!(parse(&["--width", "8", "--level", "maze.txt"]).is_some());
+    }
+
+    #[test]
+    fn a_far_respawn_draws_a_streak_from_the_old_food() {
+        for seed in 0..20 {
+            let seed = seed.to_string();
+            let mut streaking = game(20, 20, &["--seed", &seed, "--food-streak"]);
+            let old = Point { x: 0, y: 0 };
+            streaking.food = Some(old);
+            streaking.spawn_food();
+            let new = streaking.food.unwrap();
+            let far = new.x + new.y >= FOOD_STREAK_MIN_DISTANCE;
+            assert_eq!(streaking.food_streak, far.then_some((old, new)));
+        }
+    }
+
+    #[test]
+    fn the_food_streak_clears_once_it_fades() {
+        let mut streaking = game(20, 20, &["--food-streak"]);
+        streaking.food_streak = Some((Point { x: 0, y: 0 }, Point { x: 10, y: 10 }));
+        streaking.food_streak_timer = FOOD_STREAK_TIME;
+        streaking.tick_food_streak(FOOD_STREAK_TIME / 2.0);
+        assert!(streaking.food_streak.is_some());
+        streaking.tick_food_streak(FOOD_STREAK_TIME);
+        assert_eq!(streaking.food_streak, None);
+        assert_eq!(streaking.food_streak_timer, 0.0);
+    }
+
+    #[test]
+    fn reduced_motion_draws_no_food_streak() {
+        let mut still = game(20, 20, &["--seed", "3", "--food-streak", "--reduced-motion"]);
+        still.food = Some(Point { x: 0, y: 0 });
+        for _ in 0..10 {
+            still.spawn_food();
+            assert_eq!(still.food_streak, None);
+        }
+    }
+
+    #[test]
+    fn head_on_reaches_only_along_the_line_ahead() {
+        let head = Point { x: 5, y: 5 };
+        assert!(head_on(head, Direction::Right, head, 2));
+        assert!(head_on(head, Direction::Right, Point { x: 7, y: 5 }, 2));
+        assert!(!head_on(head, Direction::Right, Point { x: 8, y: 5 }, 2), "beyond the radius");
+        assert!(!head_on(head, Direction::Right, Point { x: 4, y: 5 }, 2), "behind the head");
+        assert!(!head_on(head, Direction::Right, Point { x: 6, y: 4 }, 2), "off to the side");
+        assert!(head_on(head, Direction::Up, Point { x: 5, y: 3 }, 2));
+        assert!(head_on(head, Direction::Down, Point { x: 5, y: 6 }, 1));
+        assert!(head_on(head, Direction::Left, Point { x: 4, y: 5 }, 1));
+    }
+
+    #[test]
+    fn a_head_on_magnet_eats_and_scores_once_per_step() {
+        let mut aimed = running(20, 20, &["--seed", "1", "--magnet", "2", "--head-on", "--start-length", "3"]);
+        let head = aimed.snake[0];
+        let beside = Point { x: head.x + 1, y: head.y - 1 };
+        aimed.direction = Direction::Right;
+        aimed.next_direction = Direction::Right;
+        aimed.food = Some(beside);
+        aimed.food_kind = FoodKind::Normal;
+        aimed.update_snake();
+        assert_eq!((aimed.score, aimed.food_eaten), (0, 0), "food beside the head is out of reach");
+
+        let ahead = Point { x: aimed.snake[0].x + 2, y: aimed.snake[0].y };
+        aimed.food = Some(ahead);
+        aimed.extra_food = Some(Point { x: ahead.x - 1, y: ahead.y });
+        aimed.update_snake();
+        assert_eq!((aimed.score, aimed.food_eaten), (1, 1));
+        assert_eq!(aimed.snake.len(), 4);
+        assert!(aimed.food != Some(ahead));
+        assert!(aimed.extra_food.is_some(), "the extra food waits for a later step");
+    }
 }
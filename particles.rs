@@ -0,0 +1,88 @@
+use rand::Rng;
+
+use crate::Point;
+
+/// Seconds a particle lives before it has shrunk away.
+pub const PARTICLE_LIFETIME: f32 = 1.0;
+/// Slowest and fastest a particle flies outward, in cells per second.
+const MIN_PARTICLE_SPEED: f32 = 2.0;
+const MAX_PARTICLE_SPEED: f32 = 6.0;
+
+/// One square of the snake bursting apart, positioned in cells so it doesn't depend
+/// on the window size.
+#[derive(Clone, Copy)]
+pub struct Particle {
+    // Center position and velocity, in cells and cells per second.
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    // Seconds since the particle was spawned.
+    pub age: f32,
+}
+
+impl Particle {
+    /// Returns how big the particle is drawn, as a fraction of a cell: full size when
+    /// spawned, shrinking to nothing over its lifetime.
+    pub fn size(&self) -> f32 {
+        (1.0 - self.age / PARTICLE_LIFETIME).max(0.0)
+    }
+}
+
+/// Spawns one particle from the center of each of `cells`, flying off in a random
+/// direction at a random speed.
+pub fn burst<R: Rng>(rng: &mut R, cells: &[Point]) -> Vec<Particle> {
+    cells
+        .iter()
+        .map(|p| {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let speed = rng.gen_range(MIN_PARTICLE_SPEED..MAX_PARTICLE_SPEED);
+            Particle {
+                x: p.x as f32 + 0.5,
+                y: p.y as f32 + 0.5,
+                vx: angle.cos() * speed,
+                vy: angle.sin() * speed,
+                age: 0.0,
+            }
+        })
+        .collect()
+}
+
+/// Moves every particle on by `dt` seconds, removing those that have lived out their lifetime.
+pub fn update(particles: &mut Vec<Particle>, dt: f32) {
+    for particle in particles.iter_mut() {
+        particle.x += particle.vx * dt;
+        particle.y += particle.vy * dt;
+        particle.age += dt;
+    }
+    particles.retain(|particle| particle.age < PARTICLE_LIFETIME);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn burst_spawns_one_particle_per_segment() {
+        let cells = [Point { x: 3, y: 4 }, Point { x: 2, y: 4 }, Point { x: 1, y: 4 }];
+        let particles = burst(&mut StdRng::seed_from_u64(1), &cells);
+        assert_eq!(particles.len(), cells.len());
+        for (particle, cell) in particles.iter().zip(&cells) {
+            assert_eq!((particle.x, particle.y), (cell.x as f32 + 0.5, cell.y as f32 + 0.5));
+            assert_eq!(particle.size(), 1.0);
+        }
+    }
+
+    #[test]
+    fn particles_shrink_then_expire() {
+        let cells = [Point { x: 0, y: 0 }, Point { x: 1, y: 0 }];
+        let mut particles = burst(&mut StdRng::seed_from_u64(2), &cells);
+        update(&mut particles, PARTICLE_LIFETIME / 2.0);
+        assert_eq!(particles.len(), 2);
+        assert!((particles[0].size() - 0.5).abs() < 1e-5);
+        update(&mut particles, PARTICLE_LIFETIME / 2.0);
+        assert!(particles.is_empty());
+    }
+}
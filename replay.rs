@@ -0,0 +1,133 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Point, validate_dimensions};
+
+/// The snake's movement over one run, compact enough to keep the best run on disk.
+///
+/// Rather than every tick's full body, this stores the starting body plus the head
+/// position and length after each step; any tick's body is the most recent cells the
+/// head has covered.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub score: u32,
+    pub grid_width: i32,
+    pub grid_height: i32,
+    // The game tick the recording starts at: 0 unless the run was resumed or the
+    // snake respawned partway through.
+    #[serde(default)]
+    pub start_tick: u64,
+    // The body before the first step, head first.
+    pub start: Vec<Point>,
+    // The head position and the snake's length after each step.
+    pub heads: Vec<Point>,
+    pub lengths: Vec<u32>,
+}
+
+impl Replay {
+    /// Starts recording a run whose snake begins as `start`.
+    pub fn new(start: &[Point], grid_width: i32, grid_height: i32) -> Replay {
+        Replay {
+            score: 0,
+            grid_width,
+            grid_height,
+            start_tick: 0,
+            start: start.to_vec(),
+            heads: Vec::new(),
+            lengths: Vec::new(),
+        }
+    }
+
+    /// Records one step: where the head went and how long the snake is now.
+    pub fn push(&mut self, head: Point, length: usize) {
+        self.heads.push(head);
+        self.lengths.push(length as u32);
+    }
+
+    /// Drops the steps after the first `steps`, for when play is wound back.
+    pub fn truncate(&mut self, steps: usize) {
+        self.heads.truncate(steps);
+        self.lengths.truncate(steps);
+    }
+
+    /// Returns how many steps were recorded.
+    pub fn steps(&self) -> usize {
+        self.heads.len()
+    }
+
+    /// Returns the body, head first, after `tick` steps (the starting body at tick 0).
+    ///
+    /// Ticks past the end of the recording wrap around to the start, so the replay loops.
+    pub fn body_at(&self, tick: usize) -> Vec<Point> {
+        let tick = tick % (self.steps() + 1);
+        let Some(length) = tick.checked_sub(1).map(|i| self.lengths[i] as usize) else {
+            return self.start.clone();
+        };
+        // The cells the head has covered, newest first, continuing into the starting body.
+        self.heads[..tick]
+            .iter()
+            .rev()
+            .chain(&self.start)
+            .take(length)
+            .copied()
+            .collect()
+    }
+
+    /// Loads a replay from `path`, rejecting files that don't parse or don't make sense.
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let replay: Replay = toml::from_str(&text).map_err(|e| e.to_string())?;
+        validate_dimensions(replay.grid_width, replay.grid_height)?;
+        if replay.start.is_empty() {
+            return Err("the replay's snake has no segments".to_string());
+        }
+        if replay.heads.len() != replay.lengths.len() {
+            return Err("the replay has a different number of heads and lengths".to_string());
+        }
+        Ok(replay)
+    }
+
+    /// Writes the replay to `path` through a temporary file, like a save.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::Value::try_from(self)
+            .and_then(|value| toml::to_string(&value))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, text)?;
+        fs::rename(&temp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bodies_follow_the_recorded_heads_and_lengths() {
+        let mut replay = Replay::new(&[Point { x: 2, y: 0 }, Point { x: 1, y: 0 }], 5, 5);
+        replay.push(Point { x: 3, y: 0 }, 2);
+        replay.push(Point { x: 3, y: 1 }, 3);
+        assert_eq!(replay.body_at(0), vec![Point { x: 2, y: 0 }, Point { x: 1, y: 0 }]);
+        assert_eq!(replay.body_at(1), vec![Point { x: 3, y: 0 }, Point { x: 2, y: 0 }]);
+        assert_eq!(replay.body_at(2), vec![Point { x: 3, y: 1 }, Point { x: 3, y: 0 }, Point { x: 2, y: 0 }]);
+        // Past the end, the replay loops back to the start.
+        assert_eq!(replay.body_at(3), replay.body_at(0));
+        assert_eq!(replay.body_at(5), replay.body_at(2));
+    }
+
+    #[test]
+    fn truncating_drops_the_later_steps() {
+        let mut replay = Replay::new(&[Point { x: 0, y: 0 }], 5, 5);
+        replay.push(Point { x: 1, y: 0 }, 1);
+        replay.push(Point { x: 2, y: 0 }, 1);
+        replay.truncate(1);
+        assert_eq!(replay.steps(), 1);
+        assert_eq!(replay.body_at(1), vec![Point { x: 1, y: 0 }]);
+    }
+}
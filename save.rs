@@ -0,0 +1,75 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai;
+use crate::{Direction, FoodKind, Point, validate_dimensions};
+
+/// Everything needed to pick a run back up where it left off, stored as TOML.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SaveState {
+    pub grid_width: i32,
+    pub grid_height: i32,
+    pub direction: Direction,
+    pub next_direction: Direction,
+    pub food_kind: FoodKind,
+    pub score: u32,
+    pub elapsed: f32,
+    pub food_eaten: u32,
+    pub move_period: f32,
+    pub rewinds_left: u32,
+    // Steps taken so far; saves from before it was kept start again from 0.
+    #[serde(default)]
+    pub tick: u64,
+    // The run's original seed, and a seed drawn from its RNG to continue food placement.
+    // TOML integers are signed, so these hold the u64 seeds' bits as i64.
+    pub seed: i64,
+    pub rng_seed: i64,
+    pub food: Option<Point>,
+    pub snake: Vec<Point>,
+    pub obstacles: Vec<Point>,
+}
+
+impl SaveState {
+    /// Loads a save from `path`, rejecting files that don't parse or don't make sense.
+    pub fn load(path: &Path) -> Result<SaveState, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let state: SaveState = toml::from_str(&text).map_err(|e| e.to_string())?;
+        state.validate()?;
+        Ok(state)
+    }
+
+    /// Writes the save to `path` through a temporary file, so a crash mid-write
+    /// never leaves a half-written save behind.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Going through a toml::Value puts plain values ahead of tables whatever the
+        // field order, which matters once a point list is empty.
+        let text = toml::Value::try_from(self)
+            .and_then(|value| toml::to_string(&value))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, text)?;
+        fs::rename(&temp, path)
+    }
+
+    /// Checks that the save describes a playable board.
+    fn validate(&self) -> Result<(), String> {
+        validate_dimensions(self.grid_width, self.grid_height)?;
+        if self.snake.is_empty() {
+            return Err("the snake has no segments".to_string());
+        }
+        if self.move_period.is_nan() || self.move_period <= 0.0 {
+            return Err(format!("bad move period {}", self.move_period));
+        }
+        let mut points = self.snake.iter().chain(&self.obstacles).chain(&self.food);
+        if let Some(p) = points.find(|&&p| !ai::in_bounds(p, self.grid_width, self.grid_height)) {
+            return Err(format!("({},{}) is outside the grid", p.x, p.y));
+        }
+        Ok(())
+    }
+}
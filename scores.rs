@@ -0,0 +1,114 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// How many of the latest runs the average score is taken over.
+pub const RECENT_RUNS: usize = 10;
+
+/// Score records kept between sessions, stored as TOML.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scores {
+    // The best score of any finished run (0 before the first).
+    pub high_score: u32,
+    // The scores of the latest runs, oldest first, at most RECENT_RUNS of them.
+    pub recent: Vec<u32>,
+}
+
+/// Adds `score` to the end of `recent`, dropping the oldest scores so at most
+/// `capacity` are kept.
+pub fn push_recent(recent: &mut Vec<u32>, score: u32, capacity: usize) {
+    recent.push(score);
+    let excess = recent.len().saturating_sub(capacity);
+    recent.drain(..excess);
+}
+
+/// Returns the mean of `scores`, or None if there are none yet.
+pub fn average(scores: &[u32]) -> Option<f32> {
+    if scores.is_empty() {
+        return None;
+    }
+    Some(scores.iter().map(|&score| score as f32).sum::<f32>() / scores.len() as f32)
+}
+
+impl Scores {
+    /// Records a finished run's score, returning true if it set a new high score.
+    pub fn record(&mut self, score: u32) -> bool {
+        push_recent(&mut self.recent, score, RECENT_RUNS);
+        if score <= self.high_score {
+            return false;
+        }
+        self.high_score = score;
+        true
+    }
+
+    /// Returns the average score of the latest runs, or None before the first.
+    pub fn recent_average(&self) -> Option<f32> {
+        average(&self.recent)
+    }
+
+    /// Loads the records from `path`, starting from none if it is missing or unreadable.
+    pub fn load(path: &Path) -> Scores {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the records to `path`, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_latest_scores_are_kept() {
+        let mut recent = Vec::new();
+        for score in 1..=5 {
+            push_recent(&mut recent, score, 3);
+        }
+        assert_eq!(recent, vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn the_average_covers_a_partly_filled_list() {
+        let mut scores = Scores::default();
+        assert_eq!(scores.recent_average(), None);
+        scores.record(4);
+        scores.record(7);
+        assert_eq!(scores.recent, vec![4, 7]);
+        assert_eq!(scores.recent_average(), Some(5.5));
+    }
+
+    #[test]
+    fn the_average_drops_runs_older_than_the_last_ten() {
+        let mut scores = Scores::default();
+        scores.record(1000);
+        for _ in 0..RECENT_RUNS {
+            scores.record(2);
+        }
+        assert_eq!(scores.recent.len(), RECENT_RUNS);
+        assert_eq!(scores.recent_average(), Some(2.0));
+        assert_eq!(scores.high_score, 1000);
+    }
+
+    #[test]
+    fn recording_reports_a_new_high_score() {
+        let mut scores = Scores::default();
+        assert!(scores.record(5));
+        assert!(!scores.record(5));
+        assert!(!scores.record(3));
+        assert!(scores.record(6));
+        assert_eq!(scores.high_score, 6);
+    }
+}
@@ -0,0 +1,89 @@
+use std::io::{self, BufRead};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::Direction;
+
+/// One line of scripted input from `--stdin-input`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScriptCommand {
+    Steer(Direction),
+    // Opens the pause menu, or closes it if it is open.
+    Pause,
+    // Starts a new game from the game-over screen.
+    Restart,
+}
+
+/// Parses one line of scripted input.
+///
+/// Directions are `u`, `d`, `l` and `r` or spelled out, `p` or `pause` pauses, and
+/// `restart` restarts (spelled out, since `r` is right). Case and surrounding space
+/// are ignored. Blank lines and `#` comments give None.
+pub fn parse_line(line: &str) -> Result<Option<ScriptCommand>, String> {
+    let word = line.trim().to_ascii_lowercase();
+    let command = match word.as_str() {
+        "" => return Ok(None),
+        _ if word.starts_with('#') => return Ok(None),
+        "u" | "up" => ScriptCommand::Steer(Direction::Up),
+        "d" | "down" => ScriptCommand::Steer(Direction::Down),
+        "l" | "left" => ScriptCommand::Steer(Direction::Left),
+        "r" | "right" => ScriptCommand::Steer(Direction::Right),
+        "p" | "pause" => ScriptCommand::Pause,
+        "restart" => ScriptCommand::Restart,
+        _ => return Err(format!("unknown command: {}", line.trim())),
+    };
+    Ok(Some(command))
+}
+
+/// Starts reading commands from stdin, one per line, on a thread of its own, so the
+/// game can pick them up each frame without waiting for input.
+///
+/// Lines that don't parse are reported and skipped. The thread stops at the end of
+/// stdin, or once the game drops the receiver.
+pub fn read_stdin() -> mpsc::Receiver<ScriptCommand> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            match parse_line(&line) {
+                Ok(Some(command)) => {
+                    if tx.send(command).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("ignoring scripted input: {}", e),
+            }
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_short_and_spelled_out_commands() {
+        assert_eq!(parse_line("u"), Ok(Some(ScriptCommand::Steer(Direction::Up))));
+        assert_eq!(parse_line("  Down "), Ok(Some(ScriptCommand::Steer(Direction::Down))));
+        assert_eq!(parse_line("L"), Ok(Some(ScriptCommand::Steer(Direction::Left))));
+        assert_eq!(parse_line("r"), Ok(Some(ScriptCommand::Steer(Direction::Right))));
+        assert_eq!(parse_line("pause"), Ok(Some(ScriptCommand::Pause)));
+        assert_eq!(parse_line("RESTART"), Ok(Some(ScriptCommand::Restart)));
+    }
+
+    #[test]
+    fn blank_lines_and_comments_are_skipped() {
+        assert_eq!(parse_line(""), Ok(None));
+        assert_eq!(parse_line("   "), Ok(None));
+        assert_eq!(parse_line("# turn at the wall"), Ok(None));
+    }
+
+    #[test]
+    fn unknown_lines_are_an_error() {
+        assert_eq!(parse_line(" jump "), Err("unknown command: jump".to_string()));
+    }
+}
@@ -0,0 +1,182 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::DEFAULT_GRID_SIZE;
+use crate::hud::HudPosition;
+
+/// Environment variable that turns reduced motion on for a session when set (to
+/// anything but an empty string or `0`).
+const REDUCED_MOTION_VAR: &str = "SNAKE_REDUCED_MOTION";
+
+/// Player preferences that persist between sessions, stored as TOML.
+///
+/// Missing keys take their default values, so older files keep loading. Toggling a
+/// setting in the game changes what is saved on quit; the matching command line
+/// flags only last for the session, unless the player saves them explicitly.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Background music and sound effect volumes, each in [0, 1].
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    // Per-channel mutes, kept separate so unmuting restores the old volume.
+    pub music_muted: bool,
+    pub sfx_muted: bool,
+    // Let any key, not just R, restart from the game-over screen.
+    pub any_key_restarts: bool,
+    // Turn off purely cosmetic animation (see SnakeGame::reduced_motion).
+    pub reduced_motion: bool,
+    // The display toggles as the player last left them: where the HUD sits and whether
+    // it shows, the head readout, the rulers, and mirror mode.
+    pub hud_position: HudPosition,
+    pub hud_visible: bool,
+    pub head_info: bool,
+    pub rulers: bool,
+    pub invert_controls: bool,
+    // The theme: a checkerboard background, and drop shadows as for --shadow and
+    // --shadow-color (no offset draws none, no color uses the default).
+    pub checkerboard: bool,
+    pub shadow_offset: Option<f32>,
+    pub shadow_color: Option<[u8; 4]>,
+    // The grid size in cells, as last picked with F5. --width, --height and levels
+    // override it.
+    pub grid_width: i32,
+    pub grid_height: i32,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            music_volume: 0.5,
+            sfx_volume: 1.0,
+            music_muted: false,
+            sfx_muted: false,
+            any_key_restarts: false,
+            reduced_motion: false,
+            hud_position: HudPosition::TopLeft,
+            hud_visible: true,
+            head_info: false,
+            rulers: false,
+            invert_controls: false,
+            checkerboard: false,
+            shadow_offset: None,
+            shadow_color: None,
+            grid_width: DEFAULT_GRID_SIZE,
+            grid_height: DEFAULT_GRID_SIZE,
+        }
+    }
+}
+
+/// Returns true if the SNAKE_REDUCED_MOTION environment variable asks for reduced
+/// motion. It is never written to the settings, so it lasts only while it is set.
+pub fn reduced_motion_from_env() -> bool {
+    env_flag(std::env::var(REDUCED_MOTION_VAR).ok().as_deref())
+}
+
+/// Reads an on/off environment variable's value: unset, empty and `0` mean off.
+fn env_flag(value: Option<&str>) -> bool {
+    value.is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+impl Settings {
+    /// Loads the settings from `path`, or the defaults if there is no file yet.
+    ///
+    /// A file that can't be read or doesn't parse is an error rather than the
+    /// defaults, so that the caller can leave it alone instead of saving over it.
+    pub fn load(path: &Path) -> Result<Settings, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(e) => return Err(e.to_string()),
+        };
+        toml::from_str(&text).map_err(|e| e.to_string())
+    }
+
+    /// Writes the settings to `path`, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::temp_path;
+
+    #[test]
+    fn saved_settings_load_back() {
+        let path = temp_path("settings").join("config.toml");
+        let settings = Settings {
+            music_volume: 0.25,
+            sfx_volume: 0.75,
+            music_muted: true,
+            sfx_muted: true,
+            any_key_restarts: true,
+            reduced_motion: true,
+            hud_position: HudPosition::Bottom,
+            hud_visible: false,
+            head_info: true,
+            rulers: true,
+            invert_controls: true,
+            checkerboard: true,
+            shadow_offset: Some(0.25),
+            shadow_color: Some([10, 20, 30, 40]),
+            grid_width: 30,
+            grid_height: 12,
+        };
+        settings.save(&path).unwrap();
+        let loaded = Settings::load(&path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(loaded, Ok(settings));
+    }
+
+    #[test]
+    fn an_older_file_gets_defaults_for_newer_keys() {
+        // A file saved before the theme and grid size were kept.
+        let path = temp_path("old-settings").join("config.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "music_volume = 0.1\nsfx_muted = true\nhud_position = \"Bottom\"\nrulers = true\n").unwrap();
+        let loaded = Settings::load(&path).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        let expected = Settings {
+            music_volume: 0.1,
+            sfx_muted: true,
+            hud_position: HudPosition::Bottom,
+            rulers: true,
+            ..Settings::default()
+        };
+        assert_eq!(loaded, expected);
+    }
+
+    #[test]
+    fn a_missing_file_loads_the_defaults() {
+        let loaded = Settings::load(Path::new("/nonexistent/snake/config.toml"));
+        assert_eq!(loaded, Ok(Settings::default()));
+    }
+
+    #[test]
+    fn a_malformed_file_is_an_error() {
+        let path = temp_path("bad-settings").join("config.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "music_volume = \"loud\"\n").unwrap();
+        let loaded = Settings::load(&path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(loaded.is_err());
+    }
+
+    #[test]
+    fn reduced_motion_variable_reads_empty_and_zero_as_off() {
+        assert!(!env_flag(None));
+        assert!(!env_flag(Some("")));
+        assert!(!env_flag(Some("0")));
+        assert!(env_flag(Some("1")));
+        assert!(env_flag(Some("yes")));
+    }
+}
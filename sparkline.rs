@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+
+use ggez::graphics::Rect;
+use ggez::mint::Point2;
+
+/// A bounded record of recent samples, drawn as a tiny line chart.
+///
+/// Once full, each new sample pushes out the oldest one.
+pub struct Sparkline {
+    samples: VecDeque<u32>,
+    capacity: usize,
+}
+
+impl Sparkline {
+    /// Creates an empty sparkline that keeps at most `capacity` samples.
+    pub fn new(capacity: usize) -> Sparkline {
+        Sparkline {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records a sample, dropping the oldest one if the buffer is full.
+    pub fn push(&mut self, value: u32) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    /// Returns the retained samples, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = u32> + '_ {
+        self.samples.iter().copied()
+    }
+
+    /// Maps the samples onto `area`: oldest at the left edge, the largest value at the top.
+    ///
+    /// The x spacing assumes a full buffer, so the line grows in from the left until
+    /// it spans the whole area.
+    pub fn points(&self, area: Rect) -> Vec<Point2<f32>> {
+        let max = self.samples().max().unwrap_or(0).max(1) as f32;
+        let step = area.w / self.capacity.saturating_sub(1).max(1) as f32;
+        self.samples()
+            .enumerate()
+            .map(|(i, value)| Point2 {
+                x: area.x + i as f32 * step,
+                y: area.bottom() - value as f32 / max * area.h,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_last_samples_are_kept() {
+        let mut sparkline = Sparkline::new(3);
+        for value in 1..=5 {
+            sparkline.push(value);
+        }
+        assert_eq!(sparkline.samples().collect::<Vec<_>>(), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn a_partly_filled_buffer_keeps_everything() {
+        let mut sparkline = Sparkline::new(4);
+        sparkline.push(7);
+        sparkline.push(8);
+        assert_eq!(sparkline.samples().collect::<Vec<_>>(), vec![7, 8]);
+    }
+
+    #[test]
+    fn a_zero_capacity_sparkline_stays_empty() {
+        let mut sparkline = Sparkline::new(0);
+        sparkline.push(1);
+        assert_eq!(sparkline.samples().count(), 0);
+    }
+
+    #[test]
+    fn points_span_the_area_with_the_peak_at_the_top() {
+        let mut sparkline = Sparkline::new(3);
+        for value in [0, 2, 4] {
+            sparkline.push(value);
+        }
+        let points = sparkline.points(Rect::new(10.0, 20.0, 100.0, 40.0));
+        let points: Vec<_> = points.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(points, vec![(10.0, 60.0), (60.0, 40.0), (110.0, 20.0)]);
+    }
+}
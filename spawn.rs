@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::Point;
+
+/// Guesses random cells until one isn't in `occupied`, giving up after `tries` guesses.
+///
+/// Each guess costs one lookup, so this is quick until the board is nearly full.
+pub fn by_rejection<R: Rng>(
+    rng: &mut R,
+    occupied: &HashSet<Point>,
+    grid_width: i32,
+    grid_height: i32,
+    tries: usize,
+) -> Option<Point> {
+    (0..tries)
+        .map(|_| Point {
+            x: rng.gen_range(0..grid_width),
+            y: rng.gen_range(0..grid_height),
+        })
+        .find(|p| !occupied.contains(p))
+}
+
+/// Lists every cell not in `occupied` and picks one of them.
+///
+/// Returns None when every cell is taken.
+pub fn by_collecting<R: Rng>(
+    rng: &mut R,
+    occupied: &HashSet<Point>,
+    grid_width: i32,
+    grid_height: i32,
+) -> Option<Point> {
+    let free: Vec<Point> = cells(grid_width, grid_height).filter(|p| !occupied.contains(p)).collect();
+    free.choose(rng).copied()
+}
+
+/// Walks the board once, keeping each cell not in `occupied` with a chance of one
+/// over the free cells seen so far, so no list is built.
+///
+/// Returns None when every cell is taken. The game doesn't use this; it's kept for
+/// `cargo bench --bench spawn` to compare against.
+#[allow(dead_code)]
+pub fn by_reservoir<R: Rng>(
+    rng: &mut R,
+    occupied: &HashSet<Point>,
+    grid_width: i32,
+    grid_height: i32,
+) -> Option<Point> {
+    let mut chosen = None;
+    for (seen, p) in cells(grid_width, grid_height).filter(|p| !occupied.contains(p)).enumerate() {
+        if rng.gen_range(0..=seen) == 0 {
+            chosen = Some(p);
+        }
+    }
+    chosen
+}
+
+/// Every cell of the board, row by row.
+fn cells(grid_width: i32, grid_height: i32) -> impl Iterator<Item = Point> {
+    (0..grid_height).flat_map(move |y| (0..grid_width).map(move |x| Point { x, y }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    const SIZE: i32 = 10;
+
+    /// Takes every cell but those in `free`.
+    fn all_but(free: &[Point]) -> HashSet<Point> {
+        cells(SIZE, SIZE).filter(|p| !free.contains(p)).collect()
+    }
+
+    #[test]
+    fn every_strategy_picks_an_unoccupied_cell() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let half: HashSet<Point> = cells(SIZE, SIZE).filter(|p| (p.x + p.y) % 2 == 0).collect();
+        for _ in 0..50 {
+            for cell in [
+                by_rejection(&mut rng, &half, SIZE, SIZE, usize::MAX),
+                by_collecting(&mut rng, &half, SIZE, SIZE),
+                by_reservoir(&mut rng, &half, SIZE, SIZE),
+            ] {
+                let cell = cell.unwrap();
+                assert!(!half.contains(&cell));
+                assert!((0..SIZE).contains(&cell.x) && (0..SIZE).contains(&cell.y));
+            }
+        }
+    }
+
+    #[test]
+    fn every_strategy_finds_the_last_free_cell() {
+        let last = Point { x: 7, y: 2 };
+        let occupied = all_but(&[last]);
+        let mut rng = StdRng::seed_from_u64(4);
+        assert_eq!(by_rejection(&mut rng, &occupied, SIZE, SIZE, usize::MAX), Some(last));
+        assert_eq!(by_collecting(&mut rng, &occupied, SIZE, SIZE), Some(last));
+        assert_eq!(by_reservoir(&mut rng, &occupied, SIZE, SIZE), Some(last));
+    }
+
+    #[test]
+    fn a_full_board_has_no_free_cell() {
+        let full = all_but(&[]);
+        let mut rng = StdRng::seed_from_u64(5);
+        assert_eq!(by_rejection(&mut rng, &full, SIZE, SIZE, 100), None);
+        assert_eq!(by_collecting(&mut rng, &full, SIZE, SIZE), None);
+        assert_eq!(by_reservoir(&mut rng, &full, SIZE, SIZE), None);
+    }
+}
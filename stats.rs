@@ -0,0 +1,96 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Column names written as the first line of a new stats file.
+pub const CSV_HEADER: &str = "timestamp,score,length,elapsed_secs,food_eaten,death_cause,seed";
+
+/// The numbers recorded for one finished run.
+pub struct RunStats {
+    // Seconds since the Unix epoch when the run ended.
+    pub timestamp: u64,
+    pub score: u32,
+    pub length: usize,
+    pub elapsed_secs: f32,
+    pub food_eaten: u32,
+    pub death_cause: &'static str,
+    pub seed: u64,
+}
+
+/// Formats one run as a CSV row (without a trailing newline).
+pub fn csv_row(stats: &RunStats) -> String {
+    format!(
+        "{},{},{},{:.2},{},{},{}",
+        stats.timestamp,
+        stats.score,
+        stats.length,
+        stats.elapsed_secs,
+        stats.food_eaten,
+        stats.death_cause,
+        stats.seed
+    )
+}
+
+/// Formats one run as a single human-readable line, for printing when the run is over.
+pub fn summary_line(stats: &RunStats) -> String {
+    format!(
+        "score {}, length {}, time {:.1}s, seed {}, ended by {}",
+        stats.score, stats.length, stats.elapsed_secs, stats.seed, stats.death_cause
+    )
+}
+
+/// Returns the text to append for one run, starting with the header if the file is empty.
+pub fn csv_append_text(stats: &RunStats, file_is_empty: bool) -> String {
+    if file_is_empty {
+        format!("{}\n{}\n", CSV_HEADER, csv_row(stats))
+    } else {
+        format!("{}\n", csv_row(stats))
+    }
+}
+
+/// Appends one run to the CSV file at `path`, creating it (with a header) if needed.
+pub fn append_run(path: &Path, stats: &RunStats) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let file_is_empty = file.metadata()?.len() == 0;
+    file.write_all(csv_append_text(stats, file_is_empty).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run() -> RunStats {
+        RunStats {
+            timestamp: 1_700_000_000,
+            score: 12,
+            length: 15,
+            elapsed_secs: 61.257,
+            food_eaten: 13,
+            death_cause: "wall",
+            seed: 42,
+        }
+    }
+
+    #[test]
+    fn csv_row_matches_the_header_columns() {
+        let row = csv_row(&run());
+        assert_eq!(row, "1700000000,12,15,61.26,13,wall,42");
+        assert_eq!(row.split(',').count(), CSV_HEADER.split(',').count());
+    }
+
+    #[test]
+    fn an_empty_file_gets_the_header_first() {
+        let text = csv_append_text(&run(), true);
+        assert_eq!(text, format!("{}\n1700000000,12,15,61.26,13,wall,42\n", CSV_HEADER));
+    }
+
+    #[test]
+    fn a_file_with_rows_only_gets_the_new_row() {
+        assert_eq!(csv_append_text(&run(), false), "1700000000,12,15,61.26,13,wall,42\n");
+    }
+
+    #[test]
+    fn summary_line_reads_out_the_run() {
+        assert_eq!(summary_line(&run()), "score 12, length 15, time 61.3s, seed 42, ended by wall");
+    }
+}
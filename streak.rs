@@ -0,0 +1,139 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Seconds in a day, for turning timestamps into day numbers.
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// How many days in a row the player has finished a run above the streak threshold,
+/// stored as TOML between sessions.
+///
+/// Days are counted in UTC from the Unix epoch, so a day rolls over at midnight UTC
+/// whatever the local time zone.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Streak {
+    pub count: u32,
+    // The day of the last run that kept the streak going (None if there is no streak).
+    pub last_day: Option<u64>,
+}
+
+/// Returns the day number of a Unix timestamp.
+pub fn day_of(timestamp: u64) -> u64 {
+    timestamp / SECS_PER_DAY
+}
+
+/// Returns the calendar date of a day number as `YYYY-MM-DD` (in UTC, like the days).
+pub fn date_string(day: u64) -> String {
+    // Converts days since 1970-01-01 to a proleptic Gregorian date, working in
+    // 400-year eras of 146097 days, with years starting in March so leap days
+    // come last.
+    let z = day + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let date = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, date)
+}
+
+/// Returns the streak and its last day after a run scoring `score` on day `today`.
+///
+/// A run at or above `threshold` extends a streak last kept yesterday, keeps one
+/// already kept today as it is, and otherwise (after a gap, or with no streak) starts
+/// a new streak of one. A run below the threshold ends the streak.
+pub fn update(count: u32, last_day: Option<u64>, today: u64, score: u32, threshold: u32) -> (u32, Option<u64>) {
+    if score < threshold {
+        return (0, None);
+    }
+    let count = match last_day {
+        Some(day) if day == today => count.max(1),
+        Some(day) if day + 1 == today => count + 1,
+        _ => 1,
+    };
+    (count, Some(today))
+}
+
+impl Streak {
+    /// Returns the streak as it stands on day `today`: a streak last kept before
+    /// yesterday has lapsed.
+    pub fn current(&self, today: u64) -> u32 {
+        match self.last_day {
+            Some(day) if day + 1 >= today => self.count,
+            _ => 0,
+        }
+    }
+
+    /// Records a finished run scoring `score` on day `today`.
+    pub fn record(&mut self, today: u64, score: u32, threshold: u32) {
+        (self.count, self.last_day) = update(self.count, self.last_day, today, score, threshold);
+    }
+
+    /// Loads the streak from `path`, starting from none if it is missing or unreadable.
+    pub fn load(path: &Path) -> Streak {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the streak to `path`, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_run_the_next_day_extends_the_streak() {
+        assert_eq!(update(3, Some(10), 11, 50, 20), (4, Some(11)));
+    }
+
+    #[test]
+    fn a_second_run_the_same_day_keeps_the_streak() {
+        assert_eq!(update(3, Some(10), 10, 50, 20), (3, Some(10)));
+        assert_eq!(update(0, Some(10), 10, 50, 20), (1, Some(10)));
+    }
+
+    #[test]
+    fn a_gap_starts_a_new_streak() {
+        assert_eq!(update(3, Some(10), 12, 50, 20), (1, Some(12)));
+        assert_eq!(update(0, None, 12, 20, 20), (1, Some(12)));
+    }
+
+    #[test]
+    fn a_run_below_the_threshold_ends_the_streak() {
+        assert_eq!(update(3, Some(10), 11, 19, 20), (0, None));
+    }
+
+    #[test]
+    fn a_streak_lapses_after_a_missed_day() {
+        let streak = Streak { count: 3, last_day: Some(10) };
+        assert_eq!(streak.current(10), 3);
+        assert_eq!(streak.current(11), 3);
+        assert_eq!(streak.current(12), 0);
+        assert_eq!(Streak::default().current(12), 0);
+    }
+
+    #[test]
+    fn days_turn_into_utc_dates() {
+        assert_eq!(day_of(SECS_PER_DAY - 1), 0);
+        assert_eq!(day_of(SECS_PER_DAY), 1);
+        assert_eq!(date_string(0), "1970-01-01");
+        assert_eq!(date_string(day_of(951_782_400)), "2000-02-29");
+        assert_eq!(date_string(day_of(1_709_251_200)), "2024-03-01");
+        assert_eq!(date_string(day_of(1_735_603_200)), "2024-12-31");
+    }
+}
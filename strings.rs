@@ -0,0 +1,117 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Every piece of text the game shows, so it can be translated or reworded.
+///
+/// Loaded from a TOML locale file; keys the file leaves out keep their English
+/// defaults. A `{name}` in a template is replaced by the value it names when shown.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct Strings {
+    // HUD lines, given {date}, {score}, {points} (style or per food), {passes} and {lives}.
+    pub daily: String,
+    pub score: String,
+    pub style: String,
+    pub food_points: String,
+    pub wall_passes: String,
+    pub lives: String,
+    pub new_high_score: String,
+    pub paused: String,
+    pub press_to_start: String,
+    pub resume_prompt: String,
+    pub resume_choices: String,
+    // The game-over screen.
+    pub time_up: String,
+    pub board_full: String,
+    pub game_over: String,
+    pub game_over_any_key: String,
+    // The terminal frontend's game-over line, shown after the score.
+    pub game_over_terminal: String,
+    // The --hover readout, given {x}, {y} and {contents}.
+    pub hover_cell: String,
+    // Given {days} and {threshold}.
+    pub streak: String,
+    // Given {seed}.
+    pub maze_seed: String,
+    // Given {high}, {runs} and {average}.
+    pub recent_scores: String,
+    // Given {left}.
+    pub rewind: String,
+}
+
+impl Default for Strings {
+    fn default() -> Strings {
+        let s = String::from;
+        Strings {
+            daily: s("Daily Challenge — {date}"),
+            score: s("Score: {score}"),
+            style: s("Style: {points}"),
+            food_points: s("Food: {points} pts"),
+            wall_passes: s("Wall passes: {passes}"),
+            lives: s("Lives: {lives}"),
+            new_high_score: s("New high score!"),
+            paused: s("Paused"),
+            press_to_start: s("Press a direction to start"),
+            resume_prompt: s("Resume your last run?"),
+            resume_choices: s("Y: resume   N: start over"),
+            time_up: s("Time's up!"),
+            board_full: s("You win! The board is full."),
+            game_over: s("Game Over! Press R to Restart"),
+            game_over_any_key: s("Game Over! Press any key to restart"),
+            game_over_terminal: s("Game over! r: restart  q: quit"),
+            hover_cell: s("Cell ({x}, {y}): {contents}"),
+            streak: s("Streak: {days} day(s) scoring {threshold}+"),
+            maze_seed: s("Maze seed: {seed}"),
+            recent_scores: s("High score: {high}   Last {runs} runs: {average} average"),
+            rewind: s("Press B to rewind ({left} left)"),
+        }
+    }
+}
+
+impl Strings {
+    /// Loads the strings from the locale file at `path`.
+    ///
+    /// A locale file is asked for by name, so one that is missing or doesn't parse
+    /// is an error rather than quietly falling back.
+    pub fn load(path: &Path) -> Result<Strings, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        toml::from_str(&text).map_err(|e| e.to_string())
+    }
+}
+
+/// Fills in a template, replacing each `{name}` with its value from `values`.
+///
+/// Placeholders with no value are left as they are, so a typo shows up on screen.
+pub fn fill(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_partial_locale_overrides_only_its_keys() {
+        let strings: Strings = toml::from_str("paused = \"En pause\"\nscore = \"Points : {score}\"\n").unwrap();
+        let english = Strings::default();
+        assert_eq!(strings.paused, "En pause");
+        assert_eq!(strings.score, "Points : {score}");
+        assert_eq!(strings.lives, english.lives);
+        assert_eq!(strings.game_over, english.game_over);
+    }
+
+    #[test]
+    fn fill_replaces_known_placeholders_and_keeps_the_rest() {
+        let text = fill("Cell ({x}, {y}): {contents}", &[("x", 3.to_string()), ("y", 4.to_string())]);
+        assert_eq!(text, "Cell (3, 4): {contents}");
+    }
+
+    #[test]
+    fn a_missing_locale_file_is_an_error() {
+        assert!(Strings::load(Path::new("/nonexistent/locale.toml")).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use std::io::{self, Read, Write};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::style::Print;
+use crossterm::{cursor, execute, terminal};
+
+use crate::strings;
+use crate::{Direction, FoodKind, Point, SnakeGame};
+
+/// Time between frames, for about 30 frames per second.
+const FRAME_TIME: Duration = Duration::from_millis(33);
+
+/// Byte that starts the escape sequences the terminal sends for arrow keys.
+const ESC: u8 = 0x1b;
+/// Byte the terminal sends for Ctrl-C while in raw mode.
+const CTRL_C: u8 = 0x03;
+
+/// A key press read from the terminal.
+#[derive(Clone, Copy, PartialEq)]
+enum TermKey {
+    Arrow(Direction),
+    Char(u8),
+}
+
+/// Renders the board as text: one line per grid row, then a status line.
+///
+/// `#` is a wall, `@` the head, `o` the body, `*` food, `x` poison, `+` the extra food
+/// of a double food event and `$` the bonus.
+pub fn render(game: &SnakeGame) -> String {
+    let mut out = String::new();
+    for y in 0..game.grid_height {
+        for x in 0..game.grid_width {
+            let p = Point { x, y };
+            let c = if game.snake.first() == Some(&p) {
+                '@'
+            } else if game.snake.contains(&p) {
+                'o'
+            } else if game.obstacles.contains(&p) {
+                '#'
+            } else if game.food == Some(p) {
+                match game.food_kind {
+                    FoodKind::Normal => '*',
+                    FoodKind::Poison => 'x',
+                }
+            } else if game.extra_food == Some(p) {
+                '+'
+            } else if game.bonus == Some(p) {
+                '$'
+            } else {
+                '.'
+            };
+            out.push(c);
+        }
+        out.push('\n');
+    }
+    if !game.options.zen {
+        out.push_str(&strings::fill(&game.strings.score, &[("score", game.score.to_string())]));
+    }
+    if game.game_over {
+        out.push_str("  ");
+        out.push_str(&game.strings.game_over_terminal);
+    }
+    out.push('\n');
+    out
+}
+
+/// Splits raw terminal input into key presses, decoding the arrow key escape sequences.
+fn parse_keys(bytes: &[u8]) -> Vec<TermKey> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let arrow = match bytes[i..] {
+            [ESC, b'[', b'A', ..] => Some(Direction::Up),
+            [ESC, b'[', b'B', ..] => Some(Direction::Down),
+            [ESC, b'[', b'C', ..] => Some(Direction::Right),
+            [ESC, b'[', b'D', ..] => Some(Direction::Left),
+            _ => None,
+        };
+        match arrow {
+            Some(dir) => {
+                keys.push(TermKey::Arrow(dir));
+                i += 3;
+            }
+            None => {
+                keys.push(TermKey::Char(bytes[i]));
+                i += 1;
+            }
+        }
+    }
+    keys
+}
+
+/// Keeps the terminal in raw mode, where keys arrive unbuffered and unechoed, with
+/// the cursor hidden, for as long as it lives.
+///
+/// Dropping it puts the terminal back, so that happens however the game loop ends,
+/// including when a panic unwinds out of it.
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        terminal::enable_raw_mode()?;
+        // From here on, dropping the guard undoes whatever has been done.
+        let guard = RawMode;
+        execute!(io::stdout(), cursor::Hide)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // Show the cursor again and leave the board on screen above the prompt. There
+        // is nowhere to report a failure from here.
+        let _ = execute!(io::stdout(), cursor::Show, Print("\r\n"));
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the game in the terminal until the player quits, then restores the terminal.
+pub fn run(mut game: SnakeGame) -> io::Result<()> {
+    let result = {
+        let _raw = RawMode::enable()?;
+        play(&mut game)
+    };
+    game.discard_autosave();
+    game.print_summary();
+    result
+}
+
+/// The frame loop: read keys, advance the simulation, redraw.
+fn play(game: &mut SnakeGame) -> io::Result<()> {
+    // Reads block, so they happen on their own thread and are picked up each frame.
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buf = [0u8; 16];
+        while let Ok(n) = stdin.read(&mut buf) {
+            if n == 0 || tx.send(buf[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut out = io::stdout();
+    // Clear the screen.
+    write!(out, "\x1b[2J")?;
+    let mut last_frame = Instant::now();
+    loop {
+        while let Ok(bytes) = rx.try_recv() {
+            for key in parse_keys(&bytes) {
+                match key {
+                    TermKey::Arrow(dir) => game.steer(dir, false),
+                    TermKey::Char(b'q') | TermKey::Char(CTRL_C) => return Ok(()),
+                    TermKey::Char(b'r') if game.game_over => game.restart(),
+                    TermKey::Char(_) => {}
+                }
+            }
+        }
+
+        let now = Instant::now();
+        game.tick((now - last_frame).as_secs_f32());
+        last_frame = now;
+        // There is no audio in the terminal.
+        game.pending_sounds.clear();
+
+        // Redraw from the top-left corner, clearing the rest of each line.
+        let frame = render(game).replace('\n', "\x1b[K\r\n");
+        write!(out, "\x1b[H{}", frame)?;
+        out.flush()?;
+        thread::sleep(FRAME_TIME);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::game;
+
+    #[test]
+    fn the_board_renders_one_character_per_cell() {
+        let mut game = game(4, 3, &["--seed", "1"]);
+        game.snake = vec![Point { x: 1, y: 1 }, Point { x: 0, y: 1 }];
+        game.obstacles = vec![Point { x: 3, y: 0 }];
+        game.food = Some(Point { x: 2, y: 2 });
+        game.food_kind = FoodKind::Normal;
+        game.bonus = Some(Point { x: 0, y: 0 });
+        let status = strings::fill(&game.strings.score, &[("score", "0".to_string())]);
+        assert_eq!(render(&game), format!("$..#\no@..\n..*.\n{}\n", status));
+    }
+
+    #[test]
+    fn a_lost_game_says_so_under_the_board() {
+        let mut game = game(3, 3, &["--seed", "1"]);
+        game.food_kind = FoodKind::Poison;
+        game.game_over = true;
+        let text = render(&game);
+        assert!(text.contains('x'));
+        assert!(text.trim_end().ends_with(&game.strings.game_over_terminal));
+    }
+
+    #[test]
+    fn arrow_sequences_decode_between_plain_keys() {
+        let keys = parse_keys(b"q\x1b[Ar\x1b[D\x1b");
+        assert!(
+            keys == vec![
+                TermKey::Char(b'q'),
+                TermKey::Arrow(Direction::Up),
+                TermKey::Char(b'r'),
+                TermKey::Arrow(Direction::Left),
+                TermKey::Char(ESC),
+            ]
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use ggez::event::{self, EventHandler, KeyCode, KeyMods};
+use ggez::graphics::{self, Color, DrawMode, Mesh, Rect, Text};
+use ggez::{Context, GameResult};
+
+use crate::layout::Layout;
+use crate::replay::Replay;
+use crate::Point;
+
+/// Time between replay steps at normal speed, in seconds.
+const REPLAY_MOVE_PERIOD: f32 = 0.2;
+/// Slowest and fastest playback speeds, as multiples of normal speed.
+const MIN_PLAYBACK_SPEED: f32 = 0.25;
+const MAX_PLAYBACK_SPEED: f32 = 8.0;
+
+/// Plays back a recorded run, with controls to pause, step and change speed.
+///
+/// Space pauses (or, at the end, plays again from the start), the right and left
+/// arrows step one tick forward and back (pausing playback), `+` and `-` double and
+/// halve the speed, and Escape quits.
+pub struct ReplayViewer {
+    pub replay: Replay,
+    // How many steps have been played, and the snake's body after them.
+    pub tick: usize,
+    pub body: Vec<Point>,
+    pub paused: bool,
+    // Playback speed as a multiple of normal speed.
+    pub speed: f32,
+    move_timer: f32,
+}
+
+impl ReplayViewer {
+    /// Starts watching `replay` from its first tick.
+    pub fn new(replay: Replay) -> ReplayViewer {
+        let body = replay.start.clone();
+        ReplayViewer {
+            replay,
+            tick: 0,
+            body,
+            paused: false,
+            speed: 1.0,
+            move_timer: 0.0,
+        }
+    }
+
+    /// Plays the next step, if there is one left, by moving the head on and dropping
+    /// whatever the recorded length leaves behind.
+    pub fn step_forward(&mut self) {
+        if self.tick >= self.replay.steps() {
+            return;
+        }
+        self.body.insert(0, self.replay.heads[self.tick]);
+        self.body.truncate(self.replay.lengths[self.tick] as usize);
+        self.tick += 1;
+    }
+
+    /// Jumps to the state after `tick` steps (or the last step, if there are fewer).
+    ///
+    /// The body is rebuilt from the start of the recording, so this lands on exactly
+    /// the state playing forward would reach.
+    pub fn seek_to_tick(&mut self, tick: usize) {
+        self.tick = tick.min(self.replay.steps());
+        self.body = self.replay.body_at(self.tick);
+    }
+}
+
+impl EventHandler for ReplayViewer {
+    /// Advances playback on a timer, stopping at the end of the recording.
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if self.paused {
+            return Ok(());
+        }
+        self.move_timer += ggez::timer::delta(ctx).as_secs_f32();
+        if self.move_timer > REPLAY_MOVE_PERIOD / self.speed {
+            self.move_timer = 0.0;
+            self.step_forward();
+            if self.tick == self.replay.steps() {
+                self.paused = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the snake, head in a lighter green, with the playback position and speed.
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::from_rgb(0, 0, 0));
+        let layout = Layout::fit(graphics::drawable_size(ctx), self.replay.grid_width, self.replay.grid_height);
+        for (i, &p) in self.body.iter().enumerate() {
+            let color = if i == 0 { Color::from_rgb(160, 255, 160) } else { Color::from_rgb(0, 255, 0) };
+            let rectangle = Mesh::new_rectangle(ctx, DrawMode::fill(), layout.cell_rect(p), color)?;
+            graphics::draw(ctx, &rectangle, (ggez::mint::Point2 { x: 0.0, y: 0.0 },))?;
+        }
+        let status = Text::new(format!(
+            "Tick {}/{}  x{}{}  Score {}",
+            self.tick,
+            self.replay.steps(),
+            self.speed,
+            if self.paused { "  (paused)" } else { "" },
+            self.replay.score,
+        ));
+        let area = layout.grid_rect();
+        let dest = ggez::mint::Point2 { x: area.x + 10.0, y: area.y + 10.0 };
+        graphics::draw(ctx, &status, (dest, Color::from_rgb(255, 255, 255)))?;
+        graphics::present(ctx)
+    }
+
+    /// Handles the playback controls.
+    fn key_down_event(&mut self, ctx: &mut Context, key: KeyCode, _mods: KeyMods, _repeat: bool) {
+        match key {
+            KeyCode::Space if self.tick == self.replay.steps() => {
+                self.seek_to_tick(0);
+                self.paused = false;
+            }
+            KeyCode::Space => self.paused = !self.paused,
+            KeyCode::Right => {
+                self.paused = true;
+                self.step_forward();
+            }
+            KeyCode::Left => {
+                self.paused = true;
+                self.seek_to_tick(self.tick.saturating_sub(1));
+            }
+            KeyCode::Equals | KeyCode::NumpadAdd => {
+                self.speed = (self.speed * 2.0).min(MAX_PLAYBACK_SPEED);
+            }
+            KeyCode::Minus | KeyCode::NumpadSubtract => {
+                self.speed = (self.speed / 2.0).max(MIN_PLAYBACK_SPEED);
+            }
+            KeyCode::Escape => event::quit(ctx),
+            _ => {}
+        }
+    }
+
+    /// Keeps drawing in window pixels when the window is resized.
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        let _ = graphics::set_screen_coordinates(ctx, Rect::new(0.0, 0.0, width, height));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A short run: a two-cell snake heading right, eating once on the second step.
+    fn replay() -> Replay {
+        let mut replay = Replay::new(&[Point { x: 2, y: 2 }, Point { x: 1, y: 2 }], 10, 10);
+        for (x, y, length) in [(3, 2, 2), (4, 2, 3), (4, 3, 3), (4, 4, 3), (3, 4, 3)] {
+            replay.push(Point { x, y }, length);
+        }
+        replay
+    }
+
+    #[test]
+    fn seeking_lands_where_playing_forward_does() {
+        let mut played = ReplayViewer::new(replay());
+        for tick in 0..=5 {
+            let mut sought = ReplayViewer::new(replay());
+            sought.seek_to_tick(tick);
+            assert_eq!(sought.tick, played.tick);
+            assert_eq!(sought.body, played.body, "tick {tick}");
+            played.step_forward();
+        }
+    }
+
+    #[test]
+    fn seeking_and_stepping_stop_at_the_end() {
+        let mut viewer = ReplayViewer::new(replay());
+        viewer.seek_to_tick(50);
+        assert_eq!(viewer.tick, 5);
+        let body = viewer.body.clone();
+        viewer.step_forward();
+        assert_eq!((viewer.tick, &viewer.body), (5, &body));
+        viewer.seek_to_tick(0);
+        assert_eq!(viewer.body, replay().start);
+    }
+}